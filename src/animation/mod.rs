@@ -2,8 +2,8 @@ use std::time::Duration;
 
 use smithay::utils::{Coordinate, Logical, Point, Rectangle, Size};
 
+#[derive(Debug, Clone, Copy)]
 pub enum AnimationType {
-    #[allow(dead_code)]
     Linear,
     EaseInOutQuad,
     OvershootBounce,
@@ -121,4 +121,3 @@ fn process_rec(
         size: size.to_i32_round(),
     }
 }
-
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+#[cfg(feature = "egl")]
+use smithay::backend::renderer::ImportEgl;
+
+use anyhow::Context;
+use smithay::{
+    backend::{
+        allocator::{Fourcc, dmabuf::Dmabuf},
+        egl::{EGLContext, EGLDevice, EGLDisplay},
+        renderer::{
+            Bind, Color32F, ImportDma, ImportMemWl, Offscreen,
+            damage::OutputDamageTracker,
+            gles::{GlesRenderbuffer, GlesRenderer},
+        },
+    },
+    output::{Mode as OutputMode, Subpixel},
+    reexports::{
+        calloop::{
+            LoopHandle,
+            timer::{TimeoutAction, Timer},
+        },
+        wayland_server::DisplayHandle,
+    },
+    utils::{Physical, Size, Transform},
+};
+
+use crate::{
+    manager::{
+        cursor::CursorManager, input::InputManager, output::OutputManager, render::RenderManager,
+        workspace::WorkspaceManager,
+    },
+    state::{GlobalData, State},
+};
+
+const DEFAULT_SIZE: (i32, i32) = (1920, 1080);
+const REFRESH: i32 = 60_000;
+
+/// A backend without any parent display or DRM device. It renders into an
+/// offscreen buffer and ticks frames from a timer, so the compositor can run
+/// in CI containers or over SSH.
+pub struct Headless {
+    renderer: GlesRenderer,
+    buffer: GlesRenderbuffer,
+    damage_tracker: Option<OutputDamageTracker>,
+    size: Size<i32, Physical>,
+}
+
+impl Headless {
+    pub fn new(loop_handle: &LoopHandle<'_, GlobalData>) -> anyhow::Result<Self> {
+        let size = output_size();
+        let mut renderer = create_renderer()?;
+
+        let buffer: GlesRenderbuffer = renderer
+            .create_buffer(
+                Fourcc::Abgr8888,
+                size.to_logical(1).to_buffer(1, Transform::Normal),
+            )
+            .context("Failed to create offscreen buffer")?;
+
+        let duration = Duration::from_micros(1_000_000_000 / REFRESH as u64);
+        loop_handle
+            .insert_source(Timer::from_duration(duration), move |_, _, data| {
+                data.backend.headless().render_output(
+                    &mut data.render_manager,
+                    &data.output_manager,
+                    &data.workspace_manager,
+                    &mut data.cursor_manager,
+                    &data.input_manager,
+                );
+
+                // For each of the windows send the frame callbacks to tell them to draw next frame.
+                data.workspace_manager
+                    .send_frames(data.start_time.elapsed());
+
+                data.workspace_manager.refresh();
+                data.popups.cleanup();
+                let _ = data.display_handle.flush_clients();
+
+                TimeoutAction::ToDuration(duration)
+            })
+            .map_err(|e| anyhow::anyhow!("Failed to insert headless frame timer: {}", e))?;
+
+        Ok(Self {
+            renderer,
+            buffer,
+            damage_tracker: None,
+            size,
+        })
+    }
+
+    pub fn init(
+        &mut self,
+        display_handle: &DisplayHandle,
+        output_manager: &mut OutputManager,
+        render_manager: &RenderManager,
+        state: &mut State,
+    ) {
+        // add virtual output
+        let output = output_manager.add_output(
+            "HEADLESS-1".to_string(),
+            (0, 0).into(),
+            Subpixel::Unknown,
+            "Mondrian".into(),
+            "Headless".into(),
+            (0, 0).into(),
+            true,
+        );
+
+        let mode = OutputMode {
+            size: self.size,
+            refresh: REFRESH,
+        };
+
+        output_manager.change_current_state(&output, Some(mode), None, None, None);
+        output_manager.set_preferred(mode);
+        self.apply_output_config(output_manager);
+
+        self.damage_tracker = Some(OutputDamageTracker::from_output(
+            output_manager.current_output(),
+        ));
+
+        // initial shm and dmabuf
+        state.shm_state.update_formats(self.renderer.shm_formats());
+
+        #[cfg(feature = "egl")]
+        if self.renderer.bind_wl_display(display_handle).is_ok() {
+            info!("EGL hardware-acceleration enabled");
+        };
+
+        let dmabuf_formats = self.renderer.dmabuf_formats();
+        let _dmabuf_global = state
+            .dmabuf_state
+            .create_global::<GlobalData>(display_handle, dmabuf_formats);
+
+        // compile shaders
+        render_manager.compile_shaders(&mut self.renderer);
+    }
+
+    // the buffer keeps its size, only the rest of the config applies
+    pub fn apply_output_config(&mut self, output_manager: &mut OutputManager) {
+        let output = output_manager.current_output().clone();
+        output_manager.apply_config(&output, None, Transform::Normal);
+    }
+
+    pub fn render_output(
+        &mut self,
+        render_manager: &mut RenderManager,
+        output_manager: &OutputManager,
+        workspace_manager: &WorkspaceManager,
+        cursor_manager: &mut CursorManager,
+        input_manager: &InputManager,
+    ) {
+        let Some(damage_tracker) = self.damage_tracker.as_mut() else {
+            return;
+        };
+
+        let elements = render_manager.get_render_elements(
+            &mut self.renderer,
+            output_manager.current_output(),
+            output_manager,
+            workspace_manager,
+            cursor_manager,
+            input_manager,
+        );
+
+        let mut framebuffer = match self.renderer.bind(&mut self.buffer) {
+            Ok(framebuffer) => framebuffer,
+            Err(err) => {
+                warn!("Headless: Failed to bind offscreen buffer: {:?}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = damage_tracker.render_output(
+            &mut self.renderer,
+            &mut framebuffer,
+            0,
+            &elements,
+            Color32F::from([0.0; 4]),
+        ) {
+            warn!("Headless: Failed to render frame: {:?}", err);
+        }
+    }
+
+    pub fn dmabuf_imported(&mut self, dmabuf: &Dmabuf) -> bool {
+        match self.renderer.import_dmabuf(dmabuf, None) {
+            Ok(_) => true,
+            Err(err) => {
+                warn!("error importing dmabuf: {:?}", err);
+                false
+            }
+        }
+    }
+}
+
+/// Pick the first EGL device that can give us a GLES context. With mesa this
+/// includes the `llvmpipe` software device, so no GPU is required.
+fn create_renderer() -> anyhow::Result<GlesRenderer> {
+    let devices = EGLDevice::enumerate().context("Failed to enumerate EGL devices")?;
+
+    for device in devices {
+        let display = match unsafe { EGLDisplay::new(device) } {
+            Ok(display) => display,
+            Err(err) => {
+                warn!(?err, "Failed to create EGL display, trying next device");
+                continue;
+            }
+        };
+
+        let context = match EGLContext::new(&display) {
+            Ok(context) => context,
+            Err(err) => {
+                warn!(?err, "Failed to create EGL context, trying next device");
+                continue;
+            }
+        };
+
+        match unsafe { GlesRenderer::new(context) } {
+            Ok(renderer) => return Ok(renderer),
+            Err(err) => {
+                warn!(?err, "Failed to create gles renderer, trying next device");
+            }
+        }
+    }
+
+    anyhow::bail!("No EGL device is usable for headless rendering")
+}
+
+/// Read the virtual output size from `MONDRIAN_HEADLESS_SIZE`, e.g. `1280x720`.
+fn output_size() -> Size<i32, Physical> {
+    std::env::var("MONDRIAN_HEADLESS_SIZE")
+        .ok()
+        .and_then(|size| {
+            let (w, h) = size.split_once('x')?;
+            Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+        })
+        .unwrap_or(DEFAULT_SIZE)
+        .into()
+}
@@ -1,14 +1,17 @@
+pub mod headless;
 pub mod tty;
 pub mod winit;
 
 use smithay::{
     backend::allocator::dmabuf::Dmabuf,
+    output::{Mode, Output},
     reexports::{
         calloop::LoopHandle,
         wayland_server::{DisplayHandle, protocol::wl_surface::WlSurface},
     },
 };
 
+use headless::Headless;
 use tty::Tty;
 use winit::Winit;
 
@@ -18,29 +21,76 @@ use crate::{
     utils::errors::AnyHowErr,
 };
 
-pub enum Backend {
-    Tty(Tty),
-    Winit(Winit),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendType {
+    Tty,
+    Winit,
+    Headless,
 }
 
-impl Backend {
-    pub fn new(loop_handle: &LoopHandle<'_, GlobalData>) -> anyhow::Result<Self> {
-        // judge the backend type, create base config
+impl BackendType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "tty" => Some(BackendType::Tty),
+            "winit" => Some(BackendType::Winit),
+            "headless" => Some(BackendType::Headless),
+            _ => None,
+        }
+    }
+
+    /// Use `MONDRIAN_BACKEND` if it is set, otherwise judge by the parent display.
+    pub fn from_env() -> Self {
+        if let Ok(name) = std::env::var("MONDRIAN_BACKEND") {
+            match Self::from_name(&name) {
+                Some(backend_type) => return backend_type,
+                None => warn!("Unknown MONDRIAN_BACKEND: {}, fallback to auto", name),
+            }
+        }
+
         let has_display = std::env::var_os("WAYLAND_DISPLAY").is_some()
             || std::env::var_os("WAYLAND_SOCKET").is_some()
             || std::env::var_os("DISPLAY").is_some();
 
-        // initial backend
         if has_display {
-            info!("Using winit backend");
-
-            let winit = Winit::new(loop_handle).anyhow_err("Failed to create winit backend")?;
-            Ok(Backend::Winit(winit))
+            BackendType::Winit
         } else {
-            info!("Using tty backend");
+            BackendType::Tty
+        }
+    }
+}
+
+pub enum Backend {
+    Tty(Tty),
+    Winit(Winit),
+    Headless(Headless),
+}
+
+impl Backend {
+    pub fn new(
+        backend_type: BackendType,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> anyhow::Result<Self> {
+        // initial backend
+        match backend_type {
+            BackendType::Winit => {
+                info!("Using winit backend");
+
+                let winit = Winit::new(loop_handle).anyhow_err("Failed to create winit backend")?;
+                Ok(Backend::Winit(winit))
+            }
+            BackendType::Tty => {
+                info!("Using tty backend");
 
-            let tty = Tty::new(loop_handle).anyhow_err("Failed to create tty backend")?;
-            Ok(Backend::Tty(tty))
+                let tty = Tty::new(loop_handle).anyhow_err("Failed to create tty backend")?;
+                Ok(Backend::Tty(tty))
+            }
+            BackendType::Headless => {
+                info!("Using headless backend");
+
+                let headless =
+                    Headless::new(loop_handle).anyhow_err("Failed to create headless backend")?;
+                Ok(Backend::Headless(headless))
+            }
         }
     }
 
@@ -60,6 +110,14 @@ impl Backend {
         }
     }
 
+    pub fn headless(&mut self) -> &mut Headless {
+        if let Self::Headless(v) = self {
+            v
+        } else {
+            panic!("backend is not Headless");
+        }
+    }
+
     pub fn init(
         &mut self,
         loop_handle: &LoopHandle<'_, GlobalData>,
@@ -79,16 +137,79 @@ impl Backend {
             Backend::Winit(winit) => {
                 winit.init(display_handle, output_manager, render_manager, state)
             }
+            Backend::Headless(headless) => {
+                headless.init(display_handle, output_manager, render_manager, state)
+            }
+        }
+    }
+
+    pub fn apply_output_configs(&mut self, output_manager: &mut OutputManager) {
+        match self {
+            Backend::Tty(tty) => tty.apply_output_configs(output_manager),
+            Backend::Winit(winit) => winit.apply_output_config(output_manager),
+            Backend::Headless(headless) => headless.apply_output_config(output_manager),
+        }
+    }
+
+    // only real outputs can be turned off and on again
+    pub fn supports_disable(&self) -> bool {
+        matches!(self, Backend::Tty(_))
+    }
+
+    pub fn supports_mode(&self, output: &Output, mode: Mode) -> bool {
+        match self {
+            Backend::Tty(tty) => tty.supports_mode(output, mode),
+            // the host window takes any size
+            Backend::Winit(_) => true,
+            Backend::Headless(_) => output.current_mode().is_some_and(|m| m.size == mode.size),
+        }
+    }
+
+    /// Switch to `mode`, returning the mode now in use if it changed at once.
+    pub fn set_output_mode(&mut self, output: &Output, mode: Mode) -> anyhow::Result<Option<Mode>> {
+        match self {
+            Backend::Tty(tty) => tty.set_output_mode(output, mode).map(Some),
+            Backend::Winit(winit) => {
+                winit.request_size(mode.size);
+                Ok(None)
+            }
+            Backend::Headless(_) => Ok(None),
+        }
+    }
+
+    /// Turn a disabled output back on, in `mode` or the one from its config.
+    pub fn enable_output(
+        &mut self,
+        output: &Output,
+        mode: Option<Mode>,
+        output_manager: &mut OutputManager,
+    ) -> anyhow::Result<()> {
+        match self {
+            Backend::Tty(tty) => tty.enable_output(output, mode, output_manager),
+            Backend::Winit(_) | Backend::Headless(_) => {
+                anyhow::bail!("Output {} cannot be enabled", output.name())
+            }
+        }
+    }
+
+    pub fn disable_output(
+        &mut self,
+        output: &Output,
+        output_manager: &mut OutputManager,
+    ) -> anyhow::Result<()> {
+        match self {
+            Backend::Tty(tty) => tty.disable_output(output, output_manager),
+            Backend::Winit(_) | Backend::Headless(_) => {
+                anyhow::bail!("Output {} cannot be disabled", output.name())
+            }
         }
     }
 
     pub fn seat_name(&self) -> String {
-        if let Self::Winit(_) = self {
-            String::from("winit")
-        } else if let Self::Tty(v) = self {
-            v.seat_name.clone()
-        } else {
-            panic!("Failed to get seat name");
+        match self {
+            Backend::Tty(tty) => tty.seat_name.clone(),
+            Backend::Winit(_) => String::from("winit"),
+            Backend::Headless(_) => String::from("headless"),
         }
     }
 
@@ -96,13 +217,14 @@ impl Backend {
         match self {
             Backend::Tty(tty) => tty.dmabuf_imported(dmabuf),
             Backend::Winit(winit) => winit.dmabuf_imported(dmabuf),
+            Backend::Headless(headless) => headless.dmabuf_imported(dmabuf),
         }
     }
 
     pub fn early_import(&mut self, surface: &WlSurface) {
         match self {
             Backend::Tty(tty) => tty.early_import(surface),
-            Backend::Winit(_) => {}
+            Backend::Winit(_) | Backend::Headless(_) => {}
         }
     }
 }
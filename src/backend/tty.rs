@@ -50,12 +50,12 @@ use smithay::{
             LoopHandle,
             timer::{TimeoutAction, Timer},
         },
-        drm::control::{Device as _, connector, crtc},
+        drm::control::{Device as _, Mode as DrmMode, connector, crtc},
         input::Libinput,
         rustix::fs::OFlags,
         wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
     },
-    utils::{Clock, DeviceFd, Monotonic},
+    utils::{Clock, DeviceFd, Monotonic, Transform},
     wayland::{drm_lease::DrmLease, presentation::Refresh},
 };
 use smithay::{output::Output, reexports::drm::control::ModeTypeFlags};
@@ -70,6 +70,7 @@ use std::{
     time::Duration,
 };
 
+use crate::config::OutputModeConfig;
 use crate::manager::input::InputManager;
 use crate::manager::render::RenderManager;
 use crate::manager::{cursor::CursorManager, output::OutputManager, workspace::WorkspaceManager};
@@ -297,15 +298,15 @@ impl Tty {
                         ) {
                             warn!("erro adding device: {:?}", err);
                         }
+                        data.outputs_changed();
                     }
                 }
                 UdevEvent::Changed { device_id } => {
                     if let Ok(node) = DrmNode::from_dev_id(device_id) {
-                        data.backend.tty().device_changed(
-                            node,
-                            &mut data.output_manager,
-                            &data.display_handle,
-                        )
+                        data.backend
+                            .tty()
+                            .device_changed(node, &mut data.output_manager);
+                        data.outputs_changed();
                     }
                 }
                 UdevEvent::Removed { device_id } => {
@@ -317,6 +318,7 @@ impl Tty {
                             &mut data.output_manager,
                             &mut data.state,
                         );
+                        data.outputs_changed();
                     }
                 }
             })
@@ -358,14 +360,8 @@ impl Tty {
                     );
 
                     // For each of the windows send the frame callbacks to tell them to draw next frame.
-                    data.workspace_manager.elements().for_each(|window| {
-                        window.send_frame(
-                            data.output_manager.current_output(),
-                            data.start_time.elapsed(),
-                            Some(Duration::ZERO),
-                            |_, _| Some(data.output_manager.current_output().clone()),
-                        )
-                    });
+                    data.workspace_manager
+                        .send_frames(data.start_time.elapsed());
 
                     data.workspace_manager.refresh();
                     data.popups.cleanup();
@@ -414,12 +410,7 @@ impl Tty {
                 match event {
                     DrmEvent::VBlank(crtc) => {
                         let meta = meta.expect("VBlank events must have metadata");
-                        data.backend.tty().on_vblank(
-                            &crtc,
-                            &meta,
-                            data.output_manager.current_output(),
-                            &data.clock,
-                        );
+                        data.backend.tty().on_vblank(&crtc, &meta, &data.clock);
                     }
                     DrmEvent::Error(error) => warn!("DRM Vblank error: {error}"),
                 };
@@ -501,17 +492,12 @@ impl Tty {
             },
         );
 
-        self.device_changed(node, output_manager, display_handle);
+        self.device_changed(node, output_manager);
 
         Ok(())
     }
 
-    pub fn device_changed(
-        &mut self,
-        node: DrmNode,
-        output_manager: &mut OutputManager,
-        display_handle: &DisplayHandle,
-    ) {
+    pub fn device_changed(&mut self, node: DrmNode, output_manager: &mut OutputManager) {
         info!("device changed: {:?}", node);
         let device: &mut OutputDevice = if let Some(device) = self.devices.get_mut(&node) {
             device
@@ -534,7 +520,7 @@ impl Tty {
                     connector,
                     crtc: Some(crtc),
                 } => {
-                    self.connector_connected(node, connector, crtc, output_manager, display_handle);
+                    self.connector_connected(node, connector, crtc, output_manager);
                 }
                 DrmScanEvent::Disconnected {
                     connector,
@@ -619,7 +605,6 @@ impl Tty {
         &mut self,
         crtc: &crtc::Handle,
         meta: &DrmEventMetadata,
-        output: &Output,
         clock: &Clock<Monotonic>,
     ) {
         for device in self.devices.values_mut() {
@@ -653,7 +638,8 @@ impl Tty {
                 .frame_submitted()
                 .map_err(Into::<SwapBuffersError>::into);
 
-            let Some(frame_duration) = output
+            let Some(frame_duration) = surface
+                .output
                 .current_mode()
                 .map(|mode| Duration::from_secs_f64(1_000f64 / mode.refresh as f64))
             else {
@@ -709,7 +695,6 @@ impl Tty {
         connector: connector::Info,
         crtc: crtc::Handle,
         output_manager: &mut OutputManager,
-        display_handle: &DisplayHandle,
     ) {
         let device = if let Some(device) = self.devices.get_mut(&node) {
             device
@@ -717,11 +702,7 @@ impl Tty {
             return;
         };
 
-        let output_name = format!(
-            "{}-{}",
-            connector.interface().as_str(),
-            connector.interface_id()
-        );
+        let output_name = connector_name(&connector);
         info!(?crtc, "Trying to setup connector {}", output_name);
 
         let drm_device = &device.drm;
@@ -761,173 +742,207 @@ impl Tty {
                 .and_then(|info| info.model())
                 .unwrap_or_else(|| "Unknown".into());
 
-            let mode_id = connector
-                .modes()
-                .iter()
-                .position(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
-                .unwrap_or(0);
-
-            let drm_mode = connector.modes()[mode_id];
-            let wl_mode = WlMode::from(drm_mode);
-
-            let (phys_w, phys_h) = connector.size().unwrap_or((0, 0));
-            info!("Connector {} size: {}x{}", output_name, phys_w, phys_h);
-
-            output_manager.add_output(
-                output_name,
-                (phys_w as i32, phys_h as i32).into(),
-                connector.subpixel().into(),
-                make,
-                model,
-                (0, 0).into(),
-                true,
-                display_handle,
-            );
-
-            output_manager.change_current_state(
-                Some(wl_mode),
-                None,
-                None,
-                Some((0, 0).into()), // TODO: multiple outputs
-            );
-            output_manager.set_preferred(wl_mode);
-
-            let driver = match drm_device.get_driver() {
-                Ok(driver) => driver,
-                Err(err) => {
-                    warn!("error getting driver: {:?}", err);
-                    return;
+            let config = output_manager
+                .configs
+                .output_config(&output_name, &make, &model)
+                .cloned();
+            if config.as_ref().is_some_and(|config| !config.enable) {
+                info!("Connector {} is disabled in the config", output_name);
+                let (phys_w, phys_h) = connector.size().unwrap_or((0, 0));
+                let output = output_manager.add_disabled_output(
+                    output_name,
+                    (phys_w as i32, phys_h as i32).into(),
+                    connector.subpixel().into(),
+                    make,
+                    model,
+                );
+                for mode in connector.modes() {
+                    output.add_mode(WlMode::from(*mode));
                 }
-            };
-
-            let mut planes = match drm_device.planes(&crtc) {
-                Ok(planes) => planes,
-                Err(err) => {
-                    warn!("error getting planes: {:?}", err);
-                    return;
+                if let Some(preferred) = preferred_mode(connector.modes()) {
+                    output.set_preferred(WlMode::from(preferred));
                 }
+                return;
+            }
+
+            let Some(drm_mode) =
+                pick_mode(connector.modes(), config.and_then(|config| config.mode))
+            else {
+                warn!("Connector {} has no modes", output_name);
+                return;
             };
 
-            // Using an overlay plane on a nvidia card breaks
-            if driver
-                .name()
-                .to_string_lossy()
-                .to_lowercase()
-                .contains("nvidia")
-                || driver
-                    .description()
-                    .to_string_lossy()
-                    .to_lowercase()
-                    .contains("nvidia")
+            if let Err(err) =
+                self.enable_connector(node, connector, crtc, drm_mode, make, model, output_manager)
             {
-                info!("Nvidia driver detected, disabling overlay planes");
-                planes.overlay = vec![];
+                warn!("Failed to setup connector {}: {:?}", output_name, err);
             }
+        }
+    }
 
-            // TODO: error handling
-            let drm_surface = match device
-                .drm
-                .create_surface(crtc, drm_mode, &[connector.handle()])
-            {
-                Ok(surface) => surface,
-                Err(err) => {
-                    warn!("error creating surface: {:?}", err);
-                    return;
-                }
-            };
+    // a failed setup leaves the output disabled
+    fn enable_connector(
+        &mut self,
+        node: DrmNode,
+        connector: connector::Info,
+        crtc: crtc::Handle,
+        drm_mode: DrmMode,
+        make: String,
+        model: String,
+        output_manager: &mut OutputManager,
+    ) -> anyhow::Result<Output> {
+        let wl_mode = WlMode::from(drm_mode);
+        let preferred = preferred_mode(connector.modes()).unwrap_or(drm_mode);
+
+        let output_name = connector_name(&connector);
+        let (phys_w, phys_h) = connector.size().unwrap_or((0, 0));
+        info!("Connector {} size: {}x{}", output_name, phys_w, phys_h);
+
+        let location = output_manager.next_location();
+        let output = output_manager.add_output(
+            output_name,
+            (phys_w as i32, phys_h as i32).into(),
+            connector.subpixel().into(),
+            make,
+            model,
+            location,
+            true,
+        );
 
-            let gbm_flags = GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT;
-            let allocator = GbmAllocator::new(device.gbm.clone(), gbm_flags);
+        // advertised to output management clients
+        for mode in connector.modes() {
+            output.add_mode(WlMode::from(*mode));
+        }
 
-            let mut renderer = self
-                .gpu_manager
-                .single_renderer(&device.render_node)
-                .unwrap();
-            let egl_context = renderer.as_gles_renderer().egl_context();
-            let render_formats = egl_context.dmabuf_render_formats();
-
-            // Filter out the CCS modifiers as they have increased bandwidth, causing some monitor
-            // configurations to stop working.
-            //
-            // The invalid modifier attempt below should make this unnecessary in some cases, but it
-            // would still be a bad idea to remove this until Smithay has some kind of full-device
-            // modesetting test that is able to "downgrade" existing connector modifiers to get enough
-            // bandwidth for a newly connected one.
-            let render_formats = render_formats
-                .iter()
-                .copied()
-                .filter(|format| {
-                    !matches!(
-                        format.modifier,
-                        Modifier::I915_y_tiled_ccs
-                    // I915_FORMAT_MOD_Yf_TILED_CCS
-                    | Modifier::Unrecognized(0x100000000000005)
-                    | Modifier::I915_y_tiled_gen12_rc_ccs
-                    | Modifier::I915_y_tiled_gen12_mc_ccs
-                    // I915_FORMAT_MOD_Y_TILED_GEN12_RC_CCS_CC
-                    | Modifier::Unrecognized(0x100000000000008)
-                    // I915_FORMAT_MOD_4_TILED_DG2_RC_CCS
-                    | Modifier::Unrecognized(0x10000000000000a)
-                    // I915_FORMAT_MOD_4_TILED_DG2_MC_CCS
-                    | Modifier::Unrecognized(0x10000000000000b)
-                    // I915_FORMAT_MOD_4_TILED_DG2_RC_CCS_CC
-                    | Modifier::Unrecognized(0x10000000000000c)
-                    )
-                })
-                .collect::<FormatSet>();
-
-            let compositor = match DrmCompositor::new(
-                OutputModeSource::Auto(output_manager.current_output().clone()),
-                drm_surface,
-                None,
-                allocator.clone(),
-                device.gbm.clone(),
-                SUPPORTED_COLOR_FORMATS,
-                render_formats,
-                device.drm.cursor_size(),
-                Some(device.gbm.clone()),
-            ) {
-                Ok(compositor) => compositor,
-                Err(err) => {
-                    warn!("error creating compositor: {:?}", err);
-                    return;
+        // a configured position wins over the automatic one
+        output_manager.change_current_state(&output, None, None, None, Some(location));
+        output_manager.apply_config(&output, Some(wl_mode), Transform::Normal);
+        output.set_preferred(WlMode::from(preferred));
+
+        match self.create_surface(node, &connector, crtc, drm_mode, &output) {
+            Ok(surface) => {
+                if let Some(device) = self.devices.get_mut(&node) {
+                    device.surfaces.insert(crtc, surface);
                 }
-            };
+                Ok(output)
+            }
+            Err(err) => {
+                output_manager.disable_output(&output);
+                Err(err)
+            }
+        }
+    }
 
-            let mut surface = Surface {
-                output: output_manager.current_output().clone(),
-                device_id: node,
-                render_node: device.render_node,
-                compositor,
-                dmabuf_feedback: None,
-            };
+    fn create_surface(
+        &mut self,
+        node: DrmNode,
+        connector: &connector::Info,
+        crtc: crtc::Handle,
+        drm_mode: DrmMode,
+        output: &Output,
+    ) -> anyhow::Result<Surface> {
+        let device = self.devices.get_mut(&node).context("unknown device")?;
+        let drm_device = &device.drm;
 
-            match self.gpu_manager.single_renderer(&self.primary_render_node) {
-                Ok(primary_renderer) => {
-                    let primary_formats = primary_renderer.dmabuf_formats();
+        let driver = drm_device.get_driver().context("error getting driver")?;
 
-                    match surface_dmabuf_feedback(
-                        &surface,
-                        primary_formats,
-                        self.primary_render_node,
-                    ) {
-                        Ok(dmabuf_feedback) => {
-                            surface.dmabuf_feedback = Some(dmabuf_feedback);
-                        }
-                        Err(err) => {
-                            warn!("error creating dmabuf feedback: {:?}", err);
-                            return;
-                        }
-                    }
-                }
-                Err(err) => {
-                    warn!("error getting renderer for primary GPU: {:?}", err);
-                    return;
-                }
-            }
+        let mut planes = drm_device
+            .planes(&crtc)
+            .map_err(|err| anyhow::anyhow!("error getting planes: {:?}", err))?;
 
-            device.surfaces.insert(crtc, surface);
+        // Using an overlay plane on a nvidia card breaks
+        if driver
+            .name()
+            .to_string_lossy()
+            .to_lowercase()
+            .contains("nvidia")
+            || driver
+                .description()
+                .to_string_lossy()
+                .to_lowercase()
+                .contains("nvidia")
+        {
+            info!("Nvidia driver detected, disabling overlay planes");
+            planes.overlay = vec![];
         }
+
+        let drm_surface = device
+            .drm
+            .create_surface(crtc, drm_mode, &[connector.handle()])
+            .map_err(|err| anyhow::anyhow!("error creating surface: {:?}", err))?;
+
+        let gbm_flags = GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT;
+        let allocator = GbmAllocator::new(device.gbm.clone(), gbm_flags);
+
+        let mut renderer = self
+            .gpu_manager
+            .single_renderer(&device.render_node)
+            .unwrap();
+        let egl_context = renderer.as_gles_renderer().egl_context();
+        let render_formats = egl_context.dmabuf_render_formats();
+
+        // Filter out the CCS modifiers as they have increased bandwidth, causing some monitor
+        // configurations to stop working.
+        //
+        // The invalid modifier attempt below should make this unnecessary in some cases, but it
+        // would still be a bad idea to remove this until Smithay has some kind of full-device
+        // modesetting test that is able to "downgrade" existing connector modifiers to get enough
+        // bandwidth for a newly connected one.
+        let render_formats = render_formats
+            .iter()
+            .copied()
+            .filter(|format| {
+                !matches!(
+                    format.modifier,
+                    Modifier::I915_y_tiled_ccs
+                // I915_FORMAT_MOD_Yf_TILED_CCS
+                | Modifier::Unrecognized(0x100000000000005)
+                | Modifier::I915_y_tiled_gen12_rc_ccs
+                | Modifier::I915_y_tiled_gen12_mc_ccs
+                // I915_FORMAT_MOD_Y_TILED_GEN12_RC_CCS_CC
+                | Modifier::Unrecognized(0x100000000000008)
+                // I915_FORMAT_MOD_4_TILED_DG2_RC_CCS
+                | Modifier::Unrecognized(0x10000000000000a)
+                // I915_FORMAT_MOD_4_TILED_DG2_MC_CCS
+                | Modifier::Unrecognized(0x10000000000000b)
+                // I915_FORMAT_MOD_4_TILED_DG2_RC_CCS_CC
+                | Modifier::Unrecognized(0x10000000000000c)
+                )
+            })
+            .collect::<FormatSet>();
+
+        let compositor = DrmCompositor::new(
+            OutputModeSource::Auto(output.clone()),
+            drm_surface,
+            None,
+            allocator.clone(),
+            device.gbm.clone(),
+            SUPPORTED_COLOR_FORMATS,
+            render_formats,
+            device.drm.cursor_size(),
+            Some(device.gbm.clone()),
+        )
+        .map_err(|err| anyhow::anyhow!("error creating compositor: {:?}", err))?;
+
+        let mut surface = Surface {
+            output: output.clone(),
+            device_id: node,
+            render_node: device.render_node,
+            compositor,
+            dmabuf_feedback: None,
+        };
+
+        let primary_formats = self
+            .gpu_manager
+            .single_renderer(&self.primary_render_node)
+            .map_err(|err| anyhow::anyhow!("error getting renderer for primary GPU: {:?}", err))?
+            .dmabuf_formats();
+        surface.dmabuf_feedback = Some(
+            surface_dmabuf_feedback(&surface, primary_formats, self.primary_render_node)
+                .context("error creating dmabuf feedback")?,
+        );
+
+        Ok(surface)
     }
 
     pub fn connector_disconnected(
@@ -953,17 +968,185 @@ impl Tty {
             info!("leasing connector");
             device.non_desktop_connectors.remove(&(handle, value));
         } else {
-            let surface = match device.surfaces.remove(&crtc) {
-                Some(surface) => surface,
-                None => {
-                    warn!("Failed to remove surface: {:?}", crtc);
-                    return;
+            match device.surfaces.remove(&crtc) {
+                Some(surface) => output_manager.remove_output(&surface.output),
+                // turned off, only known to output management
+                None => output_manager.remove_disabled_output(&connector_name(&connector)),
+            }
+        }
+    }
+
+    // the output stays known, so it can be enabled again
+    fn disable_connector(
+        &mut self,
+        node: DrmNode,
+        crtc: crtc::Handle,
+        output_manager: &mut OutputManager,
+    ) {
+        let Some(surface) = self
+            .devices
+            .get_mut(&node)
+            .and_then(|device| device.surfaces.remove(&crtc))
+        else {
+            warn!("Failed to remove surface: {:?}", crtc);
+            return;
+        };
+        output_manager.disable_output(&surface.output);
+    }
+
+    /// Bring the connected outputs in line with a reloaded config, enabling,
+    /// disabling and switching modes as needed.
+    pub fn apply_output_configs(&mut self, output_manager: &mut OutputManager) {
+        let connectors: Vec<_> = self
+            .devices
+            .iter()
+            .flat_map(|(node, device)| {
+                device
+                    .drm_scanner
+                    .crtcs()
+                    .map(move |(info, crtc)| (*node, info.clone(), crtc))
+            })
+            .collect();
+
+        for (node, connector, crtc) in connectors {
+            let Some(device) = self.devices.get_mut(&node) else {
+                continue;
+            };
+            if device
+                .non_desktop_connectors
+                .iter()
+                .any(|(handle, _)| *handle == connector.handle())
+            {
+                continue;
+            }
+
+            // disabled until now
+            let Some(output) = device.surfaces.get(&crtc).map(|s| s.output.clone()) else {
+                self.connector_connected(node, connector, crtc, output_manager);
+                continue;
+            };
+
+            let config = output_manager.output_config(&output).cloned();
+            if config.as_ref().is_some_and(|config| !config.enable) {
+                if output_manager.outputs().count() == 1 {
+                    warn!("Keeping {} enabled, it is the only output", output.name());
+                } else {
+                    info!("Disabling output {}", output.name());
+                    self.disable_connector(node, crtc, output_manager);
+                    continue;
                 }
+            }
+
+            let Some(drm_mode) = pick_mode(connector.modes(), config.and_then(|c| c.mode)) else {
+                continue;
+            };
+            let wl_mode = WlMode::from(drm_mode);
+            let Some(surface) = self
+                .devices
+                .get_mut(&node)
+                .and_then(|device| device.surfaces.get_mut(&crtc))
+            else {
+                continue;
             };
-            output_manager.remove_output(&surface.output);
+            if output.current_mode() != Some(wl_mode) {
+                if let Err(err) = surface.compositor.use_mode(drm_mode) {
+                    warn!("Failed to set mode on {}: {:?}", output.name(), err);
+                    continue;
+                }
+            }
+
+            output_manager.apply_config(&output, Some(wl_mode), Transform::Normal);
         }
     }
 
+    // by name, disabled outputs have no surface
+    fn output_connector(
+        &self,
+        output: &Output,
+    ) -> Option<(DrmNode, crtc::Handle, connector::Info)> {
+        self.devices.iter().find_map(|(node, device)| {
+            let (info, crtc) = device
+                .drm_scanner
+                .crtcs()
+                .find(|(info, _)| connector_name(info) == output.name())?;
+            Some((*node, crtc, info.clone()))
+        })
+    }
+
+    // a refresh of 0 takes any rate
+    fn find_mode(&self, output: &Output, mode: WlMode) -> Option<DrmMode> {
+        let (_, _, connector) = self.output_connector(output)?;
+        let config = OutputModeConfig {
+            width: mode.size.w,
+            height: mode.size.h,
+            refresh: (mode.refresh > 0).then_some(mode.refresh),
+        };
+
+        matching_mode(connector.modes(), config).filter(|found| {
+            mode.refresh == 0 || (WlMode::from(*found).refresh - mode.refresh).abs() <= 1000
+        })
+    }
+
+    pub fn supports_mode(&self, output: &Output, mode: WlMode) -> bool {
+        self.find_mode(output, mode).is_some()
+    }
+
+    pub fn set_output_mode(&mut self, output: &Output, mode: WlMode) -> anyhow::Result<WlMode> {
+        let drm_mode = self.find_mode(output, mode).context("mode not supported")?;
+        let (node, crtc, _) = self.output_connector(output).context("no connector")?;
+        let surface = self
+            .devices
+            .get_mut(&node)
+            .and_then(|device| device.surfaces.get_mut(&crtc))
+            .context("output is disabled")?;
+
+        surface
+            .compositor
+            .use_mode(drm_mode)
+            .map_err(|err| anyhow::anyhow!("error setting mode: {:?}", err))?;
+        Ok(WlMode::from(drm_mode))
+    }
+
+    pub fn enable_output(
+        &mut self,
+        output: &Output,
+        mode: Option<WlMode>,
+        output_manager: &mut OutputManager,
+    ) -> anyhow::Result<()> {
+        let (node, crtc, connector) = self.output_connector(output).context("no connector")?;
+        let drm_mode = match mode {
+            Some(mode) => self.find_mode(output, mode).context("mode not supported")?,
+            None => {
+                let config = output_manager.output_config(output).and_then(|c| c.mode);
+                pick_mode(connector.modes(), config).context("no modes")?
+            }
+        };
+
+        info!("Enabling output {}", output.name());
+        let properties = output.physical_properties();
+        self.enable_connector(
+            node,
+            connector,
+            crtc,
+            drm_mode,
+            properties.make,
+            properties.model,
+            output_manager,
+        )?;
+        Ok(())
+    }
+
+    pub fn disable_output(
+        &mut self,
+        output: &Output,
+        output_manager: &mut OutputManager,
+    ) -> anyhow::Result<()> {
+        let (node, crtc, _) = self.output_connector(output).context("no connector")?;
+        info!("Disabling output {}", output.name());
+        self.disable_connector(node, crtc, output_manager);
+        Ok(())
+    }
+
     pub fn render_output(
         &mut self,
         render_manager: &mut RenderManager,
@@ -987,8 +1170,11 @@ impl Tty {
                     .single_renderer(&surface.render_node)
                     .unwrap();
 
+                // each crtc shows the workspace of its own output
+                let output = surface.output.clone();
                 let elements = render_manager.get_render_elements(
                     &mut renderer,
+                    &output,
                     output_manager,
                     workspace_manager,
                     cursor_manager,
@@ -1013,11 +1199,12 @@ impl Tty {
                     Ok((rendered, states)) => {
                         if rendered {
                             // need queue_frame to switch buffer
-                            let output_presentation_feedback = take_presentation_feedback(
-                                output_manager.current_output(),
-                                workspace_manager.current_space(),
-                                &states,
-                            );
+                            let spaces = workspace_manager
+                                .visible_workspace(&output)
+                                .map(|workspace| workspace.current_spaces().to_vec())
+                                .unwrap_or_default();
+                            let output_presentation_feedback =
+                                take_presentation_feedback(&output, &spaces, &states);
 
                             // queue_frame will arise vlbank
                             match surface
@@ -1173,24 +1360,78 @@ fn surface_dmabuf_feedback(
     Ok(SurfaceDmabufFeedback { render, scanout })
 }
 
+fn connector_name(connector: &connector::Info) -> String {
+    format!(
+        "{}-{}",
+        connector.interface().as_str(),
+        connector.interface_id()
+    )
+}
+
+fn preferred_mode(modes: &[DrmMode]) -> Option<DrmMode> {
+    modes
+        .iter()
+        .find(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+        .or(modes.first())
+        .copied()
+}
+
+// the size at the closest refresh rate, or the fastest one
+fn matching_mode(modes: &[DrmMode], config: OutputModeConfig) -> Option<DrmMode> {
+    modes
+        .iter()
+        .filter(|mode| {
+            let (w, h) = mode.size();
+            (w as i32, h as i32) == (config.width, config.height)
+        })
+        .min_by_key(|mode| {
+            let refresh = WlMode::from(**mode).refresh;
+            match config.refresh {
+                Some(wanted) => (refresh - wanted).abs(),
+                None => -refresh,
+            }
+        })
+        .copied()
+}
+
+fn pick_mode(modes: &[DrmMode], config: Option<OutputModeConfig>) -> Option<DrmMode> {
+    let Some(config) = config else {
+        return preferred_mode(modes);
+    };
+
+    let picked = matching_mode(modes, config);
+    if picked.is_none() {
+        warn!(
+            "No mode {}x{} available, using the preferred one",
+            config.width, config.height
+        );
+    }
+    picked.or_else(|| preferred_mode(modes))
+}
+
 pub fn take_presentation_feedback(
     output: &Output,
-    space: &Space<Window>,
+    spaces: &[&Space<Window>],
     render_element_states: &RenderElementStates,
 ) -> OutputPresentationFeedback {
     let mut output_presentation_feedback = OutputPresentationFeedback::new(output);
 
-    space.elements().for_each(|window| {
-        if space.outputs_for_element(window).contains(output) {
-            window.take_presentation_feedback(
-                &mut output_presentation_feedback,
-                surface_primary_scanout_output,
-                |surface, _| {
-                    surface_presentation_feedback_flags_from_states(surface, render_element_states)
-                },
-            );
-        }
-    });
+    for space in spaces {
+        space.elements().for_each(|window| {
+            if space.outputs_for_element(window).contains(output) {
+                window.take_presentation_feedback(
+                    &mut output_presentation_feedback,
+                    surface_primary_scanout_output,
+                    |surface, _| {
+                        surface_presentation_feedback_flags_from_states(
+                            surface,
+                            render_element_states,
+                        )
+                    },
+                );
+            }
+        });
+    }
     let map = smithay::desktop::layer_map_for_output(output);
     for layer_surface in map.layers() {
         layer_surface.take_presentation_feedback(
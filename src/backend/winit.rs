@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 #[cfg(feature = "egl")]
 use smithay::backend::renderer::ImportEgl;
 
@@ -11,8 +9,8 @@ use smithay::{
         winit::{self, WinitEvent, WinitGraphicsBackend},
     },
     output::{Mode as OutputMode, Subpixel},
-    reexports::{calloop::LoopHandle, wayland_server::DisplayHandle},
-    utils::{Rectangle, Scale, Transform},
+    reexports::{calloop::LoopHandle, wayland_server::DisplayHandle, winit::dpi::PhysicalSize},
+    utils::{Physical, Rectangle, Size, Transform},
     wayland::dmabuf::DmabufFeedbackBuilder,
 };
 
@@ -37,7 +35,9 @@ impl Winit {
             .insert_source(winit, move |event, _, data| {
                 match event {
                     WinitEvent::Resized { size, .. } => {
+                        let output = data.output_manager.current_output().clone();
                         data.output_manager.change_current_state(
+                            &output,
                             Some(OutputMode {
                                 size,
                                 refresh: 60_000,
@@ -46,13 +46,7 @@ impl Winit {
                             None,
                             None,
                         );
-                        let scale = data.output_manager.current_output().current_scale();
-                        let scale = Scale::from(scale.integer_scale());
-
-                        data.workspace_manager.modify_windows(
-                            Rectangle::from_size(size.to_logical(scale)),
-                            &data.loop_handle,
-                        );
+                        data.outputs_changed();
                     }
                     WinitEvent::Input(event) => {
                         data.process_input_event(event);
@@ -81,14 +75,8 @@ impl Winit {
                         }
 
                         // For each of the windows send the frame callbacks to tell them to draw next frame.
-                        data.workspace_manager.elements().for_each(|window| {
-                            window.send_frame(
-                                data.output_manager.current_output(),
-                                data.start_time.elapsed(),
-                                Some(Duration::ZERO),
-                                |_, _| Some(data.output_manager.current_output().clone()),
-                            )
-                        });
+                        data.workspace_manager
+                            .send_frames(data.start_time.elapsed());
 
                         // Refresh space nuonuo_state and handle certain events like enter/leave for outputs/windows
                         data.workspace_manager.refresh();
@@ -116,7 +104,7 @@ impl Winit {
         state: &mut State,
     ) {
         // add output
-        output_manager.add_output(
+        let output = output_manager.add_output(
             "winit".to_string(),
             (0, 0).into(),
             Subpixel::Unknown,
@@ -124,7 +112,6 @@ impl Winit {
             "Winit".into(),
             (0, 0).into(),
             true,
-            display_handle,
         );
 
         let mode = OutputMode {
@@ -132,13 +119,9 @@ impl Winit {
             refresh: 60_000,
         };
 
-        output_manager.change_current_state(
-            Some(mode),
-            Some(Transform::Flipped180),
-            None,
-            Some((0, 0).into()),
-        );
+        output_manager.change_current_state(&output, Some(mode), None, None, None);
         output_manager.set_preferred(mode);
+        self.apply_output_config(output_manager);
 
         // initial dmabuf
         #[cfg(feature = "egl")]
@@ -187,6 +170,24 @@ impl Winit {
         render_manager.compile_shaders(self.get_renderer());
     }
 
+    /// The configured mode resizes the host window, which then reports the
+    /// new size back as a resize.
+    pub fn apply_output_config(&mut self, output_manager: &mut OutputManager) {
+        let output = output_manager.current_output().clone();
+
+        if let Some(mode) = output_manager.output_config(&output).and_then(|c| c.mode) {
+            self.request_size((mode.width, mode.height).into());
+        }
+
+        // the GL framebuffer is upside down
+        output_manager.apply_config(&output, None, Transform::Flipped180);
+    }
+
+    pub fn request_size(&mut self, size: Size<i32, Physical>) {
+        let size = PhysicalSize::new(size.w as u32, size.h as u32);
+        let _ = self.backend.window().request_inner_size(size);
+    }
+
     pub fn render_output(
         &mut self,
         damage_tracker: &mut OutputDamageTracker,
@@ -199,6 +200,7 @@ impl Winit {
         if let Ok((renderer, mut framebuffer)) = self.backend.bind() {
             let elements = render_manager.get_render_elements(
                 renderer,
+                output_manager.current_output(),
                 output_manager,
                 workspace_manager,
                 cursor_manager,
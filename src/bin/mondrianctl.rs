@@ -0,0 +1,287 @@
+// SPDX-License-Identifier: MIT
+// Copyright (c) 2025 Alvin
+
+// Command line client for the mondrian ipc socket.
+
+#[path = "../ipc/protocol.rs"]
+mod protocol;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+};
+
+use anyhow::{Context, bail};
+
+use protocol::{
+    Event, EventKind, Geometry, OutputInfo, Request, Response, SOCKET_ENV, WindowInfo,
+    WorkspaceInfo,
+};
+
+const USAGE: &str = "\
+usage: mondrianctl [--json] <command>
+
+commands:
+    workspace <name>            switch to a workspace, created when missing
+    action <name>               run an internal function, e.g. invert
+    spawn <command> [args...]   run a program from the compositor
+    layout save <file>          write the focused workspace layout to a file
+    layout load <file>          load a layout file as the workspace template
+    workspaces                  list workspaces
+    windows                     list windows
+    outputs                     list outputs
+    tree                        print the tiled tree of the current workspace
+    subscribe [kinds...]        stream events: window focus workspace title output
+    reload                      reload the configuration
+
+options:
+    --json                      print raw JSON instead of text";
+
+fn main() -> anyhow::Result<()> {
+    let mut json = false;
+    let mut args = Vec::new();
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ => args.push(arg),
+        }
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let request = match args.as_slice() {
+        ["workspace", workspace] => Request::Workspace {
+            workspace: workspace.to_string(),
+        },
+        ["action", action] => Request::Action {
+            action: action.to_string(),
+        },
+        ["spawn", command, rest @ ..] => Request::Spawn {
+            command: command.to_string(),
+            args: rest.iter().map(|s| s.to_string()).collect(),
+        },
+        ["layout", "save", path] => return layout_save(path),
+        ["layout", "load", path] => {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read layout {}", path))?;
+            let tree = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse layout {}", path))?;
+            Request::Layout { tree }
+        }
+        ["workspaces"] => Request::Workspaces,
+        ["windows"] => Request::Windows,
+        ["outputs"] => Request::Outputs,
+        ["tree"] => Request::Tree,
+        ["reload"] => Request::Reload,
+        ["subscribe", kinds @ ..] => {
+            let mut events = Vec::new();
+            for kind in kinds {
+                match EventKind::from_name(kind) {
+                    Some(kind) => events.push(kind),
+                    None => bail!("Unknown event kind: {}", kind),
+                }
+            }
+            return subscribe(events, json);
+        }
+        _ => bail!("{}", USAGE),
+    };
+
+    let response = Connection::open()?.request(&request)?;
+    print_response(response, json)
+}
+
+struct Connection {
+    writer: UnixStream,
+    reader: BufReader<UnixStream>,
+}
+
+impl Connection {
+    fn open() -> anyhow::Result<Self> {
+        let path = std::env::var_os(SOCKET_ENV)
+            .with_context(|| format!("{} is not set, is mondrian running?", SOCKET_ENV))?;
+        let writer = UnixStream::connect(&path)
+            .with_context(|| format!("Failed to connect to {:?}", path))?;
+        let reader = BufReader::new(writer.try_clone()?);
+
+        Ok(Self { writer, reader })
+    }
+
+    fn request(&mut self, request: &Request) -> anyhow::Result<Response> {
+        let mut bytes = serde_json::to_vec(request)?;
+        bytes.push(b'\n');
+        self.writer.write_all(&bytes)?;
+
+        let line = self
+            .read_line()?
+            .context("Connection closed before a response")?;
+        serde_json::from_str(&line).context("Invalid response")
+    }
+
+    fn read_line(&mut self) -> anyhow::Result<Option<String>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+}
+
+fn layout_save(path: &str) -> anyhow::Result<()> {
+    match Connection::open()?.request(&Request::Tree)? {
+        Response::Tree { tree: Some(tree) } => {
+            let content = serde_json::to_string_pretty(&tree)?;
+            std::fs::write(path, content)
+                .with_context(|| format!("Failed to write layout {}", path))?;
+            Ok(())
+        }
+        Response::Tree { tree: None } => bail!("No tiled layout to save"),
+        Response::Error { message } => bail!("{}", message),
+        response => bail!("Unexpected response: {:?}", response),
+    }
+}
+
+fn subscribe(events: Vec<EventKind>, json: bool) -> anyhow::Result<()> {
+    let mut connection = Connection::open()?;
+
+    match connection.request(&Request::Subscribe { events })? {
+        Response::Ok => {}
+        Response::Error { message } => bail!("{}", message),
+        response => bail!("Unexpected response: {:?}", response),
+    }
+
+    let mut stdout = std::io::stdout();
+    while let Some(line) = connection.read_line()? {
+        if json {
+            write!(stdout, "{}", line)?;
+        } else {
+            let event: Event = serde_json::from_str(&line).context("Invalid event")?;
+            writeln!(stdout, "{}", format_event(&event))?;
+        }
+        // status bars read us through a pipe
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+fn print_response(response: Response, json: bool) -> anyhow::Result<()> {
+    if let Response::Error { message } = &response {
+        bail!("{}", message);
+    }
+
+    if json {
+        let value = match response {
+            Response::Workspaces { workspaces } => serde_json::to_value(workspaces)?,
+            Response::Windows { windows } => serde_json::to_value(windows)?,
+            Response::Outputs { outputs } => serde_json::to_value(outputs)?,
+            Response::Tree { tree } => serde_json::to_value(tree)?,
+            response => serde_json::to_value(response)?,
+        };
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    match response {
+        Response::Workspaces { workspaces } => workspaces.iter().for_each(print_workspace),
+        Response::Windows { windows } => windows.iter().for_each(print_window),
+        Response::Outputs { outputs } => outputs.iter().for_each(print_output),
+        Response::Tree { tree: Some(tree) } => {
+            println!("{}", serde_json::to_string_pretty(&tree)?)
+        }
+        Response::Tree { tree: None } => println!("empty"),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn marker(active: bool) -> &'static str {
+    if active { "*" } else { " " }
+}
+
+fn format_geometry(geometry: &Option<Geometry>) -> String {
+    match geometry {
+        Some(g) => format!("{}x{} at {},{}", g.width, g.height, g.x, g.y),
+        None => "-".to_string(),
+    }
+}
+
+fn print_workspace(workspace: &WorkspaceInfo) {
+    println!(
+        "{} {}\t{}\t{} windows",
+        marker(workspace.active),
+        workspace.name,
+        workspace.scheme,
+        workspace.windows
+    );
+}
+
+fn print_window(window: &WindowInfo) {
+    println!(
+        "{} {}\t{}\t{:?}\tworkspace {}\t{}{}",
+        marker(window.focused),
+        window.id,
+        window.app_id.as_deref().unwrap_or("-"),
+        window.title.as_deref().unwrap_or(""),
+        window
+            .workspace
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        format_geometry(&window.geometry),
+        if window.floating { "\tfloating" } else { "" }
+    );
+}
+
+fn print_output(output: &OutputInfo) {
+    let refresh = output
+        .refresh
+        .map(|mhz| format!("{:.3} Hz", mhz as f64 / 1000.0))
+        .unwrap_or_else(|| "-".to_string());
+
+    println!(
+        "{} {}\t{} {}\t{}\t{}\tscale {}",
+        marker(output.active),
+        output.name,
+        output.make,
+        output.model,
+        format_geometry(&output.geometry),
+        refresh,
+        output.scale
+    );
+}
+
+fn format_event(event: &Event) -> String {
+    match event {
+        Event::WindowAdded { id, workspace } => {
+            format!("window added: {} on workspace {}", id, workspace)
+        }
+        Event::WindowRemoved { id, .. } => format!("window removed: {}", id),
+        Event::WindowMoved { id, workspace, .. } => {
+            format!("window moved: {} to workspace {}", id, workspace)
+        }
+        Event::FocusChanged {
+            workspace,
+            window: Some(id),
+        } => format!("focus: window {} on workspace {}", id, workspace),
+        Event::FocusChanged {
+            workspace,
+            window: None,
+        } => format!("focus: none on workspace {}", workspace),
+        Event::WorkspaceActivated { name, .. } => format!("workspace: {}", name),
+        Event::TitleChanged {
+            id, title, app_id, ..
+        } => format!(
+            "title: window {} {} {:?}",
+            id,
+            app_id.as_deref().unwrap_or("-"),
+            title.as_deref().unwrap_or("")
+        ),
+        Event::OutputAdded { name } => format!("output added: {}", name),
+        Event::OutputRemoved { name } => format!("output removed: {}", name),
+    }
+}
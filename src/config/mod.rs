@@ -1,13 +1,98 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
-use regex::Regex;
+use smithay::utils::Transform;
 
-use crate::layout::tiled_tree::TiledScheme;
+use crate::{
+    animation::AnimationType,
+    layout::{
+        Direction,
+        tiled_tree::{MasterStack, TiledScheme},
+    },
+    manager::input::{FunctionEnum, KeyAction},
+};
+
+use parser::{ConfigError, ConfigErrorKind, ConfigErrors, Section, Statement, unquote};
+
+pub mod parser;
+pub mod watcher;
+
+const CONFIG_NAME: &str = "mondrian.conf";
+
+// defaults compiled into the binary, used when no config file exists.
+// `source` inside them resolves against this list by file name.
+const EMBEDDED: [(&str, &str); 2] = [
+    ("mondrian.conf", include_str!("./mondrian.conf")),
+    ("keybindings.conf", include_str!("./keybindings.conf")),
+];
+
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    // None for the embedded defaults
+    pub path: Option<PathBuf>,
+    pub name: String,
+    pub content: String,
+}
+
+// $XDG_CONFIG_HOME/mondrian, or ~/.config/mondrian
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("mondrian"))
+}
+
+// $XDG_STATE_HOME/mondrian, or ~/.local/state/mondrian
+pub fn state_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .map(|dir| dir.join("mondrian"))
+}
+
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneralConfigs {
+    pub cursor_theme: String,
+    pub cursor_size: u8,
+    // move the pointer onto windows focused from the keyboard
+    pub warp_pointer: bool,
+}
+
+impl GeneralConfigs {
+    fn default() -> Self {
+        Self {
+            cursor_theme: "default".to_string(),
+            cursor_size: 24,
+            warp_pointer: false,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct WorkspaceConfigs {
     pub gap: i32,
     pub scheme: TiledScheme,
+    // used by the master-stack scheme
+    pub master: MasterStack,
+    // share of a split moved by one keyboard resize
+    pub resize_step: f64,
+    pub rules: Vec<WorkspaceRule>,
 }
 
 impl WorkspaceConfigs {
@@ -15,56 +100,202 @@ impl WorkspaceConfigs {
         Self {
             gap: 12,
             scheme: TiledScheme::Default,
+            master: MasterStack::default(),
+            resize_step: 0.05,
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&WorkspaceRule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+}
+
+/// Settings of one named workspace, unset fields fall back to `workspace { }`.
+#[derive(Debug, Clone)]
+pub struct WorkspaceRule {
+    pub name: String,
+    pub scheme: Option<TiledScheme>,
+    pub gap: Option<i32>,
+    // output the workspace is created on
+    pub output: Option<String>,
+}
+
+/// Settings of one output, unset fields keep what the backend picked.
+#[derive(Debug, Clone)]
+pub struct OutputConfig {
+    // connector name like HDMI-A-1, or "make model"
+    pub name: String,
+    pub enable: bool,
+    pub mode: Option<OutputModeConfig>,
+    pub position: Option<(i32, i32)>,
+    pub scale: Option<f64>,
+    pub transform: Option<Transform>,
+}
+
+impl OutputConfig {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            enable: true,
+            mode: None,
+            position: None,
+            scale: None,
+            transform: None,
+        }
+    }
+
+    pub fn matches(&self, name: &str, make: &str, model: &str) -> bool {
+        self.name == name || self.name == format!("{} {}", make, model)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputModeConfig {
+    pub width: i32,
+    pub height: i32,
+    // mHz, None takes the fastest
+    pub refresh: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationConfigs {
+    pub enabled: bool,
+    pub duration: Duration,
+    // None keeps the curve picked by each animation
+    pub curve: Option<AnimationType>,
+}
+
+impl AnimationConfigs {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration: Duration::from_millis(30),
+            curve: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecorationConfigs {
+    pub border_width: f32,
+    pub border_color: [f32; 3],
+    pub corner_radius: f32,
+}
+
+impl DecorationConfigs {
+    fn default() -> Self {
+        Self {
+            border_width: 5.0,
+            border_color: [0.0, 0.0, 1.0],
+            corner_radius: 10.0,
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Configs {
+    // the --config override, kept to find the same files on reload
+    pub config_path: Option<PathBuf>,
+    pub files: Vec<ConfigFile>,
+
     pub exec_once_cmds: Vec<(String, Vec<String>)>,
     pub env_vars: HashMap<String, String>,
+    // key combination as written, e.g. Super_L+t
+    pub keybindings: Vec<(String, KeyAction)>,
 
+    pub conf_general: GeneralConfigs,
     pub conf_workspaces: Arc<WorkspaceConfigs>,
+    pub conf_animation: AnimationConfigs,
+    pub conf_decoration: DecorationConfigs,
+    pub conf_outputs: Vec<OutputConfig>,
 }
 
 impl Configs {
-    pub fn new() -> Self {
-        let content = include_str!("./mondrian.conf").to_string();
+    /// Load and validate every config file, all errors are collected before
+    /// giving up so that `--check-config` can report them at once.
+    pub fn new(config_path: Option<PathBuf>) -> Result<Self, ConfigErrors> {
+        let (configs, errors) = Self::collect(config_path, false);
+        if !errors.is_empty() {
+            return Err(ConfigErrors(errors));
+        }
 
-        let re_exec = Regex::new(r#"^\s*exec-once\s*=\s*(.+)$"#).unwrap();
-        let re_env = Regex::new(r#"^\s*env\s*=\s*([^,\s]+)\s*,\s*(.+)$"#).unwrap();
+        Ok(configs)
+    }
 
-        let mut exec_once_cmds = Vec::new();
-        let mut env_vars = HashMap::new();
+    /// Load the configs for startup, where a bad line must not cost the whole
+    /// session: the errors are logged and every statement that parsed still
+    /// applies. Without any readable file the embedded defaults are used.
+    pub fn load(config_path: Option<PathBuf>) -> Self {
+        let (configs, errors) = Self::collect(config_path, true);
+        for error in &errors {
+            error!("{}", error);
+        }
 
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+        configs
+    }
 
-            if let Some(cap) = re_exec.captures(line) {
-                let mut parts = cap[1].trim().split_whitespace();
-                let cmd = parts.next().unwrap_or("").to_string();
-                let args: Vec<String> = parts.map(|s| s.to_string()).collect();
+    fn collect(config_path: Option<PathBuf>, fallback: bool) -> (Self, Vec<ConfigError>) {
+        let mut loader = Loader::new();
 
-                exec_once_cmds.push((cmd, args));
-            } else if let Some(cap) = re_env.captures(line) {
-                let key = cap[1].trim();
-                let val = cap[2].trim();
+        // --config first, then the config dir, then the embedded defaults
+        let main = match &config_path {
+            Some(path) => Some(path.clone()),
+            None => config_dir()
+                .map(|dir| dir.join(CONFIG_NAME))
+                .filter(|path| path.exists()),
+        };
 
-                env_vars.insert(key.to_string(), val.to_string());
+        match main {
+            Some(path) => {
+                info!("Loading config from {:?}", path);
+                loader.load_file(&path, None);
+            }
+            None => {
+                info!("No config file found, using embedded defaults");
+                loader.load_embedded(CONFIG_NAME);
             }
         }
 
-        Self {
-            exec_once_cmds,
-            env_vars,
-            conf_workspaces: Arc::new(WorkspaceConfigs::default()),
+        if fallback && loader.files.is_empty() {
+            warn!("No config file could be read, using embedded defaults");
+            loader.load_embedded(CONFIG_NAME);
         }
+
+        let configs = Self {
+            config_path,
+            files: loader.files,
+            exec_once_cmds: loader.exec_once_cmds,
+            env_vars: loader.env_vars,
+            keybindings: loader.keybindings,
+            conf_general: loader.general,
+            conf_workspaces: Arc::new(loader.workspace),
+            conf_animation: loader.animation,
+            conf_decoration: loader.decoration,
+            conf_outputs: loader.outputs,
+        };
+
+        (configs, loader.errors)
+    }
+
+    /// The block for an output, by connector name first.
+    pub fn output_config(&self, name: &str, make: &str, model: &str) -> Option<&OutputConfig> {
+        self.conf_outputs
+            .iter()
+            .find(|config| config.name == name)
+            .or_else(|| {
+                self.conf_outputs
+                    .iter()
+                    .find(|config| config.matches(name, make, model))
+            })
     }
 
     pub fn init(&self) {
+        self.exec_once();
+        self.apply_env();
+    }
+
+    fn exec_once(&self) {
         for (cmd, args) in &self.exec_once_cmds {
             let mut command = std::process::Command::new(cmd);
             command.args(args);
@@ -77,7 +308,10 @@ impl Configs {
                 _ => {}
             }
         }
+    }
 
+    // also run on reload, exec-once is not
+    pub fn apply_env(&self) {
         for (key, val) in &self.env_vars {
             unsafe {
                 info!("set {} = {}", key, val);
@@ -86,3 +320,552 @@ impl Configs {
         }
     }
 }
+
+// where a `source` statement points from, for errors about the sourced file
+struct Origin {
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+struct Loader {
+    files: Vec<ConfigFile>,
+    errors: Vec<ConfigError>,
+
+    exec_once_cmds: Vec<(String, Vec<String>)>,
+    env_vars: HashMap<String, String>,
+    keybindings: Vec<(String, KeyAction)>,
+    general: GeneralConfigs,
+    workspace: WorkspaceConfigs,
+    animation: AnimationConfigs,
+    decoration: DecorationConfigs,
+    outputs: Vec<OutputConfig>,
+}
+
+impl Loader {
+    fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            errors: Vec::new(),
+            exec_once_cmds: Vec::new(),
+            env_vars: HashMap::new(),
+            keybindings: Vec::new(),
+            general: GeneralConfigs::default(),
+            workspace: WorkspaceConfigs::default(),
+            animation: AnimationConfigs::default(),
+            decoration: DecorationConfigs::default(),
+            outputs: Vec::new(),
+        }
+    }
+
+    // blocks naming the same output are merged
+    fn output(&mut self, statement: &Statement) -> &mut OutputConfig {
+        let name = statement.section_name.as_deref().unwrap_or_default();
+        let index = match self.outputs.iter().position(|config| config.name == name) {
+            Some(index) => index,
+            None => {
+                self.outputs.push(OutputConfig::new(name));
+                self.outputs.len() - 1
+            }
+        };
+        &mut self.outputs[index]
+    }
+
+    fn io_error(&mut self, origin: Option<Origin>, path: &Path, err: std::io::Error) {
+        let message = format!("failed to read {:?}: {}", path, err);
+        let error = match origin {
+            Some(origin) => ConfigError::new(
+                &origin.file,
+                origin.line,
+                origin.column,
+                ConfigErrorKind::Io(message),
+            ),
+            None => ConfigError::new(
+                &path.display().to_string(),
+                0,
+                0,
+                ConfigErrorKind::Io(message),
+            ),
+        };
+        self.errors.push(error);
+    }
+
+    // A file that was already loaded is skipped, which also breaks include cycles.
+    fn load_file(&mut self, path: &Path, origin: Option<Origin>) {
+        let path = match fs::canonicalize(path) {
+            Ok(path) => path,
+            Err(err) => return self.io_error(origin, path, err),
+        };
+
+        if self
+            .files
+            .iter()
+            .any(|file| file.path.as_ref() == Some(&path))
+        {
+            warn!("Config {:?} is sourced more than once, skipping", path);
+            return;
+        }
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) => return self.io_error(origin, &path, err),
+        };
+
+        self.load_content(path.display().to_string(), Some(path), content);
+    }
+
+    fn load_embedded(&mut self, name: &str) {
+        let Some((name, content)) = EMBEDDED.iter().find(|(n, _)| *n == name) else {
+            warn!("No embedded config named {}, skipping", name);
+            return;
+        };
+
+        let name = format!("<embedded>/{}", name);
+        if self.files.iter().any(|file| file.name == name) {
+            return;
+        }
+
+        self.load_content(name, None, content.to_string());
+    }
+
+    // statements are applied in order, a sourced file in place of its
+    // `source` line, so later lines override earlier ones
+    fn load_content(&mut self, name: String, path: Option<PathBuf>, content: String) {
+        let (statements, errors) = parser::parse(&name, &content);
+        self.errors.extend(errors);
+
+        self.files.push(ConfigFile {
+            path: path.clone(),
+            name: name.clone(),
+            content,
+        });
+
+        for statement in statements {
+            if statement.section.is_none() && statement.key == "source" {
+                self.source(&name, path.as_deref(), &statement);
+            } else if let Err(error) = self.apply(&statement) {
+                self.errors
+                    .push(ConfigError::new(&name, statement.line, error.0, error.1));
+            }
+        }
+    }
+
+    fn source(&mut self, name: &str, path: Option<&Path>, statement: &Statement) {
+        let source = expand_home(unquote(&statement.value));
+
+        match path {
+            // relative sources are relative to the file that names them
+            Some(path) => {
+                let dir = path.parent().unwrap_or(Path::new("/"));
+                let origin = Origin {
+                    file: name.to_string(),
+                    line: statement.line,
+                    column: statement.value_column,
+                };
+                self.load_file(&dir.join(source), Some(origin));
+            }
+            // the embedded defaults only source each other
+            None => match source.file_name().and_then(|n| n.to_str()) {
+                Some(file_name) => self.load_embedded(file_name),
+                None => warn!("Invalid source {:?} in embedded config", source),
+            },
+        }
+    }
+
+    fn apply(&mut self, statement: &Statement) -> Result<(), (usize, ConfigErrorKind)> {
+        let key = statement.key.as_str();
+        let value = statement.value.as_str();
+        let column = statement.value_column;
+
+        let invalid = |expected: &'static str| {
+            (
+                column,
+                ConfigErrorKind::InvalidValue {
+                    key: key.to_string(),
+                    expected,
+                },
+            )
+        };
+
+        if value.is_empty() {
+            return Err((column, ConfigErrorKind::EmptyValue(key.to_string())));
+        }
+
+        match (statement.section, key) {
+            (None, "exec-once") => {
+                let mut parts = value.split_whitespace();
+                let cmd = parts.next().unwrap_or("").to_string();
+                let args: Vec<String> = parts.map(|s| s.to_string()).collect();
+
+                self.exec_once_cmds.push((cmd, args));
+            }
+            (None, "env") => {
+                let (name, val) = value
+                    .split_once(',')
+                    .filter(|(name, _)| !name.trim().is_empty())
+                    .ok_or_else(|| invalid("`NAME,VALUE`"))?;
+
+                self.env_vars
+                    .insert(name.trim().to_string(), val.trim().to_string());
+            }
+            (None, "bind") => {
+                let binding = parse_bind(value, column)?;
+                self.keybindings.push(binding);
+            }
+            (None, "workspace_rule") => {
+                let rule = parse_workspace_rule(value).map_err(invalid)?;
+                // a later rule for the same workspace wins
+                self.workspace.rules.retain(|r| r.name != rule.name);
+                self.workspace.rules.push(rule);
+            }
+
+            (Some(Section::General), "cursor_theme") => {
+                self.general.cursor_theme = unquote(value).to_string();
+            }
+            (Some(Section::General), "cursor_size") => {
+                self.general.cursor_size = value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|size| *size > 0)
+                    .ok_or_else(|| invalid("a size between 1 and 255"))?;
+            }
+            (Some(Section::General), "warp_pointer") => {
+                self.general.warp_pointer =
+                    parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+            }
+
+            (Some(Section::Workspace), "gap") => {
+                self.workspace.gap = value
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|gap| *gap >= 0)
+                    .ok_or_else(|| invalid("a non-negative integer"))?;
+            }
+            (Some(Section::Workspace), "scheme") => {
+                self.workspace.scheme = parse_scheme(unquote(value))
+                    .ok_or_else(|| invalid("default, spiral, master-stack or scrolling"))?;
+            }
+            (Some(Section::Workspace), "master_count") => {
+                self.workspace.master.count = value
+                    .parse::<usize>()
+                    .map_err(|_| invalid("a non-negative integer"))?;
+            }
+            (Some(Section::Workspace), "master_ratio") => {
+                self.workspace.master.ratio = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|ratio| (0.1..=0.9).contains(ratio))
+                    .ok_or_else(|| invalid("a ratio between 0.1 and 0.9"))?;
+            }
+            (Some(Section::Workspace), "master_side") => {
+                self.workspace.master.side = parse_side(unquote(value))
+                    .ok_or_else(|| invalid("left, right, top or bottom"))?;
+            }
+            (Some(Section::Workspace), "resize_step") => {
+                self.workspace.resize_step = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|step| *step > 0.0 && *step <= 0.5)
+                    .ok_or_else(|| invalid("a ratio between 0 and 0.5"))?;
+            }
+
+            (Some(Section::Animation), "enabled") => {
+                self.animation.enabled =
+                    parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+            }
+            (Some(Section::Animation), "duration") => {
+                let millis: u64 = value
+                    .parse()
+                    .map_err(|_| invalid("a duration in milliseconds"))?;
+                self.animation.duration = Duration::from_millis(millis);
+            }
+            (Some(Section::Animation), "curve") => {
+                self.animation.curve = Some(match unquote(value) {
+                    "linear" => AnimationType::Linear,
+                    "ease-in-out-quad" => AnimationType::EaseInOutQuad,
+                    "overshoot-bounce" => AnimationType::OvershootBounce,
+                    _ => {
+                        return Err(invalid("linear, ease-in-out-quad or overshoot-bounce"));
+                    }
+                });
+            }
+
+            (Some(Section::Decoration), "border_width") => {
+                self.decoration.border_width =
+                    parse_non_negative(value).ok_or_else(|| invalid("a non-negative number"))?;
+            }
+            (Some(Section::Decoration), "border_color") => {
+                self.decoration.border_color = parse_color(unquote(value))
+                    .ok_or_else(|| invalid("a color like \"#4c7899\""))?;
+            }
+            (Some(Section::Decoration), "corner_radius") => {
+                self.decoration.corner_radius =
+                    parse_non_negative(value).ok_or_else(|| invalid("a non-negative number"))?;
+            }
+
+            (Some(Section::Output), "enable") => {
+                let enable = parse_bool(value).ok_or_else(|| invalid("true or false"))?;
+                self.output(statement).enable = enable;
+            }
+            (Some(Section::Output), "mode") => {
+                let mode = parse_mode(unquote(value))
+                    .ok_or_else(|| invalid("WIDTHxHEIGHT or WIDTHxHEIGHT@HZ"))?;
+                self.output(statement).mode = Some(mode);
+            }
+            (Some(Section::Output), "position") => {
+                let position = parse_position(unquote(value)).ok_or_else(|| invalid("`X,Y`"))?;
+                self.output(statement).position = Some(position);
+            }
+            (Some(Section::Output), "scale") => {
+                let scale = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|scale| scale.is_finite() && *scale > 0.0)
+                    .ok_or_else(|| invalid("a positive number"))?;
+                self.output(statement).scale = Some(scale);
+            }
+            (Some(Section::Output), "transform") => {
+                let transform = parse_transform(unquote(value)).ok_or_else(|| {
+                    invalid("normal, 90, 180, 270, flipped, flipped-90, flipped-180 or flipped-270")
+                })?;
+                self.output(statement).transform = Some(transform);
+            }
+
+            _ => {
+                return Err((
+                    statement.key_column,
+                    ConfigErrorKind::UnknownKey(key.to_string()),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// bind = Super_L+t, command, "kitty"
+// bind = Super_L+1, exec, "workspace 1"
+fn parse_bind(value: &str, column: usize) -> Result<(String, KeyAction), (usize, ConfigErrorKind)> {
+    // the argument may contain commas of its own
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for _ in 0..2 {
+        let Some(end) = value[start..].find(',') else {
+            break;
+        };
+        parts.push((start, &value[start..start + end]));
+        start += end + 1;
+    }
+    parts.push((start, &value[start..]));
+
+    let part_column = |(offset, part): (usize, &str)| {
+        let leading = part.len() - part.trim_start().len();
+        column + parser::column(value, offset + leading) - 1
+    };
+
+    let [keys, action, argument] = parts[..] else {
+        return Err((
+            column,
+            ConfigErrorKind::InvalidValue {
+                key: "bind".to_string(),
+                expected: "`KEYS, command|exec, \"ARGUMENT\"`",
+            },
+        ));
+    };
+
+    if keys.1.trim().is_empty() {
+        return Err((
+            part_column(keys),
+            ConfigErrorKind::InvalidValue {
+                key: "bind".to_string(),
+                expected: "a key combination like Super_L+t",
+            },
+        ));
+    }
+
+    let argument_text = unquote(argument.1.trim());
+    if argument_text.is_empty() {
+        return Err((
+            part_column(argument),
+            ConfigErrorKind::EmptyValue("bind".to_string()),
+        ));
+    }
+
+    let key_action = match action.1.trim() {
+        "command" => {
+            let mut parts = argument_text.split_whitespace();
+            let cmd = parts.next().unwrap_or("").to_string();
+            let args: Vec<String> = parts.map(|s| s.to_string()).collect();
+
+            KeyAction::Command(cmd, args)
+        }
+        "exec" => match FunctionEnum::from_name(argument_text) {
+            Some(function) => KeyAction::Internal(function),
+            None => {
+                return Err((
+                    part_column(argument),
+                    ConfigErrorKind::UnknownFunction(argument_text.to_string()),
+                ));
+            }
+        },
+        other => {
+            return Err((
+                part_column(action),
+                ConfigErrorKind::UnknownBindAction(other.to_string()),
+            ));
+        }
+    };
+
+    let keys = keys
+        .1
+        .split('+')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("+");
+
+    Ok((keys, key_action))
+}
+
+// workspace_rule = 3, scheme:spiral, gap:8, output:HDMI-A-1
+fn parse_workspace_rule(value: &str) -> Result<WorkspaceRule, &'static str> {
+    let mut parts = value.split(',').map(str::trim);
+
+    let name = parts.next().map(unquote).unwrap_or_default();
+    if name.is_empty() {
+        return Err("a workspace name followed by `key:value` options");
+    }
+
+    let mut rule = WorkspaceRule {
+        name: name.to_string(),
+        scheme: None,
+        gap: None,
+        output: None,
+    };
+
+    for part in parts.filter(|part| !part.is_empty()) {
+        let (key, value) = part
+            .split_once(':')
+            .map(|(key, value)| (key.trim(), unquote(value.trim())))
+            .ok_or("options like `scheme:spiral`, `gap:8` or `output:NAME`")?;
+
+        match key {
+            "scheme" => {
+                rule.scheme = Some(
+                    parse_scheme(value)
+                        .ok_or("scheme:default, spiral, master-stack or scrolling")?,
+                )
+            }
+            "gap" => {
+                rule.gap = Some(
+                    value
+                        .parse::<i32>()
+                        .ok()
+                        .filter(|gap| *gap >= 0)
+                        .ok_or("a non-negative gap")?,
+                )
+            }
+            "output" => rule.output = Some(value.to_string()),
+            _ => return Err("one of scheme, gap or output"),
+        }
+    }
+
+    Ok(rule)
+}
+
+fn parse_scheme(value: &str) -> Option<TiledScheme> {
+    match value {
+        "default" => Some(TiledScheme::Default),
+        "spiral" => Some(TiledScheme::Spiral),
+        "master-stack" => Some(TiledScheme::MasterStack),
+        "scrolling" => Some(TiledScheme::Scrolling),
+        _ => None,
+    }
+}
+
+fn parse_side(value: &str) -> Option<Direction> {
+    match value {
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        "top" => Some(Direction::Up),
+        "bottom" => Some(Direction::Down),
+        _ => None,
+    }
+}
+
+// 2560x1440 or 2560x1440@143.97
+fn parse_mode(value: &str) -> Option<OutputModeConfig> {
+    let (size, refresh) = match value.split_once('@') {
+        Some((size, refresh)) => (size, Some(refresh)),
+        None => (value, None),
+    };
+    let (width, height) = size.split_once('x')?;
+
+    let refresh = match refresh {
+        Some(refresh) => Some(
+            refresh
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|hz| hz.is_finite() && *hz > 0.0)
+                .map(|hz| (hz * 1000.0).round() as i32)?,
+        ),
+        None => None,
+    };
+
+    Some(OutputModeConfig {
+        width: width.trim().parse().ok().filter(|w| *w > 0)?,
+        height: height.trim().parse().ok().filter(|h| *h > 0)?,
+        refresh,
+    })
+}
+
+fn parse_position(value: &str) -> Option<(i32, i32)> {
+    let (x, y) = value.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+fn parse_transform(value: &str) -> Option<Transform> {
+    match value {
+        "normal" => Some(Transform::Normal),
+        "90" => Some(Transform::_90),
+        "180" => Some(Transform::_180),
+        "270" => Some(Transform::_270),
+        "flipped" => Some(Transform::Flipped),
+        "flipped-90" => Some(Transform::Flipped90),
+        "flipped-180" => Some(Transform::Flipped180),
+        "flipped-270" => Some(Transform::Flipped270),
+        _ => None,
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_non_negative(value: &str) -> Option<f32> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite() && *v >= 0.0)
+}
+
+// "#rrggbb" or 0xrrggbb
+fn parse_color(value: &str) -> Option<[f32; 3]> {
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))?;
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some([
+        ((rgb >> 16) & 0xff) as f32 / 255.0,
+        ((rgb >> 8) & 0xff) as f32 / 255.0,
+        (rgb & 0xff) as f32 / 255.0,
+    ])
+}
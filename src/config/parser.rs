@@ -0,0 +1,474 @@
+use std::fmt;
+
+// Grammar of mondrian.conf and the files it sources:
+//
+//     # comment, also allowed after a value
+//     key = value
+//     section {
+//         key = value
+//     }
+//     output HDMI-A-1 {
+//         key = value
+//     }
+//
+// `#` inside double quotes does not start a comment. Sections cannot nest,
+// only `output` takes a name, which may be quoted.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    General,
+    Workspace,
+    Animation,
+    Decoration,
+    Output,
+}
+
+impl Section {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "general" => Some(Section::General),
+            "workspace" => Some(Section::Workspace),
+            "animation" => Some(Section::Animation),
+            "decoration" => Some(Section::Decoration),
+            "output" => Some(Section::Output),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Section::General => "general",
+            Section::Workspace => "workspace",
+            Section::Animation => "animation",
+            Section::Decoration => "decoration",
+            Section::Output => "output",
+        }
+    }
+
+    fn named(&self) -> bool {
+        matches!(self, Section::Output)
+    }
+}
+
+/// One `key = value` line, columns are 1-based and count chars.
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub section: Option<Section>,
+    // the name after `output`
+    pub section_name: Option<String>,
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+    pub key_column: usize,
+    pub value_column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigErrorKind {
+    Io(String),
+    ExpectedAssignment,
+    EmptyKey,
+    EmptyValue(String),
+    UnknownKey(String),
+    UnknownSection(String),
+    NestedSection(String),
+    UnclosedSection(String),
+    MissingSectionName(String),
+    UnexpectedSectionName(String),
+    UnmatchedBrace,
+    InvalidValue { key: String, expected: &'static str },
+    UnknownBindAction(String),
+    UnknownFunction(String),
+}
+
+impl fmt::Display for ConfigErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigErrorKind::Io(err) => write!(f, "{}", err),
+            ConfigErrorKind::ExpectedAssignment => write!(f, "expected `key = value`"),
+            ConfigErrorKind::EmptyKey => write!(f, "missing key before `=`"),
+            ConfigErrorKind::EmptyValue(key) => write!(f, "missing value for `{}`", key),
+            ConfigErrorKind::UnknownKey(key) => write!(f, "unknown key `{}`", key),
+            ConfigErrorKind::UnknownSection(name) => write!(f, "unknown section `{}`", name),
+            ConfigErrorKind::NestedSection(name) => {
+                write!(f, "section `{}` cannot be nested", name)
+            }
+            ConfigErrorKind::UnclosedSection(name) => {
+                write!(f, "section `{}` is never closed", name)
+            }
+            ConfigErrorKind::MissingSectionName(name) => {
+                write!(f, "section `{}` needs a name", name)
+            }
+            ConfigErrorKind::UnexpectedSectionName(name) => {
+                write!(f, "section `{}` takes no name", name)
+            }
+            ConfigErrorKind::UnmatchedBrace => write!(f, "`}}` without an open section"),
+            ConfigErrorKind::InvalidValue { key, expected } => {
+                write!(f, "invalid value for `{}`, expected {}", key, expected)
+            }
+            ConfigErrorKind::UnknownBindAction(action) => {
+                write!(
+                    f,
+                    "unknown bind action `{}`, expected command or exec",
+                    action
+                )
+            }
+            ConfigErrorKind::UnknownFunction(name) => write!(f, "no function named `{}`", name),
+        }
+    }
+}
+
+/// An error at a position in a config file, line 0 means the whole file.
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub kind: ConfigErrorKind,
+}
+
+impl ConfigError {
+    pub fn new(file: &str, line: usize, column: usize, kind: ConfigErrorKind) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            column,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.kind)
+        } else {
+            write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file, self.line, self.column, self.kind
+            )
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Every error found while loading, one per line when displayed.
+#[derive(Debug, Clone)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+enum Scope {
+    Top,
+    Section(Section, Option<String>, usize),
+    // body of an unknown section, skipped after reporting it once
+    Unknown,
+}
+
+pub fn parse(file: &str, content: &str) -> (Vec<Statement>, Vec<ConfigError>) {
+    let mut statements = Vec::new();
+    let mut errors = Vec::new();
+    let mut scope = Scope::Top;
+
+    for (index, raw) in content.lines().enumerate() {
+        let line = index + 1;
+        let text = strip_comment(raw);
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let start = text.len() - text.trim_start().len();
+        let key_column = column(raw, start);
+
+        if trimmed == "}" {
+            match scope {
+                Scope::Top => errors.push(ConfigError::new(
+                    file,
+                    line,
+                    key_column,
+                    ConfigErrorKind::UnmatchedBrace,
+                )),
+                _ => scope = Scope::Top,
+            }
+            continue;
+        }
+
+        // a value may end in `{` too, a section header has no `=`
+        if let Some(name) = trimmed.strip_suffix('{').filter(|name| !name.contains('=')) {
+            let name = name.trim();
+            if !matches!(scope, Scope::Top) {
+                errors.push(ConfigError::new(
+                    file,
+                    line,
+                    key_column,
+                    ConfigErrorKind::NestedSection(name.to_string()),
+                ));
+                continue;
+            }
+
+            let (name, section_name) = match name.split_once(char::is_whitespace) {
+                Some((name, rest)) => (name, Some(unquote(rest.trim()))),
+                None => (name, None),
+            };
+            let section_name = section_name.filter(|n| !n.is_empty()).map(str::to_string);
+
+            scope = match Section::from_name(name) {
+                Some(section) if section.named() != section_name.is_some() => {
+                    let kind = if section.named() {
+                        ConfigErrorKind::MissingSectionName(name.to_string())
+                    } else {
+                        ConfigErrorKind::UnexpectedSectionName(name.to_string())
+                    };
+                    errors.push(ConfigError::new(file, line, key_column, kind));
+                    Scope::Unknown
+                }
+                Some(section) => Scope::Section(section, section_name, line),
+                None => {
+                    errors.push(ConfigError::new(
+                        file,
+                        line,
+                        key_column,
+                        ConfigErrorKind::UnknownSection(name.to_string()),
+                    ));
+                    Scope::Unknown
+                }
+            };
+            continue;
+        }
+
+        let (section, section_name) = match &scope {
+            Scope::Top => (None, None),
+            Scope::Section(section, name, _) => (Some(*section), name.clone()),
+            Scope::Unknown => continue,
+        };
+
+        let Some(eq) = text.find('=') else {
+            errors.push(ConfigError::new(
+                file,
+                line,
+                key_column,
+                ConfigErrorKind::ExpectedAssignment,
+            ));
+            continue;
+        };
+
+        let key = text[..eq].trim();
+        if key.is_empty() {
+            errors.push(ConfigError::new(
+                file,
+                line,
+                key_column,
+                ConfigErrorKind::EmptyKey,
+            ));
+            continue;
+        }
+
+        let rest = &text[eq + 1..];
+        let value = rest.trim();
+        let value_start = eq + 1 + (rest.len() - rest.trim_start().len());
+
+        statements.push(Statement {
+            section,
+            section_name,
+            key: key.to_string(),
+            value: value.to_string(),
+            line,
+            key_column,
+            value_column: column(raw, value_start),
+        });
+    }
+
+    if let Scope::Section(section, _, line) = scope {
+        errors.push(ConfigError::new(
+            file,
+            line,
+            1,
+            ConfigErrorKind::UnclosedSection(section.name().to_string()),
+        ));
+    }
+
+    (statements, errors)
+}
+
+/// Cut the line at the first `#` outside double quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 1-based char column of a byte offset.
+pub fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// Remove one pair of surrounding double quotes.
+pub fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::config::Loader;
+
+    // the diagnostics of `content`, as --check-config prints them
+    fn diagnostics(content: &str) -> Vec<String> {
+        let (_, errors) = parse("test.conf", content);
+        errors.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn statements_keep_their_position() {
+        let (statements, errors) = parse(
+            "test.conf",
+            "gap = 8\n\nworkspace {\n    scheme =  spiral # comment\n}\noutput \"Dell U2720\" {\n  scale = 1.5\n}\n",
+        );
+        assert!(errors.is_empty());
+
+        let scheme = &statements[1];
+        assert_eq!(scheme.section, Some(Section::Workspace));
+        assert_eq!(
+            (scheme.key.as_str(), scheme.value.as_str()),
+            ("scheme", "spiral")
+        );
+        assert_eq!(
+            (scheme.line, scheme.key_column, scheme.value_column),
+            (4, 5, 15)
+        );
+
+        let scale = &statements[2];
+        assert_eq!(scale.section, Some(Section::Output));
+        assert_eq!(scale.section_name.as_deref(), Some("Dell U2720"));
+        assert_eq!((scale.line, scale.value_column), (7, 11));
+    }
+
+    #[test]
+    fn unmatched_brace() {
+        assert_eq!(
+            diagnostics("gap = 8\n  }\n"),
+            vec!["test.conf:2:3: `}` without an open section"]
+        );
+    }
+
+    #[test]
+    fn nested_and_unknown_sections() {
+        assert_eq!(
+            diagnostics("general {\n    animation {\n}\ncolors {\n    a = b\n}\n"),
+            vec![
+                "test.conf:2:5: section `animation` cannot be nested",
+                "test.conf:4:1: unknown section `colors`",
+            ]
+        );
+    }
+
+    #[test]
+    fn section_names() {
+        assert_eq!(
+            diagnostics("output {\n}\ngeneral eDP-1 {\n}\n"),
+            vec![
+                "test.conf:1:1: section `output` needs a name",
+                "test.conf:3:1: section `general` takes no name",
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_section() {
+        assert_eq!(
+            diagnostics("\nanimation {\n    duration = 200\n"),
+            vec!["test.conf:2:1: section `animation` is never closed"]
+        );
+    }
+
+    #[test]
+    fn value_ending_in_brace() {
+        let (statements, errors) = parse(
+            "test.conf",
+            "exec-once = sh -c \"while true; do {\"\ngeneral {\n    cursor_theme = {\n}\n",
+        );
+        assert!(errors.is_empty());
+        assert_eq!(statements[0].value, "sh -c \"while true; do {\"");
+        assert_eq!(statements[1].section, Some(Section::General));
+        assert_eq!(statements[1].value, "{");
+    }
+
+    #[test]
+    fn quoted_hash() {
+        let (statements, errors) = parse(
+            "test.conf",
+            "bind = Super_L+c, exec, \"echo #1\" # run it\n# key = value\n",
+        );
+        assert!(errors.is_empty());
+        assert_eq!(statements.len(), 1);
+        assert_eq!(statements[0].value, "Super_L+c, exec, \"echo #1\"");
+    }
+
+    #[test]
+    fn broken_lines() {
+        assert_eq!(
+            diagnostics("gap 8\n  = 8\ngap =\n"),
+            vec![
+                "test.conf:1:1: expected `key = value`",
+                "test.conf:2:3: missing key before `=`",
+            ]
+        );
+    }
+
+    #[test]
+    fn source_errors_name_the_sourced_file() {
+        let dir = std::env::temp_dir().join(format!("mondrian-parser-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.conf"),
+            "source = broken.conf\nsource = missing.conf\n",
+        )
+        .unwrap();
+        fs::write(dir.join("broken.conf"), "\n  }\n").unwrap();
+
+        let mut loader = Loader::new();
+        loader.load_file(&dir.join("main.conf"), None);
+        let dir = fs::canonicalize(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let errors: Vec<String> = loader.errors.iter().map(ToString::to_string).collect();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0],
+            format!(
+                "{}:2:3: `}}` without an open section",
+                dir.join("broken.conf").display()
+            )
+        );
+        assert!(errors[1].starts_with(&format!(
+            "{}:2:10: failed to read",
+            dir.join("main.conf").display()
+        )));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    cell::Cell, collections::HashMap, ffi::OsString, os::unix::ffi::OsStrExt, path::PathBuf,
+    rc::Rc, time::Duration,
+};
+
+use anyhow::Context;
+use smithay::reexports::{
+    calloop::{
+        Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+        generic::Generic,
+        timer::{TimeoutAction, Timer},
+    },
+    rustix::{
+        self,
+        fs::inotify::{self, CreateFlags, WatchFlags},
+        io::Errno,
+    },
+};
+
+use crate::{state::GlobalData, utils::errors::AnyHowErr};
+
+use super::Configs;
+
+// editors write a file in several steps, wait for them to settle
+const RELOAD_DELAY: Duration = Duration::from_millis(200);
+
+// struct inotify_event { int wd; uint32_t mask, cookie, len; char name[]; }
+const EVENT_HEADER: usize = 16;
+
+/// Watches every loaded config file through inotify and reloads on change.
+///
+/// The parent directories are watched rather than the files, so that editors
+/// replacing a file by rename are noticed as well.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    token: RegistrationToken,
+}
+
+impl ConfigWatcher {
+    pub fn new(
+        loop_handle: &LoopHandle<'static, GlobalData>,
+        configs: &Configs,
+    ) -> anyhow::Result<Self> {
+        let fd = inotify::init(CreateFlags::CLOEXEC | CreateFlags::NONBLOCK)
+            .context("Failed to init inotify")?;
+
+        let mut dirs: HashMap<PathBuf, Vec<OsString>> = HashMap::new();
+        for path in configs.files.iter().filter_map(|file| file.path.as_ref()) {
+            if let (Some(dir), Some(name)) = (path.parent(), path.file_name()) {
+                dirs.entry(dir.to_path_buf())
+                    .or_default()
+                    .push(name.to_os_string());
+            }
+        }
+
+        let mut watches: HashMap<i32, Vec<OsString>> = HashMap::new();
+        for (dir, names) in dirs {
+            let wd = inotify::add_watch(
+                &fd,
+                &dir,
+                WatchFlags::CLOSE_WRITE
+                    | WatchFlags::MOVED_TO
+                    | WatchFlags::CREATE
+                    | WatchFlags::DELETE,
+            )
+            .with_context(|| format!("Failed to watch {:?}", dir))?;
+
+            watches.insert(wd, names);
+        }
+
+        let pending = Rc::new(Cell::new(false));
+
+        let token = loop_handle
+            .insert_source(
+                Generic::new(fd, Interest::READ, Mode::Level),
+                move |_, fd, data| {
+                    // Safety: we don't drop the fd
+                    let fd = unsafe { fd.get_mut() };
+
+                    let mut changed = false;
+                    let mut buffer = [0u8; 4096];
+
+                    loop {
+                        let len = match rustix::io::read(&*fd, &mut buffer[..]) {
+                            Ok(len) => len,
+                            Err(Errno::AGAIN) => break,
+                            Err(Errno::INTR) => continue,
+                            Err(err) => {
+                                warn!("Failed to read inotify events: {}", err);
+                                break;
+                            }
+                        };
+
+                        let mut offset = 0;
+                        while offset + EVENT_HEADER <= len {
+                            let header = &buffer[offset..offset + EVENT_HEADER];
+                            let wd = i32::from_ne_bytes(header[0..4].try_into().unwrap());
+                            let name_len =
+                                u32::from_ne_bytes(header[12..16].try_into().unwrap()) as usize;
+
+                            let name = &buffer[offset + EVENT_HEADER..]
+                                [..name_len.min(len - offset - EVENT_HEADER)];
+                            let name = name.split(|b| *b == 0).next().unwrap_or_default();
+
+                            if watches
+                                .get(&wd)
+                                .is_some_and(|names| names.iter().any(|n| n.as_bytes() == name))
+                            {
+                                changed = true;
+                            }
+
+                            offset += EVENT_HEADER + name_len;
+                        }
+                    }
+
+                    if changed && !pending.get() {
+                        pending.set(true);
+
+                        let pending = pending.clone();
+                        let _ = data.loop_handle.insert_source(
+                            Timer::from_duration(RELOAD_DELAY),
+                            move |_, _, data| {
+                                pending.set(false);
+
+                                info!("Config changed, reloading");
+                                if let Err(err) = data.reload() {
+                                    error!("{:#}", err);
+                                }
+                                TimeoutAction::Drop
+                            },
+                        );
+                    }
+
+                    Ok(PostAction::Continue)
+                },
+            )
+            .anyhow_err("Failed to init config watcher source")?;
+
+        Ok(Self { token })
+    }
+
+    pub fn remove(self, loop_handle: &LoopHandle<'static, GlobalData>) {
+        loop_handle.remove(self.token);
+    }
+}
@@ -1,15 +1,18 @@
 use smithay::{
     backend::input::{Event, InputBackend, KeyState, KeyboardKeyEvent},
+    desktop::Window,
     input::keyboard::{FilterResult, xkb::keysym_get_name},
+    output::Output,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
     utils::{SERIAL_COUNTER, Serial},
 };
 
 use crate::{
-    manager::{
-        input::{FunctionEnum, KeyAction},
-        workspace::WorkspaceId,
+    layout::{
+        Direction,
+        json_tiled_tree::{JsonTree, layout_load_path, layout_save_path},
     },
+    manager::input::{FunctionEnum, KeyAction, Submap},
     state::GlobalData,
 };
 
@@ -38,6 +41,13 @@ impl GlobalData {
             |data, _modifiers, keysym_handle| {
                 match event_state {
                     KeyState::Pressed => {
+                        // releases still pass below, so modifiers are tracked
+                        if let Some(submap) = data.input_manager.submap() {
+                            let name = keysym_get_name(keysym_handle.modified_sym());
+                            data.submap_key(submap, &name);
+                            return FilterResult::Intercept(());
+                        }
+
                         let mut pressed_keys_name: Vec<String> =
                             keyboard.with_pressed_keysyms(|keysym_handles| {
                                 keysym_handles
@@ -45,7 +55,7 @@ impl GlobalData {
                                     .map(|keysym_handle| {
                                         let keysym_value = keysym_handle.modified_sym();
                                         let name = keysym_get_name(keysym_value);
-                                        if name == "Control_L" {
+                                        if name == "Super_L" {
                                             #[cfg(feature = "trace_input")]
                                             info!("mainmod_pressed: true");
 
@@ -69,7 +79,7 @@ impl GlobalData {
                     KeyState::Released => {
                         let keysym_value = keysym_handle.modified_sym();
                         let name = keysym_get_name(keysym_value);
-                        if name == "Control_L" {
+                        if name == "Super_L" {
                             #[cfg(feature = "trace_input")]
                             info!("mainmod_pressed: false");
 
@@ -83,77 +93,205 @@ impl GlobalData {
     }
 
     pub fn action_keys(&mut self, keys: String, serial: Serial) {
-        let keybindings = self.input_manager.get_keybindings();
+        let action = self.input_manager.get_keybindings().get(&keys).cloned();
 
-        if let Some(command) = keybindings.get(&keys) {
-            match command {
-                KeyAction::Command(cmd, args) => {
-                    #[cfg(feature = "trace_input")]
-                    info!("Command: {} {}", cmd, args.join(" "));
+        if let Some(action) = action {
+            self.run_action(action, serial);
+        }
+    }
 
-                    let mut command = std::process::Command::new(cmd);
+    pub fn run_action(&mut self, action: KeyAction, serial: Serial) {
+        match action {
+            KeyAction::Command(cmd, args) => {
+                #[cfg(feature = "trace_input")]
+                info!("Command: {} {}", cmd, args.join(" "));
 
-                    for arg in args {
-                        command.arg(arg);
-                    }
+                let mut command = std::process::Command::new(&cmd);
 
-                    match command.spawn() {
-                        #[cfg(feature = "trace_input")]
-                        Ok(child) => {
-                            info!("Command spawned with PID: {}", child.id());
-                        }
-                        Err(e) => {
-                            error!(
-                                "Failed to execute command '{} {}': {}",
-                                cmd,
-                                args.join(" "),
-                                e
-                            );
-                        }
-                        #[cfg(not(feature = "trace_input"))]
-                        _ => {}
+                for arg in &args {
+                    command.arg(arg);
+                }
+
+                match command.spawn() {
+                    #[cfg(feature = "trace_input")]
+                    Ok(child) => {
+                        info!("Command spawned with PID: {}", child.id());
                     }
+                    Err(e) => {
+                        error!(
+                            "Failed to execute command '{} {}': {}",
+                            cmd,
+                            args.join(" "),
+                            e
+                        );
+                    }
+                    #[cfg(not(feature = "trace_input"))]
+                    _ => {}
+                }
+            }
+            KeyAction::Internal(func) => match func {
+                FunctionEnum::Workspace(name) => {
+                    self.switch_workspace(&name, serial);
                 }
-                KeyAction::Internal(func) => match func {
-                    FunctionEnum::SwitchWorkspace1 => {
-                        self.set_keyboard_focus(None, serial);
-                        self.workspace_manager.set_activated(WorkspaceId::new(1));
+                FunctionEnum::MoveToWorkspace(name) => {
+                    self.move_to_workspace(&name, false, serial);
+                }
+                FunctionEnum::MoveToWorkspaceFollow(name) => {
+                    self.move_to_workspace(&name, true, serial);
+                }
+                FunctionEnum::WorkspaceNext => {
+                    if let Some(name) = self.workspace_manager.relative_workspace(1) {
+                        self.switch_workspace(&name, serial);
                     }
-                    FunctionEnum::SwitchWorkspace2 => {
-                        self.set_keyboard_focus(None, serial);
-                        self.workspace_manager.set_activated(WorkspaceId::new(2));
+                }
+                FunctionEnum::WorkspacePrev => {
+                    if let Some(name) = self.workspace_manager.relative_workspace(-1) {
+                        self.switch_workspace(&name, serial);
                     }
-                    FunctionEnum::InvertWindow => {
-                        self.workspace_manager.invert_window(&self.loop_handle);
+                }
+                FunctionEnum::WorkspaceBackAndForth => {
+                    if let Some(name) = self
+                        .workspace_manager
+                        .previous_workspace()
+                        .map(str::to_string)
+                    {
+                        self.switch_workspace(&name, serial);
                     }
-                    FunctionEnum::Expansion => {
-                        self.workspace_manager.tiled_expansion(&self.loop_handle);
+                }
+                FunctionEnum::InvertWindow => {
+                    self.workspace_manager.invert_window(&self.loop_handle);
+                }
+                FunctionEnum::Expansion => {
+                    self.workspace_manager.tiled_expansion(&self.loop_handle);
+                }
+                FunctionEnum::Recover => {
+                    self.workspace_manager.tiled_recover(&self.loop_handle);
+                }
+                FunctionEnum::ToggleFloating => {
+                    self.workspace_manager.toggle_floating(&self.loop_handle);
+                }
+                FunctionEnum::Fullscreen => {
+                    self.workspace_manager.toggle_fullscreen(&self.loop_handle);
+                }
+                FunctionEnum::Maximize => {
+                    self.workspace_manager.toggle_maximized(&self.loop_handle);
+                }
+                FunctionEnum::FocusNext => {
+                    if let Some(window) = self.workspace_manager.focus_next(&self.loop_handle) {
+                        self.focus_window(&window, serial);
                     }
-                    FunctionEnum::Recover => {
-                        self.workspace_manager.tiled_recover(&self.loop_handle);
+                }
+                FunctionEnum::Focus(direction) => {
+                    self.focus_direction(&direction, serial);
+                }
+                FunctionEnum::Grow(direction) => {
+                    let step = self.configs.conf_workspaces.resize_step;
+                    self.workspace_manager
+                        .resize_focus(&direction, step, &self.loop_handle);
+                }
+                FunctionEnum::Shrink(direction) => {
+                    let step = self.configs.conf_workspaces.resize_step;
+                    self.workspace_manager
+                        .resize_focus(&direction, -step, &self.loop_handle);
+                }
+                FunctionEnum::Equalize => {
+                    self.workspace_manager.equalize(&self.loop_handle);
+                }
+                FunctionEnum::Promote => {
+                    self.workspace_manager.promote(&self.loop_handle);
+                }
+                FunctionEnum::MasterCount(delta) => {
+                    self.workspace_manager
+                        .change_master_count(delta, &self.loop_handle);
+                }
+                FunctionEnum::ColumnWidth => {
+                    self.workspace_manager.cycle_column_width(&self.loop_handle);
+                }
+                FunctionEnum::ConsumeOrExpel(direction) => {
+                    self.workspace_manager
+                        .consume_or_expel(&direction, &self.loop_handle);
+                }
+                FunctionEnum::Container(kind) => {
+                    self.workspace_manager
+                        .toggle_container(kind, &self.loop_handle);
+                }
+                FunctionEnum::CycleTab(delta) => {
+                    if let Some(window) = self.workspace_manager.cycle_tab(delta, &self.loop_handle)
+                    {
+                        self.focus_window(&window, serial);
                     }
-                    FunctionEnum::Quit => {
-                        if let Some(focus) = &self.workspace_manager.current_workspace().focus() {
-                            info!("quit");
-                            let toplevel = focus.toplevel().unwrap();
-                            toplevel.send_close();
-                        }
+                }
+                FunctionEnum::ResizeMode => {
+                    self.input_manager.set_submap(Some(Submap::Resize));
+                }
+                FunctionEnum::Quit => {
+                    if let Some(focus) = &self.workspace_manager.current_workspace().focus() {
+                        info!("quit");
+                        let toplevel = focus.toplevel().unwrap();
+                        toplevel.send_close();
                     }
-                    FunctionEnum::Up(direction)
-                    | FunctionEnum::Down(direction)
-                    | FunctionEnum::Left(direction)
-                    | FunctionEnum::Right(direction) => {
+                }
+                FunctionEnum::Up(direction)
+                | FunctionEnum::Down(direction)
+                | FunctionEnum::Left(direction)
+                | FunctionEnum::Right(direction) => {
+                    self.workspace_manager
+                        .exchange_window(&direction, &self.loop_handle);
+                }
+                FunctionEnum::Kill => {
+                    info!("Kill the full compositor");
+                    std::process::exit(0);
+                }
+                FunctionEnum::Json => match (self.workspace_manager.layout(), layout_save_path()) {
+                    (Some(json_tree), Some(path)) => json_tree.to_json(&path),
+                    (None, _) => warn!("No tiled layout to save"),
+                    (_, None) => warn!("Failed to find a state dir for the layout"),
+                },
+                FunctionEnum::JsonLoad => {
+                    if let Some(json_tree) =
+                        layout_load_path().and_then(|path| JsonTree::from_json(&path))
+                    {
                         self.workspace_manager
-                            .exchange_window(&direction, &self.loop_handle);
+                            .load_template(json_tree, &self.loop_handle);
                     }
-                    FunctionEnum::Kill => {
-                        info!("Kill the full compositor");
-                        std::process::exit(0);
+                }
+                FunctionEnum::Reload => {
+                    if let Err(err) = self.reload() {
+                        warn!("Failed to reload: {:#}", err);
                     }
-                    FunctionEnum::Json => {
-                        // TODO
+                }
+            },
+        }
+    }
+
+    fn submap_key(&mut self, submap: Submap, name: &str) {
+        match submap {
+            Submap::Resize => {
+                // Right and Down grow, Left and Up shrink, using the far
+                // border when the window has no border on that side
+                let (direction, amount) = match name {
+                    "Escape" | "Return" => {
+                        self.input_manager.set_submap(None);
+                        return;
                     }
-                },
+                    "Left" => (Direction::Right, -1),
+                    "Right" => (Direction::Right, 1),
+                    "Up" => (Direction::Down, -1),
+                    "Down" => (Direction::Down, 1),
+                    _ => return,
+                };
+
+                let amount = amount as f64 * self.configs.conf_workspaces.resize_step;
+                if !self
+                    .workspace_manager
+                    .resize_focus(&direction, amount, &self.loop_handle)
+                {
+                    self.workspace_manager.resize_focus(
+                        &direction.opposite(),
+                        amount,
+                        &self.loop_handle,
+                    );
+                }
             }
         }
     }
@@ -170,5 +308,145 @@ impl GlobalData {
 
         keyboard.set_focus(self, surface, serial);
     }
-}
 
+    pub fn focus_direction(&mut self, direction: &Direction, serial: Serial) {
+        if let Some(window) = self
+            .workspace_manager
+            .focus_direction(direction, &self.loop_handle)
+        {
+            self.focus_window(&window, serial);
+            return;
+        }
+
+        // nothing further on this side, cross to the neighboring output
+        let current = self.output_manager.current_output();
+        let Some(output) = self
+            .output_manager
+            .neighbor_output(current, direction)
+            .cloned()
+        else {
+            return;
+        };
+        let Some(workspace_id) = self.workspace_manager.visible_on(&output) else {
+            warn!("No workspace on output {}", output.name());
+            return;
+        };
+
+        self.output_manager.set_current(&output);
+        self.set_keyboard_focus(None, serial);
+        self.workspace_manager
+            .set_activated(workspace_id, &self.loop_handle);
+
+        match self.workspace_manager.current_workspace().focus().cloned() {
+            Some(window) => {
+                self.workspace_manager.set_focus(Some(window.clone()));
+                self.focus_window(&window, serial);
+            }
+            None => {
+                if let Some(geo) = self.output_manager.output_geometry(&output) {
+                    if self.configs.conf_general.warp_pointer {
+                        let center = geo.loc + geo.size.downscale(2).to_point();
+                        self.warp_pointer(center.to_f64(), serial);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Show the workspace called `name`, on the output its rule names if it
+    /// has to be created.
+    pub fn switch_workspace(&mut self, name: &str, serial: Serial) {
+        let output = self.workspace_output(name);
+        let Some(output_geo) = self.output_manager.output_geometry(&output) else {
+            warn!("Failed to get output {:?} geometry", output);
+            return;
+        };
+
+        self.set_keyboard_focus(None, serial);
+        self.workspace_manager
+            .switch_to(name, &output, output_geo, &self.loop_handle);
+        self.output_manager.set_current(&output);
+
+        if let Some(window) = self.workspace_manager.current_workspace().focus().cloned() {
+            self.workspace_manager.set_focus(Some(window.clone()));
+            let surface = window.toplevel().unwrap().wl_surface().clone();
+            self.set_keyboard_focus(Some(surface), serial);
+        }
+    }
+
+    /// Move the focused window to the workspace called `name`, creating it
+    /// if needed, and switch there when `follow` is set.
+    pub fn move_to_workspace(&mut self, name: &str, follow: bool, serial: Serial) {
+        let current = self.workspace_manager.current_workspace();
+        if current.name() == name {
+            return;
+        }
+        let Some(window) = current.focus().cloned() else {
+            return;
+        };
+
+        let target = match self.workspace_manager.workspace_by_name(name) {
+            Some(workspace) => workspace.id(),
+            None => {
+                let output = self.workspace_output(name);
+                let Some(output_geo) = self.output_manager.output_geometry(&output) else {
+                    warn!("Failed to get output {:?} geometry", output);
+                    return;
+                };
+                self.workspace_manager.add_workspace(
+                    name,
+                    &output,
+                    output_geo,
+                    false,
+                    &self.loop_handle,
+                )
+            }
+        };
+
+        if !self
+            .workspace_manager
+            .move_window(&window, target, &self.loop_handle)
+        {
+            return;
+        }
+        self.window_manager.move_window(&window, target);
+
+        if follow {
+            self.switch_workspace(name, serial);
+        } else {
+            let surface = self
+                .workspace_manager
+                .current_workspace()
+                .focus()
+                .map(|focus| focus.toplevel().unwrap().wl_surface().clone());
+            self.set_keyboard_focus(surface, serial);
+        }
+    }
+
+    // where the workspace lives, or would be created
+    fn workspace_output(&self, name: &str) -> Output {
+        match self.workspace_manager.workspace_by_name(name) {
+            Some(workspace) => workspace.output().clone(),
+            None => self
+                .configs
+                .conf_workspaces
+                .rule(name)
+                .and_then(|rule| rule.output.as_deref())
+                .and_then(|output| self.output_manager.output_by_name(output))
+                .unwrap_or_else(|| self.output_manager.current_output())
+                .clone(),
+        }
+    }
+
+    fn focus_window(&mut self, window: &Window, serial: Serial) {
+        let surface = window.toplevel().unwrap().wl_surface().clone();
+        self.set_keyboard_focus(Some(surface), serial);
+
+        if self.configs.conf_general.warp_pointer {
+            if let Some(rec) = self.workspace_manager.window_geometry(window) {
+                let center = rec.loc + rec.size.downscale(2).to_point();
+                self.warp_pointer(center.to_f64(), serial);
+            }
+        }
+    }
+}
@@ -9,9 +9,10 @@ use crate::state::GlobalData;
 pub struct PointerMoveSurfaceGrab {
     // TODO: can use smaller struct such as InputState
     pub start_data: PointerGrabStartData<GlobalData>,
-    #[allow(dead_code)]
     pub window: Window,
     pub initial_window_location: Point<i32, Logical>,
+    // lifted out of the tiled tree, dropped back in when the grab ends
+    pub retile: bool,
 }
 
 impl PointerGrab<GlobalData> for PointerMoveSurfaceGrab {
@@ -20,6 +21,11 @@ impl PointerGrab<GlobalData> for PointerMoveSurfaceGrab {
     }
 
     fn unset(&mut self, data: &mut GlobalData) {
+        if self.retile {
+            data.workspace_manager
+                .drop_window(self.start_data.location, &data.loop_handle);
+        }
+
         data.cursor_manager
             .set_cursor_image(CursorImageStatus::default_named());
     }
@@ -68,7 +74,12 @@ impl PointerGrab<GlobalData> for PointerMoveSurfaceGrab {
         let delta = event.location - self.start_data.location;
         self.initial_window_location += delta.to_i32_round();
 
-        // TODO
+        data.workspace_manager
+            .move_floating(&self.window, self.initial_window_location);
+
+        if self.retile {
+            data.workspace_manager.update_drop(event.location);
+        }
 
         self.start_data.location = event.location;
     }
@@ -82,6 +82,7 @@ impl GlobalData {
             return;
         } else {
             let new_position = position + event.delta();
+            self.focus_output_at(new_position);
 
             // clamp to screen limits
             // this event is never generated by winit
@@ -313,11 +314,20 @@ impl GlobalData {
 
         let layer_map = layer_map_for_output(&output);
 
-        // TODO: First is full screen
+        // a fullscreen window covers the top layer, the overlay one stays above it
+        let fullscreen = self
+            .workspace_manager
+            .visible_workspace(&output)
+            .is_some_and(|w| w.fullscreen().is_some());
+
         // The layer
         if let Some(layer) = layer_map
             .layer_under(WlrLayer::Overlay, position - output_geo.loc.to_f64())
-            .or_else(|| layer_map.layer_under(WlrLayer::Top, position - output_geo.loc.to_f64()))
+            .or_else(|| {
+                layer_map
+                    .layer_under(WlrLayer::Top, position - output_geo.loc.to_f64())
+                    .filter(|_| !fullscreen)
+            })
         {
             let layer_loc = layer_map.layer_geometry(layer).unwrap().loc.to_f64();
 
@@ -329,7 +339,10 @@ impl GlobalData {
                     self.set_keyboard_focus(Some(surface.clone()), serial);
                 }
 
-                return Some((surface, surface_loc.to_f64() + layer_loc));
+                return Some((
+                    surface,
+                    surface_loc.to_f64() + layer_loc + output_geo.loc.to_f64(),
+                ));
             }
         }
         // The window
@@ -358,21 +371,67 @@ impl GlobalData {
                     self.set_keyboard_focus(Some(surface.clone()), serial);
                 }
 
-                return Some((surface, surface_loc.to_f64() + layer_loc));
+                return Some((
+                    surface,
+                    surface_loc.to_f64() + layer_loc + output_geo.loc.to_f64(),
+                ));
             }
         }
         None
     }
 
+    /// Move the pointer to `position` without a device event.
+    pub fn warp_pointer(&mut self, position: Point<f64, Logical>, serial: Serial) {
+        let pointer = match self.input_manager.get_pointer() {
+            Some(p) => p,
+            None => {
+                error!("get pointer error");
+                return;
+            }
+        };
+
+        let under = self.surface_under(position, serial, false);
+        pointer.motion(
+            self,
+            under,
+            &MotionEvent {
+                location: position,
+                serial,
+                time: self.clock.now().as_millis(),
+            },
+        );
+        pointer.frame(self);
+    }
+
+    // crossing onto another output focuses it and its workspace
+    fn focus_output_at(&mut self, position: Point<f64, Logical>) {
+        let Some(output) = self.output_manager.output_at(position).cloned() else {
+            return;
+        };
+        if &output == self.output_manager.current_output() {
+            return;
+        }
+
+        self.output_manager.set_current(&output);
+        if let Some(id) = self.workspace_manager.visible_on(&output) {
+            self.workspace_manager.set_activated(id, &self.loop_handle);
+        }
+    }
+
     fn clamp_coords(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
         // TODO: finish this
         let output = self.output_manager.current_output();
         let output_geo = self.output_manager.output_geometry(output).unwrap();
 
-        let x = pos.x.clamp(0.0, output_geo.size.w as f64);
-        let y = pos.y.clamp(0.0, output_geo.size.h as f64);
+        let x = pos.x.clamp(
+            output_geo.loc.x as f64,
+            (output_geo.loc.x + output_geo.size.w) as f64,
+        );
+        let y = pos.y.clamp(
+            output_geo.loc.y as f64,
+            (output_geo.loc.y + output_geo.size.h) as f64,
+        );
 
         (x, y).into()
     }
 }
-
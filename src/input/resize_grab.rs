@@ -2,18 +2,20 @@ use smithay::{
     desktop::Window,
     input::pointer::{CursorImageStatus, GrabStartData as PointerGrabStartData, PointerGrab},
     reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::{self, ResizeEdge},
-    utils::{Logical, Rectangle},
+    utils::{Logical, Point, Rectangle},
 };
 
-use crate::state::GlobalData;
+use crate::{manager::window::WindowExt, state::GlobalData};
 
 pub struct ResizeSurfaceGrab {
     start_data: PointerGrabStartData<GlobalData>,
     window: Window,
-    #[allow(dead_code)]
     edges: ResizeEdge,
-    #[allow(dead_code)]
     initial_rect: Rectangle<i32, Logical>,
+    // pointer movement since the grab started
+    delta: Point<f64, Logical>,
+    // part of delta already applied to the tiled tree
+    applied: Point<i32, Logical>,
 }
 
 impl ResizeSurfaceGrab {
@@ -34,8 +36,63 @@ impl ResizeSurfaceGrab {
             window,
             edges,
             initial_rect,
+            delta: (0.0, 0.0).into(),
+            applied: (0, 0).into(),
         }
     }
+
+    // the initial rectangle dragged by the grabbed edges, clamped to the client limits
+    fn floating_rec(&self) -> Rectangle<i32, Logical> {
+        let delta = self.delta.to_i32_round::<i32>();
+        let (min_size, max_size) = self.window.size_limits();
+
+        let left = matches!(
+            self.edges,
+            ResizeEdge::Left | ResizeEdge::TopLeft | ResizeEdge::BottomLeft
+        );
+        let right = matches!(
+            self.edges,
+            ResizeEdge::Right | ResizeEdge::TopRight | ResizeEdge::BottomRight
+        );
+        let top = matches!(
+            self.edges,
+            ResizeEdge::Top | ResizeEdge::TopLeft | ResizeEdge::TopRight
+        );
+        let bottom = matches!(
+            self.edges,
+            ResizeEdge::Bottom | ResizeEdge::BottomLeft | ResizeEdge::BottomRight
+        );
+
+        let clamp = |size: i32, min: i32, max: i32| {
+            let size = size.max(min.max(1));
+            if max > 0 { size.min(max) } else { size }
+        };
+
+        let mut rec = self.initial_rect;
+        if left {
+            rec.size.w = self.initial_rect.size.w - delta.x;
+        } else if right {
+            rec.size.w = self.initial_rect.size.w + delta.x;
+        }
+        if top {
+            rec.size.h = self.initial_rect.size.h - delta.y;
+        } else if bottom {
+            rec.size.h = self.initial_rect.size.h + delta.y;
+        }
+
+        rec.size.w = clamp(rec.size.w, min_size.w, max_size.w);
+        rec.size.h = clamp(rec.size.h, min_size.h, max_size.h);
+
+        // keep the opposite edge in place
+        if left {
+            rec.loc.x += self.initial_rect.size.w - rec.size.w;
+        }
+        if top {
+            rec.loc.y += self.initial_rect.size.h - rec.size.h;
+        }
+
+        rec
+    }
 }
 
 impl PointerGrab<GlobalData> for ResizeSurfaceGrab {
@@ -67,54 +124,22 @@ impl PointerGrab<GlobalData> for ResizeSurfaceGrab {
     ) {
         handle.motion(data, None, event);
 
-        let _delta = event.location - self.start_data.location;
-
-        // data.workspace_manager
-        //     .resize(delta.to_i32_round(), &self.edges, &mut self.initial_rect);
-
+        let delta = event.location - self.start_data.location;
+        self.delta += delta;
         self.start_data.location = event.location;
 
-        // let mut new_window_width = self.initial_rect.size.w;
-        // let mut new_window_height = self.initial_rect.size.h;
-
-        // if self.edges.intersects(ResizeEdge::LEFT | ResizeEdge::RIGHT) {
-        //     // if self.edges.intersects(ResizeEdge::LEFT) {
-        //     //     delta.x = -delta.x
-        //     // }
-        //     new_window_width = (self.initial_rect.size.w as f64 + delta.x) as i32;
-        // }
-        // if self.edges.intersects(ResizeEdge::TOP | ResizeEdge::BOTTOM) {
-        //     // if self.edges.intersects(ResizeEdge::TOP) {
-        //     //     delta.y = -delta.y;
-        //     // }
-        //     new_window_height = (self.initial_rect.size.h as f64 + delta.y) as i32;
-        // }
-
-        // let (min_size, max_size) =
-        //     compositor::with_states(self.window.toplevel().unwrap().wl_surface(), |states| {
-        //         let mut guard = states.cached_state.get::<SurfaceCachedState>();
-        //         let data = guard.current();
-        //         (data.min_size, data.max_size)
-        //     });
-
-        // let min_width = min_size.w.max(1);
-        // let min_height = min_size.h.max(1);
-
-        // let max_width = (max_size.w == 0).then(i32::max_value).unwrap_or(max_size.w);
-        // let max_height = (max_size.h == 0).then(i32::max_value).unwrap_or(max_size.h);
-
-        // self.last_window_size = Size::from((
-        //     new_window_width.max(min_width).min(max_width),
-        //     new_window_height.max(min_height).min(max_height),
-        // ));
-
-        // let xdg = self.window.toplevel().unwrap();
-        // xdg.with_pending_state(|state| {
-        //     state.states.set(xdg_toplevel::State::Resizing);
-        //     state.size = Some(self.last_window_size);
-        // });
-
-        // xdg.send_pending_configure();
+        if data.workspace_manager.is_floating(&self.window) {
+            let rec = self.floating_rec();
+            data.workspace_manager.resize_floating(&self.window, rec);
+        } else {
+            // only hand whole pixels to the tree, the rest stays in self.delta
+            let total = self.delta.to_i32_round::<i32>();
+            let step = total - self.applied;
+            self.applied = total;
+
+            data.workspace_manager
+                .resize(&self.window, self.edges, step, &data.loop_handle);
+        }
     }
 
     fn relative_motion(
@@ -0,0 +1,553 @@
+pub mod protocol;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use smithay::{
+    reexports::calloop::{
+        Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+        channel::{self, Channel, Sender},
+        generic::Generic,
+    },
+    utils::{Logical, Rectangle, SERIAL_COUNTER},
+};
+
+use crate::{
+    layout::json_tiled_tree::JsonTree,
+    manager::{
+        input::{FunctionEnum, KeyAction},
+        window::WindowExt,
+    },
+    state::GlobalData,
+    utils::errors::AnyHowErr,
+};
+
+use protocol::{
+    Event, EventKind, Geometry, OutputInfo, Request, Response, WindowInfo, WorkspaceInfo,
+};
+
+// drop clients that keep sending data without ever finishing a line
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+// drop clients that stopped reading, rather than buffering for them forever
+const MAX_PENDING_SIZE: usize = 4 * 1024 * 1024;
+
+// Cloned into the managers, events are queued on the main loop and fanned
+// out to subscribers from there.
+#[derive(Clone)]
+pub struct EventSender(Sender<Event>);
+
+impl EventSender {
+    pub fn send(&self, event: Event) {
+        // only fails once the loop is gone
+        let _ = self.0.send(event);
+    }
+}
+
+impl fmt::Debug for EventSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSender").finish_non_exhaustive()
+    }
+}
+
+pub fn event_channel(loop_handle: &LoopHandle<'static, GlobalData>) -> anyhow::Result<EventSender> {
+    let (sender, channel): (Sender<Event>, Channel<Event>) = channel::channel();
+
+    loop_handle
+        .insert_source(channel, |event, _, data| {
+            if let (channel::Event::Msg(event), Some(ipc_server)) = (event, &mut data.ipc_server) {
+                ipc_server.broadcast(&event, &data.loop_handle);
+            }
+        })
+        .anyhow_err("Failed to init ipc event source")?;
+
+    Ok(EventSender(sender))
+}
+
+// A connection, written to without ever blocking the compositor. What the
+// socket can't take yet waits in `pending` for a writable source.
+#[derive(Debug)]
+struct Client {
+    stream: UnixStream,
+    pending: Vec<u8>,
+    // the writable source, registered while something is pending
+    writer: Option<RegistrationToken>,
+    // the events a subscriber wants, None for request clients
+    events: Option<Vec<EventKind>>,
+    // the client is done sending, dropped once everything is written
+    closing: bool,
+}
+
+impl Client {
+    // returns true once nothing is left to write
+    fn flush(&mut self) -> std::io::Result<bool> {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(false),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[derive(Debug)]
+pub struct IpcServer {
+    pub socket_path: PathBuf,
+    clients: HashMap<u64, Client>,
+    next_id: u64,
+}
+
+impl IpcServer {
+    pub fn new(
+        loop_handle: &LoopHandle<'static, GlobalData>,
+        wayland_display: &str,
+    ) -> anyhow::Result<Self> {
+        let socket_dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        let socket_path = socket_dir.join(format!(
+            "mondrian.{}.{}.sock",
+            wayland_display,
+            std::process::id()
+        ));
+
+        // a stale socket from a crashed session with the same pid
+        let _ = std::fs::remove_file(&socket_path);
+
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("Failed to bind ipc socket {:?}", socket_path))?;
+        listener
+            .set_nonblocking(true)
+            .context("Failed to set ipc socket nonblocking")?;
+
+        loop_handle
+            .insert_source(
+                Generic::new(listener, Interest::READ, Mode::Level),
+                |_, listener, data| {
+                    // Safety: we don't drop the listener
+                    let listener = unsafe { listener.get_mut() };
+
+                    loop {
+                        match listener.accept() {
+                            Ok((stream, _)) => {
+                                let Some(ipc_server) = &mut data.ipc_server else {
+                                    break;
+                                };
+                                if let Err(err) =
+                                    ipc_server.insert_client(&data.loop_handle, stream)
+                                {
+                                    warn!("Failed to insert ipc client: {:?}", err);
+                                }
+                            }
+                            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                            Err(err) => {
+                                warn!("Failed to accept ipc client: {}", err);
+                                break;
+                            }
+                        }
+                    }
+
+                    Ok(PostAction::Continue)
+                },
+            )
+            .anyhow_err("Failed to init ipc source")?;
+
+        info!(path = ?socket_path, "Listening on ipc socket.");
+
+        Ok(Self {
+            socket_path,
+            clients: HashMap::new(),
+            next_id: 0,
+        })
+    }
+
+    fn insert_client(
+        &mut self,
+        loop_handle: &LoopHandle<'static, GlobalData>,
+        stream: UnixStream,
+    ) -> anyhow::Result<()> {
+        stream
+            .set_nonblocking(true)
+            .context("Failed to set ipc client nonblocking")?;
+        let reader = stream.try_clone().context("Failed to clone ipc client")?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut buffer: Vec<u8> = Vec::new();
+
+        loop_handle
+            .insert_source(
+                Generic::new(reader, Interest::READ, Mode::Level),
+                move |_, reader, data| {
+                    // Safety: we don't drop the stream
+                    let reader = unsafe { reader.get_mut() };
+
+                    let Some(ipc_server) = &mut data.ipc_server else {
+                        return Ok(PostAction::Remove);
+                    };
+                    // dropped for not reading its responses
+                    if !ipc_server.clients.contains_key(&id) {
+                        return Ok(PostAction::Remove);
+                    }
+
+                    let mut closed = false;
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        match reader.read(&mut chunk) {
+                            Ok(0) => {
+                                closed = true;
+                                break;
+                            }
+                            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                            Err(err) => {
+                                warn!("Failed to read ipc client: {}", err);
+                                ipc_server.remove_client(id, &data.loop_handle);
+                                return Ok(PostAction::Remove);
+                            }
+                        }
+                    }
+
+                    while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                        let line: Vec<u8> = buffer.drain(..=pos).collect();
+                        if line.iter().all(u8::is_ascii_whitespace) {
+                            continue;
+                        }
+
+                        let response = match serde_json::from_slice::<Request>(&line) {
+                            // the connection only carries events from now on
+                            Ok(Request::Subscribe { events }) => {
+                                if let Some(ipc_server) = &mut data.ipc_server {
+                                    ipc_server.subscribe(id, events, &data.loop_handle);
+                                }
+                                return Ok(PostAction::Remove);
+                            }
+                            Ok(request) => data.handle_ipc_request(request),
+                            Err(err) => Response::error(format!("Invalid request: {}", err)),
+                        };
+
+                        if let Some(ipc_server) = &mut data.ipc_server {
+                            ipc_server.send(id, &response, &data.loop_handle);
+                        }
+                    }
+
+                    if closed || buffer.len() > MAX_REQUEST_SIZE {
+                        if let Some(ipc_server) = &mut data.ipc_server {
+                            ipc_server.close_client(id, &data.loop_handle);
+                        }
+                        return Ok(PostAction::Remove);
+                    }
+
+                    Ok(PostAction::Continue)
+                },
+            )
+            .anyhow_err("Failed to init ipc client source")?;
+
+        self.clients.insert(
+            id,
+            Client {
+                stream,
+                pending: Vec::new(),
+                writer: None,
+                events: None,
+                closing: false,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn subscribe(
+        &mut self,
+        id: u64,
+        events: Vec<EventKind>,
+        loop_handle: &LoopHandle<'static, GlobalData>,
+    ) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.events = Some(events);
+            self.send(id, &Response::Ok, loop_handle);
+        }
+    }
+
+    pub fn broadcast(&mut self, event: &Event, loop_handle: &LoopHandle<'static, GlobalData>) {
+        let kind = event.kind();
+        let subscribers: Vec<u64> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| {
+                client
+                    .events
+                    .as_ref()
+                    .is_some_and(|events| events.is_empty() || events.contains(&kind))
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in subscribers {
+            self.send(id, event, loop_handle);
+        }
+    }
+
+    // queue one line of JSON and write what the socket takes right away
+    fn send<T: Serialize>(
+        &mut self,
+        id: u64,
+        message: &T,
+        loop_handle: &LoopHandle<'static, GlobalData>,
+    ) {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+
+        match serde_json::to_vec(message) {
+            Ok(bytes) => {
+                client.pending.extend_from_slice(&bytes);
+                client.pending.push(b'\n');
+            }
+            Err(err) => {
+                warn!("Failed to serialize ipc message: {}", err);
+                return;
+            }
+        }
+
+        match client.flush() {
+            Ok(true) => {}
+            Ok(false) if client.pending.len() > MAX_PENDING_SIZE => {
+                info!("Dropping ipc client: not reading");
+                self.remove_client(id, loop_handle);
+            }
+            Ok(false) if client.writer.is_none() => {
+                let writer = self.insert_writer(id, loop_handle);
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.writer = writer;
+                }
+            }
+            Ok(false) => {}
+            Err(err) => {
+                info!("Dropping ipc client: {}", err);
+                self.remove_client(id, loop_handle);
+            }
+        }
+    }
+
+    // a source that flushes the client whenever its socket takes more
+    fn insert_writer(
+        &self,
+        id: u64,
+        loop_handle: &LoopHandle<'static, GlobalData>,
+    ) -> Option<RegistrationToken> {
+        let stream = match self.clients.get(&id)?.stream.try_clone() {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to clone ipc client: {}", err);
+                return None;
+            }
+        };
+
+        let token = loop_handle.insert_source(
+            Generic::new(stream, Interest::WRITE, Mode::Level),
+            move |_, _, data| {
+                let Some(ipc_server) = &mut data.ipc_server else {
+                    return Ok(PostAction::Remove);
+                };
+                Ok(ipc_server.writable(id))
+            },
+        );
+
+        match token {
+            Ok(token) => Some(token),
+            Err(err) => {
+                warn!("Failed to init ipc writer source: {}", err);
+                None
+            }
+        }
+    }
+
+    fn writable(&mut self, id: u64) -> PostAction {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return PostAction::Remove;
+        };
+
+        match client.flush() {
+            Ok(false) => return PostAction::Continue,
+            Ok(true) => client.writer = None,
+            Err(err) => {
+                info!("Dropping ipc client: {}", err);
+                client.closing = true;
+                client.writer = None;
+            }
+        }
+
+        if client.closing {
+            self.clients.remove(&id);
+        }
+        PostAction::Remove
+    }
+
+    // the client is done sending, keep it until its responses are written
+    fn close_client(&mut self, id: u64, loop_handle: &LoopHandle<'static, GlobalData>) {
+        match self.clients.get_mut(&id) {
+            Some(client) if client.writer.is_some() => client.closing = true,
+            Some(_) => self.remove_client(id, loop_handle),
+            None => {}
+        }
+    }
+
+    fn remove_client(&mut self, id: u64, loop_handle: &LoopHandle<'static, GlobalData>) {
+        if let Some(token) = self.clients.remove(&id).and_then(|client| client.writer) {
+            loop_handle.remove(token);
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+fn geometry(rec: Rectangle<i32, Logical>) -> Geometry {
+    Geometry {
+        x: rec.loc.x,
+        y: rec.loc.y,
+        width: rec.size.w,
+        height: rec.size.h,
+    }
+}
+
+impl GlobalData {
+    pub fn handle_ipc_request(&mut self, request: Request) -> Response {
+        match request {
+            Request::Action { action } => match FunctionEnum::from_name(&action) {
+                Some(function) => {
+                    self.run_action(KeyAction::Internal(function), SERIAL_COUNTER.next_serial());
+                    Response::Ok
+                }
+                None => Response::error(format!("Unknown action: {}", action)),
+            },
+            Request::Spawn { command, args } => {
+                self.run_action(
+                    KeyAction::Command(command, args),
+                    SERIAL_COUNTER.next_serial(),
+                );
+                Response::Ok
+            }
+            Request::Workspace { workspace } => {
+                let name = workspace.trim();
+                if name.is_empty() {
+                    return Response::error("Empty workspace name");
+                }
+
+                self.switch_workspace(name, SERIAL_COUNTER.next_serial());
+                Response::Ok
+            }
+            Request::Layout { tree } => match serde_json::from_value::<JsonTree>(tree) {
+                Ok(json_tree) => {
+                    self.workspace_manager
+                        .load_template(json_tree, &self.loop_handle);
+                    Response::Ok
+                }
+                Err(err) => Response::error(format!("Invalid layout: {}", err)),
+            },
+            Request::Reload => match self.reload() {
+                Ok(()) => Response::Ok,
+                Err(err) => Response::error(format!("{:#}", err)),
+            },
+            Request::Workspaces => Response::Workspaces {
+                workspaces: self.workspaces_info(),
+            },
+            Request::Windows => Response::Windows {
+                windows: self.windows_info(),
+            },
+            Request::Outputs => Response::Outputs {
+                outputs: self.outputs_info(),
+            },
+            Request::Tree => match self.workspace_manager.layout() {
+                Some(json_tree) => match serde_json::to_value(&json_tree) {
+                    Ok(tree) => Response::Tree { tree: Some(tree) },
+                    Err(err) => Response::error(format!("Failed to serialize tree: {}", err)),
+                },
+                None => Response::Tree { tree: None },
+            },
+            // handled by the client source, it knows the connection
+            Request::Subscribe { .. } => Response::error("Subscribe needs its own connection"),
+        }
+    }
+
+    fn workspaces_info(&self) -> Vec<WorkspaceInfo> {
+        let activated = self.workspace_manager.activated_workspace();
+
+        self.workspace_manager
+            .workspaces()
+            .map(|workspace| WorkspaceInfo {
+                id: workspace.id().get(),
+                name: workspace.name().to_string(),
+                scheme: format!("{:?}", workspace.scheme()),
+                windows: workspace.elements().count(),
+                focus: workspace.focus().map(|w| w.id().get()),
+                active: activated == Some(workspace.id()),
+            })
+            .collect()
+    }
+
+    fn windows_info(&self) -> Vec<WindowInfo> {
+        let focus = self.workspace_manager.current_workspace().focus();
+
+        self.window_manager
+            .windows
+            .iter()
+            .map(|window| {
+                let (title, app_id) = window.get_title_and_id().unwrap_or((None, None));
+                let workspace_id = self.window_manager.window_workspace.get(window).copied();
+                let workspace = workspace_id.and_then(|id| self.workspace_manager.workspace(id));
+                let rec = workspace.and_then(|workspace| workspace.window_geometry(window));
+
+                WindowInfo {
+                    id: window.id().get(),
+                    title,
+                    app_id,
+                    workspace: workspace_id.map(|id| id.get()),
+                    geometry: rec.map(geometry),
+                    focused: focus == Some(window),
+                    floating: workspace.is_some_and(|workspace| workspace.is_floating(window)),
+                }
+            })
+            .collect()
+    }
+
+    fn outputs_info(&self) -> Vec<OutputInfo> {
+        self.output_manager
+            .outputs
+            .iter()
+            .map(|element| {
+                let output = element.output();
+                let properties = output.physical_properties();
+
+                OutputInfo {
+                    name: output.name(),
+                    make: properties.make,
+                    model: properties.model,
+                    geometry: self.output_manager.output_geometry(output).map(geometry),
+                    refresh: output.current_mode().map(|mode| mode.refresh),
+                    scale: output.current_scale().fractional_scale(),
+                    active: element.activate,
+                }
+            })
+            .collect()
+    }
+}
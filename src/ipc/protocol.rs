@@ -0,0 +1,196 @@
+// Wire format of the ipc socket, shared with mondrianctl.
+//
+// Every message is a single line of JSON terminated by '\n'. A client writes
+// one `Request` per line and reads exactly one `Response` line back.
+//
+// After a `Subscribe` request is answered with `Ok`, the connection stops
+// taking requests and only carries `Event` lines until the client hangs up.
+//
+// Keep this file free of crate:: imports, the control client includes it
+// directly with #[path]. Not every item is used on both sides.
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+
+pub const SOCKET_ENV: &str = "MONDRIAN_SOCKET";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    // run an internal function, same names as `exec` keybindings
+    Action {
+        action: String,
+    },
+    Spawn {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    // switch to a workspace by name, created when missing
+    Workspace {
+        workspace: String,
+    },
+    // load a tree in the format of `Tree` as the layout template
+    Layout {
+        tree: serde_json::Value,
+    },
+    Reload,
+    Workspaces,
+    Windows,
+    Outputs,
+    Tree,
+    // an empty list subscribes to every kind of event
+    Subscribe {
+        #[serde(default)]
+        events: Vec<EventKind>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Error { message: String },
+    Workspaces { workspaces: Vec<WorkspaceInfo> },
+    Windows { windows: Vec<WindowInfo> },
+    Outputs { outputs: Vec<OutputInfo> },
+    Tree { tree: Option<serde_json::Value> },
+}
+
+impl Response {
+    pub fn error(message: impl Into<String>) -> Self {
+        Response::Error {
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    pub id: usize,
+    #[serde(default)]
+    pub name: String,
+    pub scheme: String,
+    pub windows: usize,
+    pub focus: Option<u64>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowInfo {
+    pub id: u64,
+    pub title: Option<String>,
+    pub app_id: Option<String>,
+    pub workspace: Option<usize>,
+    pub geometry: Option<Geometry>,
+    pub focused: bool,
+    #[serde(default)]
+    pub floating: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputInfo {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub geometry: Option<Geometry>,
+    pub refresh: Option<i32>,
+    pub scale: f64,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Window,
+    Focus,
+    Workspace,
+    Title,
+    Output,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 5] = [
+        EventKind::Window,
+        EventKind::Focus,
+        EventKind::Workspace,
+        EventKind::Title,
+        EventKind::Output,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        EventKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Window => "window",
+            EventKind::Focus => "focus",
+            EventKind::Workspace => "workspace",
+            EventKind::Title => "title",
+            EventKind::Output => "output",
+        }
+    }
+}
+
+// Fields are only ever added to these variants, never renamed or removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    WindowAdded {
+        id: u64,
+        workspace: usize,
+    },
+    WindowRemoved {
+        id: u64,
+        workspace: Option<usize>,
+    },
+    WindowMoved {
+        id: u64,
+        workspace: usize,
+        previous: Option<usize>,
+    },
+    FocusChanged {
+        workspace: usize,
+        window: Option<u64>,
+    },
+    WorkspaceActivated {
+        id: usize,
+        previous: Option<usize>,
+        #[serde(default)]
+        name: String,
+    },
+    TitleChanged {
+        id: u64,
+        title: Option<String>,
+        app_id: Option<String>,
+    },
+    OutputAdded {
+        name: String,
+    },
+    OutputRemoved {
+        name: String,
+    },
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::WindowAdded { .. } | Event::WindowRemoved { .. } | Event::WindowMoved { .. } => {
+                EventKind::Window
+            }
+            Event::FocusChanged { .. } => EventKind::Focus,
+            Event::WorkspaceActivated { .. } => EventKind::Workspace,
+            Event::TitleChanged { .. } => EventKind::Title,
+            Event::OutputAdded { .. } | Event::OutputRemoved { .. } => EventKind::Output,
+        }
+    }
+}
@@ -1,75 +1,352 @@
-use std::fs;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
+use smithay::desktop::Window;
 
-use super::{Direction, tiled_tree::TiledTree};
+use crate::{
+    config::{config_dir, state_dir},
+    manager::window::WindowExt,
+};
 
-#[derive(Serialize, Deserialize)]
+use super::{
+    Direction,
+    tiled_tree::{ContainerKind, NodeData, NodeId, TiledTree},
+};
+
+const LAYOUT_FILE: &str = "group.json";
+
+// the `json` binding saves the focused layout in the state dir
+pub fn layout_save_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join(LAYOUT_FILE))
+}
+
+// the last saved layout, or one written by hand next to the configs
+pub fn layout_load_path() -> Option<PathBuf> {
+    layout_save_path()
+        .filter(|path| path.exists())
+        .or_else(|| config_dir().map(|dir| dir.join(LAYOUT_FILE)))
+}
+
+/// The split axis saved in json, a split only cares about how it divides the
+/// rectangle, the left/up child always comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitAxis {
+    #[serde(alias = "Left", alias = "Right")]
+    Horizontal,
+    #[serde(alias = "Up", alias = "Down")]
+    Vertical,
+}
+
+impl SplitAxis {
+    pub fn direction(&self) -> Direction {
+        match self {
+            SplitAxis::Horizontal => Direction::Right,
+            SplitAxis::Vertical => Direction::Down,
+        }
+    }
+}
+
+impl From<&Direction> for SplitAxis {
+    fn from(direction: &Direction) -> Self {
+        match direction {
+            Direction::Left | Direction::Right => SplitAxis::Horizontal,
+            Direction::Up | Direction::Down => SplitAxis::Vertical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
-enum JsonNode {
+pub enum JsonNode {
     Leaf {
         app_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
     },
     Split {
-        direction: Direction,
-        offset: (i32, i32),
+        direction: SplitAxis,
+        // share of the left child, layouts saved with pixel offsets load as halves
+        #[serde(default = "half")]
+        ratio: f64,
         left: Box<JsonNode>,
         right: Box<JsonNode>,
     },
+    Container {
+        kind: ContainerKind,
+        tabs: Vec<JsonNode>,
+    },
 }
 
-#[derive(Serialize, Deserialize)]
+fn half() -> f64 {
+    0.5
+}
+
+impl JsonNode {
+    /// Collect the leaves' app_id from left to right.
+    pub fn leaves(&self) -> Vec<&str> {
+        match self {
+            JsonNode::Leaf { app_id, .. } => vec![app_id.as_str()],
+            JsonNode::Split { left, right, .. } => {
+                let mut leaves = left.leaves();
+                leaves.extend(right.leaves());
+                leaves
+            }
+            JsonNode::Container { tabs, .. } => tabs.iter().flat_map(JsonNode::leaves).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonTree {
-    tiled_tree: JsonNode,
+    pub tiled_tree: JsonNode,
 }
 
-impl From<TiledTree> for JsonTree {
-    fn from(_value: TiledTree) -> Self {
-        todo!()
+impl TryFrom<&TiledTree<Window>> for JsonTree {
+    type Error = anyhow::Error;
+
+    fn try_from(tree: &TiledTree<Window>) -> Result<Self, Self::Error> {
+        fn leaf(window: &Window) -> JsonNode {
+            let (title, app_id) = window.get_title_and_id().unwrap_or((None, None));
+            JsonNode::Leaf {
+                app_id: app_id.unwrap_or_default(),
+                title,
+            }
+        }
+
+        fn convert(tree: &TiledTree<Window>, id: NodeId) -> anyhow::Result<JsonNode> {
+            match tree.get_node(id) {
+                Some(NodeData::Leaf { window, .. }) => Ok(leaf(window)),
+                Some(NodeData::Split {
+                    direction,
+                    ratio,
+                    left,
+                    right,
+                    ..
+                }) => Ok(JsonNode::Split {
+                    direction: direction.into(),
+                    ratio: *ratio,
+                    left: Box::new(convert(tree, *left)?),
+                    right: Box::new(convert(tree, *right)?),
+                }),
+                Some(NodeData::Container { kind, windows, .. }) => Ok(JsonNode::Container {
+                    kind: *kind,
+                    tabs: windows.iter().map(leaf).collect(),
+                }),
+                None => anyhow::bail!("node {:?} not found in tiled tree", id),
+            }
+        }
+
+        let root_id = tree
+            .get_root()
+            .ok_or_else(|| anyhow::anyhow!("empty tiled tree"))?;
+
+        Ok(Self {
+            tiled_tree: convert(tree, root_id)?,
+        })
     }
 }
 
-#[allow(dead_code)]
 impl JsonTree {
-    pub fn from_json(path: &str) -> Option<Self> {
+    pub fn from_json(path: &Path) -> Option<Self> {
         match fs::read_to_string(path) {
             Ok(data) => match serde_json::from_str(&data) {
                 Ok(tree) => Some(tree),
                 Err(err) => {
-                    warn!("Failed to deserialize JSON from {}: {:?}", path, err);
+                    warn!(
+                        "Failed to deserialize JSON from {}: {:?}",
+                        path.display(),
+                        err
+                    );
                     None
                 }
             },
             Err(err) => {
-                warn!("Failed to read file: {} with err: {:?}", path, err);
+                warn!(
+                    "Failed to read file: {} with err: {:?}",
+                    path.display(),
+                    err
+                );
                 None
             }
         }
     }
 
-    pub fn _to_json(&self, path: &str) {
+    pub fn to_json(&self, path: &Path) {
+        if let Some(dir) = path.parent()
+            && let Err(err) = fs::create_dir_all(dir)
+        {
+            warn!(
+                "Failed to create dir: {} with err: {:?}",
+                dir.display(),
+                err
+            );
+            return;
+        }
+
         match fs::write(
             path,
-            serde_json::to_string(self).expect("Failed to serialize JSON"),
+            serde_json::to_string_pretty(self).expect("Failed to serialize JSON"),
         ) {
-            Ok(_) => info!("Successfully wrote JSON to {}", path),
-            Err(err) => warn!("Failed to write file: {} with err: {:?}", path, err),
+            Ok(_) => info!("Successfully wrote JSON to {}", path.display()),
+            Err(err) => warn!(
+                "Failed to write file: {} with err: {:?}",
+                path.display(),
+                err
+            ),
         }
     }
 
+    #[allow(dead_code)]
     pub fn print_tree(&self) {
         fn print(node: &JsonNode, depth: usize) {
             let indent = "  ".repeat(depth);
             match node {
-                JsonNode::Leaf { app_id } => tracing::info!("{indent}- Leaf: {:?}", app_id),
+                JsonNode::Leaf { app_id, .. } => tracing::info!("{indent}- Leaf: {:?}", app_id),
                 JsonNode::Split { left, right, .. } => {
                     tracing::info!("{indent}- Split:");
                     print(left, depth + 1);
                     print(right, depth + 1);
                 }
+                JsonNode::Container { kind, tabs } => {
+                    tracing::info!("{indent}- {:?}:", kind);
+                    for tab in tabs {
+                        print(tab, depth + 1);
+                    }
+                }
             }
         }
         print(&self.tiled_tree, 1);
     }
 }
 
+/// A loaded layout whose leaves wait for windows with the matching `app_id`.
+#[derive(Debug)]
+pub struct LayoutTemplate {
+    tree: JsonTree,
+    slots: Vec<Option<Window>>,
+}
+
+impl LayoutTemplate {
+    pub fn new(tree: JsonTree) -> Self {
+        let slots = vec![None; tree.tiled_tree.leaves().len()];
+        Self { tree, slots }
+    }
+
+    /// Put the window into the first free leaf with the same app_id.
+    pub fn claim(&mut self, window: &Window) -> bool {
+        if self.contains(window) {
+            return false;
+        }
+
+        let app_id = match window.get_title_and_id() {
+            Some((_, Some(app_id))) => app_id,
+            _ => return false,
+        };
+
+        let leaves = self.tree.tiled_tree.leaves();
+        let free = leaves
+            .iter()
+            .zip(self.slots.iter())
+            .position(|(leaf, slot)| *leaf == app_id && slot.is_none());
+
+        match free {
+            Some(index) => {
+                self.slots[index] = Some(window.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn release(&mut self, window: &Window) -> bool {
+        match self
+            .slots
+            .iter_mut()
+            .find(|slot| slot.as_ref() == Some(window))
+        {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, window: &Window) -> bool {
+        self.slots.iter().any(|slot| slot.as_ref() == Some(window))
+    }
+
+    /// The filled leaf closest to the leaf of `window` that passes `placed`,
+    /// with the side of it that `window` takes.
+    pub fn anchor(
+        &self,
+        window: &Window,
+        placed: impl Fn(&Window) -> bool,
+    ) -> Option<(Window, Direction)> {
+        let target = self
+            .slots
+            .iter()
+            .position(|slot| slot.as_ref() == Some(window))?;
+
+        // a placed window in the leaves `start..end`
+        let filled = |start: usize, end: usize| {
+            self.slots[start..end]
+                .iter()
+                .flatten()
+                .find(|w| *w != window && placed(w))
+                .cloned()
+        };
+
+        // None if the target is not in `node`, which starts at leaf `start`
+        fn search(
+            node: &JsonNode,
+            start: usize,
+            target: usize,
+            filled: &dyn Fn(usize, usize) -> Option<Window>,
+        ) -> Option<Option<(Window, Direction)>> {
+            match node {
+                JsonNode::Leaf { .. } => (start == target).then_some(None),
+                JsonNode::Split {
+                    direction,
+                    left,
+                    right,
+                    ..
+                } => {
+                    let mid = start + left.leaves().len();
+                    let end = mid + right.leaves().len();
+
+                    // the target goes before a window of the right subtree
+                    if let Some(found) = search(left, start, target, filled) {
+                        let before = match direction {
+                            SplitAxis::Horizontal => Direction::Left,
+                            SplitAxis::Vertical => Direction::Up,
+                        };
+                        return Some(found.or_else(|| filled(mid, end).map(|w| (w, before))));
+                    }
+
+                    let found = search(right, mid, target, filled)?;
+                    Some(found.or_else(|| filled(start, mid).map(|w| (w, direction.direction()))))
+                }
+                // any other tab takes it into its container
+                JsonNode::Container { .. } => {
+                    let end = start + node.leaves().len();
+                    (start..end)
+                        .contains(&target)
+                        .then(|| filled(start, end).map(|w| (w, Direction::Right)))
+                }
+            }
+        }
+
+        search(&self.tree.tiled_tree, 0, target, &filled).flatten()
+    }
+
+    /// Build a tiled tree from the leaves that already hold a window.
+    pub fn build_tree(&self, gap: i32) -> Option<TiledTree<Window>> {
+        TiledTree::from_json_node(&self.tree.tiled_tree, gap, |index| {
+            self.slots.get(index).cloned().flatten()
+        })
+    }
+}
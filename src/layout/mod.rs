@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 
 pub mod json_tiled_tree;
 pub mod neighbor_graph;
+pub mod scrolling;
 pub mod tiled_tree;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Serialize, Deserialize)]
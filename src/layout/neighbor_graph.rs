@@ -1,117 +1,78 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
 
-use smithay::desktop::Window;
+use smithay::utils::{Logical, Rectangle};
 
 use crate::layout::Direction;
 
+/// Which windows lie next to each other, derived from the tiled geometry.
 #[derive(Debug, Clone)]
-pub struct NeighborGraph {
-    edges: HashMap<Window, HashMap<Direction, Vec<Window>>>,
+pub struct NeighborGraph<W> {
+    edges: HashMap<W, HashMap<Direction, Vec<W>>>,
 }
 
-impl NeighborGraph {
+impl<W: Clone + Eq + Hash + Debug> NeighborGraph<W> {
     pub fn new() -> Self {
         Self {
             edges: HashMap::new(),
         }
     }
 
-    pub fn get(&self, window: &Window, direction: &Direction) -> Option<&Vec<Window>> {
-        self.edges.get(window)?.get(direction)
-    }
-
-    pub fn add_window(&mut self, from: Window, direction: Direction, to: Vec<Window>) {
-        self.edges
-            .entry(from)
-            .or_default()
-            .entry(direction)
-            .or_default()
-            .extend(to);
-    }
-
-    pub fn remove_window(&mut self, from: &Window, direction: Direction, to: &Window) {
-        if let Some(dir_map) = self.edges.get_mut(from) {
-            if let Some(vec) = dir_map.get_mut(&direction) {
-                vec.retain(|win| win != to);
-                if vec.is_empty() {
-                    dir_map.remove(&direction);
-                }
-            }
-            if dir_map.is_empty() {
-                self.edges.remove(from);
-            }
-        }
-    }
-
-    pub fn remove_direction(
-        &mut self,
-        target: &Window,
-        direction: &Direction,
-    ) -> Option<Vec<Window>> {
-        self.edges.get_mut(target)?.remove(direction)
-    }
+    /// Build the graph from leaf rectangles: two windows are neighbors when
+    /// they face each other across a gap and overlap on the other axis.
+    pub fn from_recs(recs: &[(W, Rectangle<i32, Logical>)], gap: i32) -> Self {
+        let mut graph = Self::new();
 
-    pub fn tiled_add(&mut self, from: Window, direction: Direction, new: Window) {
-        let opposite = direction.opposite();
-        let orthogonal = direction.orthogonal();
-
-        // new <--> orthogonal neighbors
-        for d in orthogonal {
-            if let Some(neighbors_orthogonal) = self.get(&from, &d).cloned() {
-                for neighbor in &neighbors_orthogonal {
-                    self.add_window(neighbor.clone(), d.opposite(), vec![new.clone()]);
+        for (from, a) in recs {
+            for (to, b) in recs {
+                if from == to {
+                    continue;
                 }
 
-                self.add_window(new.clone(), d.clone(), neighbors_orthogonal);
+                let overlap_x = a.loc.x < b.loc.x + b.size.w && b.loc.x < a.loc.x + a.size.w;
+                let overlap_y = a.loc.y < b.loc.y + b.size.h && b.loc.y < a.loc.y + a.size.h;
+                let facing = |near: i32, far: i32| (0..=gap * 2).contains(&(far - near));
+
+                let direction = if overlap_y && facing(a.loc.x + a.size.w, b.loc.x) {
+                    Direction::Right
+                } else if overlap_y && facing(b.loc.x + b.size.w, a.loc.x) {
+                    Direction::Left
+                } else if overlap_x && facing(a.loc.y + a.size.h, b.loc.y) {
+                    Direction::Down
+                } else if overlap_x && facing(b.loc.y + b.size.h, a.loc.y) {
+                    Direction::Up
+                } else {
+                    continue;
+                };
+
+                graph.add_window(from.clone(), direction, vec![to.clone()]);
             }
         }
 
-        // new <--> neighbors
-        if let Some(neighbors_direction) = self.remove_direction(&from, &direction) {
-            for neighbor in &neighbors_direction {
-                self.remove_window(neighbor, opposite.clone(), &from);
-                self.add_window(neighbor.clone(), opposite.clone(), vec![new.clone()]);
-            }
-
-            self.add_window(new.clone(), direction.clone(), neighbors_direction);
-        }
-
-        // new <--> from
-        self.add_window(from.clone(), direction, vec![new.clone()]);
-        self.add_window(new, opposite, vec![from]);
+        graph
     }
 
-    pub fn exchange(&mut self, a: &Window, b: &Window) {
-        let a_neighbors = self.edges.remove(a).unwrap_or_default();
-        let b_neighbors = self.edges.remove(b).unwrap_or_default();
-
-        self.edges.insert(a.clone(), b_neighbors);
-        self.edges.insert(b.clone(), a_neighbors);
+    pub fn get(&self, window: &W, direction: &Direction) -> Option<&Vec<W>> {
+        self.edges.get(window)?.get(direction)
+    }
 
-        // exchange a <-> b
-        for (_, dir_map) in self.edges.iter_mut() {
-            for (_, neighbors) in dir_map.iter_mut() {
-                for win in neighbors.iter_mut() {
-                    if win == a {
-                        *win = b.clone();
-                    } else if win == b {
-                        *win = a.clone();
-                    }
-                }
-            }
-        }
+    pub fn add_window(&mut self, from: W, direction: Direction, to: Vec<W>) {
+        self.edges
+            .entry(from)
+            .or_default()
+            .entry(direction)
+            .or_default()
+            .extend(to);
     }
 
     #[cfg(feature = "trace_layout")]
     pub fn print(&self) {
         for (from, hash_map) in &self.edges {
-            info!("Window {:?} connections:", from.geometry().size);
+            info!("Window {:?} connections:", from);
             for (direction, to_list) in hash_map {
                 for to in to_list {
-                    info!("  ├── {:?} -> {:?}", direction, to.geometry().size);
+                    info!("  ├── {:?} -> {:?}", direction, to);
                 }
             }
         }
     }
 }
-
@@ -0,0 +1,242 @@
+use smithay::{
+    desktop::Window,
+    utils::{Logical, Point, Rectangle},
+};
+
+use crate::layout::Direction;
+
+/// Column widths to cycle through, as shares of the tiling area.
+pub const PRESET_WIDTHS: [f64; 3] = [1.0 / 3.0, 1.0 / 2.0, 2.0 / 3.0];
+
+#[derive(Debug)]
+struct Column {
+    // stacked from top to bottom
+    windows: Vec<Window>,
+    // index into PRESET_WIDTHS
+    width: usize,
+}
+
+impl Column {
+    fn new(window: Window) -> Self {
+        Self {
+            windows: vec![window],
+            width: 1,
+        }
+    }
+}
+
+/// Columns on an endless horizontal strip, the viewport shows the part of it
+/// that fits the tiling area.
+#[derive(Debug, Default)]
+pub struct Scrolling {
+    columns: Vec<Column>,
+    // left edge of the viewport on the strip
+    view_offset: i32,
+    // tiling area of the last arrange
+    area: Option<Rectangle<i32, Logical>>,
+}
+
+impl Scrolling {
+    pub fn area(&self) -> Option<Rectangle<i32, Logical>> {
+        self.area
+    }
+
+    pub fn contains(&self, window: &Window) -> bool {
+        self.position(window).is_some()
+    }
+
+    // column and row of the window
+    fn position(&self, window: &Window) -> Option<(usize, usize)> {
+        self.columns.iter().enumerate().find_map(|(i, column)| {
+            let row = column.windows.iter().position(|w| w == window)?;
+            Some((i, row))
+        })
+    }
+
+    /// Put `window` next to `target`, left and right open a new column, up and
+    /// down stack it into the column of `target`. Without target the window
+    /// opens a column at the end of the strip.
+    pub fn insert(&mut self, window: Window, target: Option<&Window>, direction: &Direction) {
+        let Some((column, row)) = target.and_then(|target| self.position(target)) else {
+            self.columns.push(Column::new(window));
+            return;
+        };
+
+        match direction {
+            Direction::Left => self.columns.insert(column, Column::new(window)),
+            Direction::Right => self.columns.insert(column + 1, Column::new(window)),
+            Direction::Up => self.columns[column].windows.insert(row, window),
+            Direction::Down => self.columns[column].windows.insert(row + 1, window),
+        }
+    }
+
+    /// Take `window` off the strip, returns the window to focus instead.
+    pub fn remove(&mut self, window: &Window) -> Option<Window> {
+        let (column, row) = self.position(window)?;
+
+        let windows = &mut self.columns[column].windows;
+        windows.remove(row);
+        if !windows.is_empty() {
+            return windows.get(row.saturating_sub(1)).cloned();
+        }
+
+        // the column goes with its last window, the focus moves left
+        self.columns.remove(column);
+        self.columns
+            .get(column.saturating_sub(1))
+            .map(|column| column.windows[0].clone())
+    }
+
+    /// The windows next to `window`: the whole column beside it, or the window
+    /// above or below in its own column.
+    pub fn neighbors(&self, window: &Window, direction: &Direction) -> Option<Vec<Window>> {
+        let (column, row) = self.position(window)?;
+
+        match direction {
+            Direction::Left => Some(self.columns.get(column.checked_sub(1)?)?.windows.clone()),
+            Direction::Right => Some(self.columns.get(column + 1)?.windows.clone()),
+            Direction::Up => Some(vec![
+                self.columns[column]
+                    .windows
+                    .get(row.checked_sub(1)?)?
+                    .clone(),
+            ]),
+            Direction::Down => Some(vec![self.columns[column].windows.get(row + 1)?.clone()]),
+        }
+    }
+
+    /// Move `window` one step: left and right carry its whole column along the
+    /// strip, up and down swap it within the column.
+    pub fn move_window(&mut self, window: &Window, direction: &Direction) -> bool {
+        let Some((column, row)) = self.position(window) else {
+            return false;
+        };
+
+        let (len, index) = match direction {
+            Direction::Left | Direction::Right => (self.columns.len(), column),
+            Direction::Up | Direction::Down => (self.columns[column].windows.len(), row),
+        };
+        let other = match direction {
+            Direction::Left | Direction::Up => index.checked_sub(1),
+            Direction::Right | Direction::Down => Some(index + 1).filter(|&i| i < len),
+        };
+        let Some(other) = other else {
+            return false;
+        };
+
+        match direction {
+            Direction::Left | Direction::Right => self.columns.swap(column, other),
+            Direction::Up | Direction::Down => self.columns[column].windows.swap(row, other),
+        }
+        true
+    }
+
+    /// Stack `window` into the column on the `direction` side, or expel it into
+    /// a column of its own there if it shares its column.
+    pub fn consume_or_expel(&mut self, window: &Window, direction: &Direction) -> bool {
+        let (Some((column, row)), Direction::Left | Direction::Right) =
+            (self.position(window), direction)
+        else {
+            return false;
+        };
+
+        if self.columns[column].windows.len() > 1 {
+            let window = self.columns[column].windows.remove(row);
+            let index = match direction {
+                Direction::Left => column,
+                _ => column + 1,
+            };
+            self.columns.insert(index, Column::new(window));
+            return true;
+        }
+
+        let target = match direction {
+            Direction::Left => column.checked_sub(1),
+            _ => Some(column + 1).filter(|&i| i < self.columns.len()),
+        };
+        let Some(target) = target else {
+            return false;
+        };
+
+        // the columns right of it move one to the left
+        let column = self.columns.remove(column);
+        let target = match direction {
+            Direction::Left => target,
+            _ => target - 1,
+        };
+        self.columns[target].windows.extend(column.windows);
+        true
+    }
+
+    /// Step the column of `window` to the next preset width.
+    pub fn cycle_width(&mut self, window: &Window) -> bool {
+        let Some((column, _)) = self.position(window) else {
+            return false;
+        };
+
+        let column = &mut self.columns[column];
+        column.width = (column.width + 1) % PRESET_WIDTHS.len();
+        true
+    }
+
+    /// Lay the strip out in `area`, scrolling the viewport just enough to show
+    /// the whole column of `focus`. Windows may end up outside of `area`.
+    pub fn arrange(
+        &mut self,
+        area: Rectangle<i32, Logical>,
+        gap: i32,
+        focus: Option<&Window>,
+    ) -> Vec<(Window, Rectangle<i32, Logical>)> {
+        self.area = Some(area);
+
+        // two halves or a third and two thirds fill the area exactly
+        let widths: Vec<i32> = self
+            .columns
+            .iter()
+            .map(|column| {
+                ((area.size.w + gap) as f64 * PRESET_WIDTHS[column.width]).round() as i32 - gap
+            })
+            .collect();
+        let xs: Vec<i32> = widths
+            .iter()
+            .scan(0, |x, width| {
+                let column_x = *x;
+                *x += width + gap;
+                Some(column_x)
+            })
+            .collect();
+
+        if let Some((column, _)) = focus.and_then(|focus| self.position(focus)) {
+            let (left, right) = (xs[column], xs[column] + widths[column]);
+            if left < self.view_offset {
+                self.view_offset = left;
+            } else if right > self.view_offset + area.size.w {
+                self.view_offset = right - area.size.w;
+            }
+        }
+
+        // no empty strip past the last column
+        let strip = xs.last().zip(widths.last()).map_or(0, |(x, w)| x + w);
+        self.view_offset = self.view_offset.min(strip - area.size.w).max(0);
+
+        let mut recs = Vec::new();
+        for (i, column) in self.columns.iter().enumerate() {
+            let count = column.windows.len() as i32;
+            let height = (area.size.h - gap * (count - 1)) / count;
+
+            for (row, window) in column.windows.iter().enumerate() {
+                let row = row as i32;
+                // the last window takes the rounding rest
+                let h = if row == count - 1 {
+                    area.size.h - (height + gap) * row
+                } else {
+                    height
+                };
+                let loc = area.loc + Point::from((xs[i] - self.view_offset, (height + gap) * row));
+                recs.push((window.clone(), Rectangle::new(loc, (widths[i], h).into())));
+            }
+        }
+
+        recs
+    }
+}
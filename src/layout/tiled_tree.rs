@@ -1,58 +1,142 @@
-use std::{mem::swap, time::Duration};
+use std::{collections::HashMap, fmt::Debug, hash::Hash, mem::swap};
 
+use serde::{Deserialize, Serialize};
 use slotmap::{SlotMap, new_key_type};
 use smithay::{
-    desktop::{Space, Window},
-    reexports::calloop::LoopHandle,
-    utils::{Logical, Point, Rectangle},
+    desktop::Window,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::ResizeEdge,
+    utils::{Logical, Point, Rectangle, Size},
 };
 
 use crate::{
     layout::{Direction, neighbor_graph::NeighborGraph},
     manager::window::WindowExt,
-    state::GlobalData,
 };
 
-use super::json_tiled_tree::JsonTree;
+use super::json_tiled_tree::JsonNode;
 
 #[derive(Debug, Clone)]
 pub enum TiledScheme {
     Default,
     Spiral,
+    MasterStack,
+    // columns on a scrollable strip, laid out by `Scrolling` instead of the tree
+    Scrolling,
 }
 
+/// Parameters of the master-stack scheme.
+#[derive(Debug, Clone)]
+pub struct MasterStack {
+    // windows in the master area, the others share the stack
+    pub count: usize,
+    // share of the master area along the side axis
+    pub ratio: f64,
+    // side of the output holding the master area
+    pub side: Direction,
+}
+
+impl Default for MasterStack {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            ratio: 0.5,
+            side: Direction::Left,
+        }
+    }
+}
+
+/// How a container shows which of its windows are there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerKind {
+    // one strip of tabs side by side
+    Tabbed,
+    // a title bar per window, one above the other
+    Stacked,
+}
+
+/// Height of a tab strip or of a single stacked title bar.
+pub const TITLE_HEIGHT: i32 = 24;
+
+// smallest share a split leaves to either child
+const MIN_RATIO: f64 = 0.05;
+
 new_key_type! {
     pub struct NodeId;
 }
 
+/// What the tree needs to know of a window, it never maps or configures one.
+pub trait TiledWindow: Clone + Eq + Hash + Debug {
+    /// Min and max size asked for by the client, 0 means unlimited.
+    fn size_limits(&self) -> (Size<i32, Logical>, Size<i32, Logical>);
+}
+
+impl TiledWindow for Window {
+    fn size_limits(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+        WindowExt::size_limits(self)
+    }
+}
+
+/// A window the tree moved, the workspace maps them in order so that the
+/// last one ends up on top.
+#[derive(Debug, Clone)]
+pub struct Placement<W> {
+    pub window: W,
+    pub rec: Rectangle<i32, Logical>,
+    // a window new to the tree slides in from here
+    pub from: Option<Rectangle<i32, Logical>>,
+}
+
+/// Where a window sat in the tree, to put it back after `remove`.
+#[derive(Debug, Clone)]
+pub struct Slot {
+    // the parent split, `remove` leaves the sibling subtree in its place
+    node: NodeId,
+    direction: Direction,
+    ratio: f64,
+    // the window was the left child
+    left: bool,
+}
+
 #[derive(Debug, Clone)]
-pub enum NodeData {
+pub enum NodeData<W> {
     Leaf {
-        window: Window,
+        window: W,
+        rec: Rectangle<i32, Logical>,
     },
     Split {
         direction: Direction,
         rec: Rectangle<i32, Logical>,
-        offset: Point<i32, Logical>,
+        // share of the room left to the left child
+        ratio: f64,
         left: NodeId,
         right: NodeId,
     },
+    // several windows in one rectangle, only the active one is shown
+    Container {
+        kind: ContainerKind,
+        windows: Vec<W>,
+        active: usize,
+        rec: Rectangle<i32, Logical>,
+    },
 }
 
+/// The tiled windows of a workspace as a tree of splits. Every change returns
+/// the windows it moved, placing them is up to the caller.
 #[derive(Debug)]
-pub struct TiledTree {
-    nodes: SlotMap<NodeId, NodeData>,
+pub struct TiledTree<W> {
+    nodes: SlotMap<NodeId, NodeData<W>>,
     spiral_node: Option<NodeId>,
     root: Option<NodeId>,
-    neighbor_graph: NeighborGraph,
+    neighbor_graph: NeighborGraph<W>,
 
     gap: i32,
 }
 
-impl TiledTree {
-    pub fn new(window: Window, gap: i32) -> Self {
+impl<W: TiledWindow> TiledTree<W> {
+    /// A tree of one window that fills `rec`.
+    pub fn new(window: W, rec: Rectangle<i32, Logical>, gap: i32) -> Self {
         let mut nodes = SlotMap::with_key();
-        let root = Some(nodes.insert(NodeData::Leaf { window }));
+        let root = Some(nodes.insert(NodeData::Leaf { window, rec }));
         let spiral_node = root.clone();
 
         Self {
@@ -65,33 +149,109 @@ impl TiledTree {
         }
     }
 
-    pub fn expansion(&self, space: &mut Space<Window>, loop_handle: &LoopHandle<'_, GlobalData>) {
-        if let Some(bound) = self.get_root_rec(space) {
+    /// Build a tree from a json layout, `leaf` gives the window of the n-th leaf.
+    /// Splits with only one filled side collapse into that side.
+    pub fn from_json_node(
+        node: &JsonNode,
+        gap: i32,
+        leaf: impl Fn(usize) -> Option<W>,
+    ) -> Option<Self> {
+        fn build<W>(
+            nodes: &mut SlotMap<NodeId, NodeData<W>>,
+            node: &JsonNode,
+            index: &mut usize,
+            last_leaf: &mut Option<NodeId>,
+            leaf: &impl Fn(usize) -> Option<W>,
+        ) -> Option<NodeId> {
+            match node {
+                JsonNode::Leaf { .. } => {
+                    let window = leaf(*index);
+                    *index += 1;
+
+                    let id = nodes.insert(NodeData::Leaf {
+                        window: window?,
+                        rec: Rectangle::default(),
+                    });
+                    *last_leaf = Some(id);
+                    Some(id)
+                }
+                JsonNode::Container { kind, tabs } => {
+                    let windows: Vec<W> = tabs
+                        .iter()
+                        .flat_map(|tab| tab.leaves())
+                        .filter_map(|_| {
+                            let window = leaf(*index);
+                            *index += 1;
+                            window
+                        })
+                        .collect();
+                    if windows.is_empty() {
+                        return None;
+                    }
+
+                    let id = nodes.insert(NodeData::Container {
+                        kind: *kind,
+                        windows,
+                        active: 0,
+                        rec: Rectangle::default(),
+                    });
+                    *last_leaf = Some(id);
+                    Some(id)
+                }
+                JsonNode::Split {
+                    direction,
+                    ratio,
+                    left,
+                    right,
+                } => {
+                    let left = build(nodes, left, index, last_leaf, leaf);
+                    let right = build(nodes, right, index, last_leaf, leaf);
+
+                    match (left, right) {
+                        (Some(left), Some(right)) => Some(nodes.insert(NodeData::Split {
+                            direction: direction.direction(),
+                            rec: Rectangle::default(),
+                            ratio: clamp_ratio(*ratio),
+                            left,
+                            right,
+                        })),
+                        (Some(id), None) | (None, Some(id)) => Some(id),
+                        (None, None) => None,
+                    }
+                }
+            }
+        }
+
+        let mut nodes = SlotMap::with_key();
+        let mut last_leaf = None;
+        let root = build(&mut nodes, node, &mut 0, &mut last_leaf, &leaf)?;
+
+        Some(Self {
+            nodes,
+            spiral_node: last_leaf,
+            root: Some(root),
+            neighbor_graph: NeighborGraph::new(),
+
+            gap,
+        })
+    }
+
+    /// Every window side by side over the root, without changing the tree.
+    pub fn expansion(&self) -> Vec<Placement<W>> {
+        let mut placements = vec![];
+
+        if let Some(bound) = self.get_root_rec() {
             let width = (bound.size.w - 2 * self.gap) / 3;
             let height = bound.size.h;
             let mut loc = bound.loc;
 
             for node in self.nodes.values() {
                 match node {
-                    NodeData::Leaf { window } => {
-                        let from = space.element_geometry(window).unwrap();
-
-                        window.set_rec((width, height).into());
-                        space.map_element(window.clone(), loc, false);
-
-                        let window = window.clone();
-
-                        loop_handle.insert_idle(move |data| {
-                            data.render_manager.add_animation(
-                                window,
-                                from,
-                                Rectangle {
-                                    loc,
-                                    size: (width, height).into(),
-                                },
-                                Duration::from_millis(30),
-                                crate::animation::AnimationType::EaseInOutQuad,
-                            );
+                    NodeData::Leaf { window, .. } => {
+                        placements.push(Placement {
+                            window: window.clone(),
+                            rec: Rectangle::new(loc, (width, height).into()),
+                            from: None,
                         });
 
                         loc.x = loc.x + width + self.gap;
@@ -100,38 +260,230 @@ impl TiledTree {
                 }
             }
         }
+
+        placements
     }
 
-    pub fn recover(&mut self, space: &mut Space<Window>, loop_handle: &LoopHandle<'_, GlobalData>) {
-        if let Some(root_id) = self.get_root() {
-            match self.nodes[root_id] {
-                NodeData::Split { rec, .. } => {
-                    self.modify(root_id, rec, space, loop_handle);
-                }
-                _ => {}
-            }
+    /// Put every window back to where the tree has it.
+    pub fn recover(&mut self) -> Vec<Placement<W>> {
+        match self.get_root_rec() {
+            Some(rec) => self.arrange(rec),
+            None => vec![],
         }
     }
 
+    // takes effect on the next arrange
+    pub fn set_gap(&mut self, gap: i32) {
+        self.gap = gap;
+    }
+
     pub fn get_root(&self) -> Option<NodeId> {
         self.root
     }
 
-    pub fn get_root_rec(&self, space: &mut Space<Window>) -> Option<Rectangle<i32, Logical>> {
-        match self.get_root() {
-            Some(root_id) => match &self.nodes[root_id] {
-                NodeData::Leaf { window } => space.element_geometry(window),
-                NodeData::Split { rec, .. } => Some(rec.clone()),
-            },
-            None => None,
+    pub fn get_root_rec(&self) -> Option<Rectangle<i32, Logical>> {
+        self.root.map(|root_id| self.node_rec(root_id))
+    }
+
+    pub fn get_node(&self, node_id: NodeId) -> Option<&NodeData<W>> {
+        self.nodes.get(node_id)
+    }
+
+    /// All windows in the tree from left to right, hidden tabs included.
+    pub fn windows(&self) -> Vec<W> {
+        self.root
+            .map(|root_id| self.subtree_windows(root_id))
+            .unwrap_or_default()
+    }
+
+    /// Every shown window from left to right with the rectangle of its leaf.
+    pub fn leaf_recs(&self) -> Vec<(W, Rectangle<i32, Logical>)> {
+        fn collect<W>(
+            nodes: &SlotMap<NodeId, NodeData<W>>,
+            id: NodeId,
+            result: &mut Vec<(W, Rectangle<i32, Logical>)>,
+        ) where
+            W: Clone,
+        {
+            match &nodes[id] {
+                NodeData::Leaf { window, rec } => result.push((window.clone(), *rec)),
+                NodeData::Split { left, right, .. } => {
+                    collect(nodes, *left, result);
+                    collect(nodes, *right, result);
+                }
+                // the hidden tabs have no place of their own
+                NodeData::Container {
+                    windows,
+                    active,
+                    rec,
+                    ..
+                } => result.push((windows[*active].clone(), *rec)),
+            }
+        }
+
+        let mut result = vec![];
+        if let Some(root_id) = self.root {
+            collect(&self.nodes, root_id, &mut result);
+        }
+        result
+    }
+
+    // where every window is placed now, hidden tabs included
+    fn shown(&self) -> HashMap<W, Rectangle<i32, Logical>> {
+        let mut shown = HashMap::new();
+        for node in self.nodes.values() {
+            match node {
+                NodeData::Leaf { window, rec } => {
+                    shown.insert(window.clone(), *rec);
+                }
+                NodeData::Container {
+                    kind, windows, rec, ..
+                } => {
+                    let content = content_rec(*rec, *kind, windows.len());
+                    shown.extend(windows.iter().map(|window| (window.clone(), content)));
+                }
+                NodeData::Split { .. } => {}
+            }
+        }
+        shown
+    }
+
+    /// Windows of a master-stack tree, masters first.
+    pub fn master_order(&self, master: &MasterStack) -> Vec<W> {
+        if let Some(NodeData::Split {
+            direction,
+            left,
+            right,
+            ..
+        }) = self.root.map(|root_id| &self.nodes[root_id])
+        {
+            // only the split between masters and stack points to the master side
+            if *direction == master.side {
+                let (masters, stack) = match direction {
+                    Direction::Left | Direction::Up => (*left, *right),
+                    Direction::Right | Direction::Down => (*right, *left),
+                };
+
+                let mut windows = self.subtree_windows(masters);
+                windows.extend(self.subtree_windows(stack));
+                return windows;
+            }
+        }
+
+        self.windows()
+    }
+
+    /// Replace the tree by a master-stack layout of `windows` in `rec`,
+    /// masters first. Masters and stack windows get equal shares of their area.
+    pub fn master_stack(
+        &mut self,
+        windows: &[W],
+        master: &MasterStack,
+        rec: Rectangle<i32, Logical>,
+    ) -> Vec<Placement<W>> {
+        let mut nodes = SlotMap::with_key();
+        let (masters, stack) = windows.split_at(master.count.min(windows.len()));
+        let chain_direction = match master.side {
+            Direction::Left | Direction::Right => Direction::Down,
+            Direction::Up | Direction::Down => Direction::Right,
+        };
+
+        let root = if masters.is_empty() || stack.is_empty() {
+            chain(&mut nodes, windows, &chain_direction, rec, self.gap)
+        } else {
+            let masters_first = matches!(master.side, Direction::Left | Direction::Up);
+            let share = if masters_first {
+                master.ratio
+            } else {
+                1.0 - master.ratio
+            };
+            let size = share_size(rec, &master.side, share, self.gap);
+            let (l_rec, r_rec) = recover_new_rec(rec, &master.side, size, self.gap);
+
+            let (left, right) = if masters_first {
+                (
+                    chain(&mut nodes, masters, &chain_direction, l_rec, self.gap),
+                    chain(&mut nodes, stack, &chain_direction, r_rec, self.gap),
+                )
+            } else {
+                (
+                    chain(&mut nodes, stack, &chain_direction, l_rec, self.gap),
+                    chain(&mut nodes, masters, &chain_direction, r_rec, self.gap),
+                )
+            };
+
+            match (left, right) {
+                (Some(left), Some(right)) => Some(nodes.insert(NodeData::Split {
+                    direction: master.side.clone(),
+                    rec,
+                    ratio: share,
+                    left,
+                    right,
+                })),
+                _ => None,
+            }
+        };
+
+        self.nodes = nodes;
+        self.root = root;
+        self.spiral_node = None;
+
+        self.arrange(rec)
+    }
+
+    fn subtree_windows(&self, id: NodeId) -> Vec<W> {
+        match &self.nodes[id] {
+            NodeData::Leaf { window, .. } => vec![window.clone()],
+            NodeData::Split { left, right, .. } => {
+                let mut windows = self.subtree_windows(*left);
+                windows.extend(self.subtree_windows(*right));
+                windows
+            }
+            NodeData::Container { windows, .. } => windows.clone(),
+        }
+    }
+
+    // drop the nodes below `id`, the node itself stays
+    fn remove_children(&mut self, id: NodeId) {
+        if let NodeData::Split { left, right, .. } = self.nodes[id] {
+            for child in [left, right] {
+                self.remove_children(child);
+                self.nodes.remove(child);
+            }
+        }
+    }
+
+    // the rectangle a node was last laid out in
+    fn node_rec(&self, id: NodeId) -> Rectangle<i32, Logical> {
+        match &self.nodes[id] {
+            NodeData::Leaf { rec, .. }
+            | NodeData::Split { rec, .. }
+            | NodeData::Container { rec, .. } => *rec,
+        }
+    }
+
+    fn node_rec_mut(&mut self, id: NodeId) -> &mut Rectangle<i32, Logical> {
+        match &mut self.nodes[id] {
+            NodeData::Leaf { rec, .. }
+            | NodeData::Split { rec, .. }
+            | NodeData::Container { rec, .. } => rec,
         }
     }
 
+    // recreate the neighbor graph from the current geometry
+    fn rebuild_neighbor_graph(&mut self) {
+        self.neighbor_graph = NeighborGraph::from_recs(&self.leaf_recs(), self.gap);
+    }
+
     pub fn get_count(&self) -> usize {
         self.nodes
             .values()
-            .filter(|node| matches!(node, NodeData::Leaf { .. }))
-            .count()
+            .map(|node| match node {
+                NodeData::Leaf { .. } => 1,
+                NodeData::Split { .. } => 0,
+                NodeData::Container { windows, .. } => windows.len(),
+            })
+            .sum()
     }
 
     fn find_parent_and_sibling(&self, target: NodeId) -> Option<(NodeId, NodeId)> {
@@ -153,119 +505,166 @@ impl TiledTree {
         self.root.is_none()
     }
 
-    pub fn find_node(&self, window: &Window) -> Option<NodeId> {
+    pub fn find_node(&self, window: &W) -> Option<NodeId> {
         self.nodes.iter().find_map(|(id, data)| match data {
-            NodeData::Leaf { window: w } if w == window => Some(id),
+            NodeData::Leaf { window: w, .. } if w == window => Some(id),
+            NodeData::Container { windows, .. } if windows.contains(window) => Some(id),
             _ => None,
         })
     }
 
-    pub fn get_first_window(&self) -> Option<&Window> {
-        let root_id = match self.get_root() {
+    /// The slot of `window`, None if it is the only window or shares a
+    /// container with others.
+    pub fn slot(&self, window: &W) -> Option<Slot> {
+        let id = self.find_node(window)?;
+        if let NodeData::Container { windows, .. } = &self.nodes[id]
+            && windows.len() > 1
+        {
+            return None;
+        }
+        let (parent_id, _) = self.find_parent_and_sibling(id)?;
+
+        match &self.nodes[parent_id] {
+            NodeData::Split {
+                direction,
+                ratio,
+                left,
+                ..
+            } => Some(Slot {
+                node: parent_id,
+                direction: direction.clone(),
+                ratio: *ratio,
+                left: *left == id,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Split the sibling subtree of a slot again and put `window` back on its
+    /// side. Returns nothing if the sibling is gone from the tree.
+    pub fn restore(&mut self, slot: Slot, window: W) -> Vec<Placement<W>> {
+        let Some(sibling) = self.nodes.get(slot.node).cloned() else {
+            return vec![];
+        };
+
+        let rec = self.node_rec(slot.node);
+        let sibling_id = self.nodes.insert(sibling);
+        let leaf_id = self.nodes.insert(NodeData::Leaf { window, rec });
+
+        if self.spiral_node == Some(slot.node) {
+            self.spiral_node = Some(sibling_id);
+        }
+
+        let shown = self.shown();
+
+        let (left, right) = if slot.left {
+            (leaf_id, sibling_id)
+        } else {
+            (sibling_id, leaf_id)
+        };
+        self.nodes[slot.node] = NodeData::Split {
+            direction: slot.direction,
+            rec,
+            ratio: slot.ratio,
+            left,
+            right,
+        };
+
+        self.relayout(shown)
+    }
+
+    pub fn neighbors(&self, window: &W, direction: &Direction) -> Option<&Vec<W>> {
+        self.neighbor_graph.get(window, direction)
+    }
+
+    pub fn get_first_window(&self) -> Option<&W> {
+        let root_id = match self.root {
             Some(r) => r,
-            None => {
-                warn!("Failed to get root_id");
-                return None;
-            }
+            None => return None,
         };
 
-        fn get_window(nodes: &SlotMap<NodeId, NodeData>, id: NodeId) -> Option<&Window> {
+        fn get_window<W>(nodes: &SlotMap<NodeId, NodeData<W>>, id: NodeId) -> Option<&W> {
             match &nodes[id] {
-                NodeData::Leaf { window } => Some(window),
+                NodeData::Leaf { window, .. } => Some(window),
                 NodeData::Split { left, .. } => get_window(nodes, *left),
+                NodeData::Container {
+                    windows, active, ..
+                } => windows.get(*active),
             }
         }
 
         get_window(&self.nodes, root_id)
     }
 
+    /// Split the place of `target` and open `new_window` on the `direction`
+    /// side of it, returns nothing if the target is not in the tree.
     pub fn insert_window(
         &mut self,
-        target: Option<&Window>,
-        new_window: Window,
+        target: Option<&W>,
+        new_window: W,
         direction: Direction,
-        space: &mut Space<Window>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) -> bool {
+    ) -> Vec<Placement<W>> {
         let target = match target {
             Some(window) => window.clone(),
             None => match self.get_first_window() {
                 Some(window) => window.clone(),
                 None => {
                     warn!("Failed to get first window");
-                    return false;
+                    return vec![];
                 }
             },
         };
 
-        if let Some(target_id) = self.find_node(&target) {
-            // resize
-            // TODO: use server geometry
-            let rec = match space.element_geometry(&target) {
-                Some(r) => r,
-                None => {
-                    warn!("Failed to get window rectangle");
-                    return false;
-                }
-            };
+        let Some(target_id) = self.find_node(&target) else {
+            return vec![];
+        };
 
-            let mut original_rec = rec.clone();
-            let new_rec = get_new_rec(&direction, &mut original_rec, self.gap);
-
-            // TODO: merge
-            target.set_rec(original_rec.size);
-            new_window.set_rec(new_rec.size);
-            space.map_element(target.clone(), original_rec.loc, false);
-            space.map_element(new_window.clone(), new_rec.loc, true);
-
-            // adjust tree
-            let old_leaf = self.nodes.insert(NodeData::Leaf {
-                window: target.clone(),
-            });
-            let new_leaf = self.nodes.insert(NodeData::Leaf {
-                window: new_window.clone(),
-            });
-
-            self.spiral_node = Some(new_leaf);
-
-            // use split node hold leafs
-            match direction {
-                Direction::Left | Direction::Up => {
-                    self.nodes[target_id] = NodeData::Split {
-                        direction: direction.clone(),
-                        rec,
-                        offset: (0, 0).into(),
-                        left: new_leaf,
-                        right: old_leaf,
-                    };
-                }
-                _ => {
-                    self.nodes[target_id] = NodeData::Split {
-                        direction: direction.clone(),
-                        rec,
-                        offset: (0, 0).into(),
-                        left: old_leaf,
-                        right: new_leaf,
-                    };
-                }
-            }
+        let shown = self.shown();
 
-            // modify neighbor_graph
-            self.neighbor_graph
-                .tiled_add(target.clone(), direction.clone(), new_window.clone());
+        // a new window joins a container as the active tab
+        if let NodeData::Container {
+            windows, active, ..
+        } = &mut self.nodes[target_id]
+        {
+            *active = windows.iter().position(|w| w == &target).unwrap_or(*active) + 1;
+            windows.insert(*active, new_window);
 
-            // TODO: use config
-            // create animation
-            loop_handle.insert_idle(move |data| {
-                data.render_manager.add_animation(
-                    target,
-                    rec,
-                    original_rec,
-                    Duration::from_millis(30),
-                    crate::animation::AnimationType::EaseInOutQuad,
-                );
+            return self.relayout(shown);
+        }
+
+        let rec = self.node_rec(target_id);
+
+        // adjust tree
+        let old_leaf = self.nodes.insert(NodeData::Leaf {
+            window: target,
+            rec,
+        });
+        let new_leaf = self.nodes.insert(NodeData::Leaf {
+            window: new_window.clone(),
+            rec,
+        });
 
-                let mut from = new_rec;
+        self.spiral_node = Some(new_leaf);
+
+        // use split node hold leafs
+        let (left, right) = match direction {
+            Direction::Left | Direction::Up => (new_leaf, old_leaf),
+            _ => (old_leaf, new_leaf),
+        };
+        self.nodes[target_id] = NodeData::Split {
+            direction: direction.clone(),
+            rec,
+            ratio: 0.5,
+            left,
+            right,
+        };
+
+        let mut placements = self.relayout(shown);
+
+        // the new window slides in from the side it opens on
+        for placement in &mut placements {
+            if placement.window == new_window {
+                let mut from = placement.rec;
                 match direction {
                     Direction::Right => {
                         from.loc.x += from.size.w;
@@ -280,75 +679,77 @@ impl TiledTree {
                         from.loc.y += from.size.h;
                     }
                 }
-
-                data.render_manager.add_animation(
-                    new_window,
-                    from,
-                    new_rec,
-                    Duration::from_millis(30),
-                    crate::animation::AnimationType::OvershootBounce,
-                );
-            });
-
-            true
-        } else {
-            false
+                placement.from = Some(from);
+            }
         }
+
+        placements
     }
 
-    pub fn insert_window_spiral(
-        &mut self,
-        new_window: Window,
-        space: &mut Space<Window>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) {
+    pub fn insert_window_spiral(&mut self, new_window: W) -> Vec<Placement<W>> {
         let spiarl_node = match self.spiral_node {
             Some(node_id) => &self.nodes[node_id],
             None => {
-                return;
+                return vec![];
             }
         };
 
         let target = match spiarl_node {
-            NodeData::Leaf { window } => window,
+            NodeData::Leaf { window, .. } => window,
+            NodeData::Container {
+                windows, active, ..
+            } => &windows[*active],
             _ => {
-                return;
+                return vec![];
             }
         };
 
         let direction = Direction::ALL[(self.get_count() - 1) % 4].clone();
 
-        self.insert_window(
-            Some(&target.clone()),
-            new_window,
-            direction,
-            space,
-            loop_handle,
-        );
+        self.insert_window(Some(&target.clone()), new_window, direction)
     }
 
-    pub fn remove(
-        &mut self,
-        target: &Window,
-        focus: &mut Option<Window>,
-        space: &mut Space<Window>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) -> bool {
+    /// Take `target` out of the tree, its sibling takes over the room of the
+    /// parent split. `focus` moves on if it was the target.
+    pub fn remove(&mut self, target: &W, focus: &mut Option<W>) -> Vec<Placement<W>> {
         let target_id = match self.find_node(target) {
             Some(r) => r,
             None => {
                 warn!("Failed to get target_id");
-                return false;
+                return vec![];
             }
         };
 
+        let shown = self.shown();
+
+        // a tab goes alone while others are left in its container
+        if let NodeData::Container {
+            windows, active, ..
+        } = &mut self.nodes[target_id]
+            && windows.len() > 1
+        {
+            let index = windows.iter().position(|w| w == target).unwrap();
+            windows.remove(index);
+            if index < *active || *active == windows.len() {
+                *active -= 1;
+            }
+
+            if focus.as_ref() == Some(target) {
+                *focus = Some(windows[*active].clone());
+            }
+
+            return self.relayout(shown);
+        }
+
         // remove last node
         if Some(target_id) == self.root {
-            if matches!(self.nodes[target_id], NodeData::Leaf { .. }) {
+            if !matches!(self.nodes[target_id], NodeData::Split { .. }) {
                 self.nodes.remove(target_id);
                 self.root = None;
+                self.spiral_node = None;
+                self.neighbor_graph = NeighborGraph::new();
                 *focus = None;
-                return true;
+                return vec![];
             }
         }
 
@@ -356,102 +757,85 @@ impl TiledTree {
             Some(r) => r,
             None => {
                 warn!("Failed to get node: {:?} parent and sibling", target_id);
-                return false;
+                return vec![];
+            }
+        };
+
+        let sibling = match self.nodes.remove(sibling_id) {
+            Some(r) => r,
+            None => {
+                warn!("Failed to remove sibling: {:?}", sibling_id);
+                return vec![];
             }
         };
 
-        if self.spiral_node == Some(target_id) {
+        // the sibling moves up into the parent, a split keeps its ratio
+        let rec = self.node_rec(parent_id);
+        self.nodes[parent_id] = sibling;
+        *self.node_rec_mut(parent_id) = rec;
+        self.nodes.remove(target_id);
+
+        if self.spiral_node == Some(target_id) || self.spiral_node == Some(sibling_id) {
             self.spiral_node = Some(parent_id);
         }
 
-        match self.nodes[parent_id] {
-            NodeData::Split { rec, .. } => {
-                let sibling_data = match self.nodes.remove(sibling_id) {
-                    Some(r) => r,
-                    None => {
-                        warn!("Failed to remove sibling: {:?}", sibling_id);
-                        return false;
-                    }
-                };
-
-                match sibling_data {
-                    NodeData::Leaf { window } => {
-                        let from = space.element_geometry(&window).unwrap();
+        let placements = self.relayout(shown);
 
-                        window.set_rec(rec.size);
-                        space.map_element(window.clone(), rec.loc, false);
+        if focus.as_ref() == Some(target) {
+            *focus = match &self.nodes[parent_id] {
+                NodeData::Leaf { window, .. } => Some(window.clone()),
+                NodeData::Split { .. } => self.get_first_window().cloned(),
+                NodeData::Container {
+                    windows, active, ..
+                } => Some(windows[*active].clone()),
+            };
+        }
 
-                        self.nodes[parent_id] = NodeData::Leaf {
-                            window: window.clone(),
-                        };
+        placements
+    }
 
-                        if focus.as_ref() == Some(target) {
-                            *focus = Some(window.clone());
-                        }
-
-                        loop_handle.insert_idle(move |data| {
-                            data.render_manager.add_animation(
-                                window,
-                                from,
-                                rec,
-                                Duration::from_millis(30),
-                                crate::animation::AnimationType::EaseInOutQuad,
-                            );
-                        });
-                    }
-                    NodeData::Split {
-                        direction,
-                        left,
-                        right,
-                        ..
-                    } => {
-                        self.nodes[parent_id] = NodeData::Split {
-                            direction,
-                            rec, // from parent
-                            offset: (0, 0).into(),
-                            left,
-                            right,
-                        };
-                        self.modify(parent_id, rec, space, loop_handle);
+    /// Lay the whole tree out in `rec`.
+    pub fn arrange(&mut self, rec: Rectangle<i32, Logical>) -> Vec<Placement<W>> {
+        let mut placements = vec![];
+        if let Some(root_id) = self.root {
+            self.layout(root_id, rec, &mut placements);
+        }
+        self.rebuild_neighbor_graph();
 
-                        if focus.as_ref() == Some(target) {
-                            *focus = self.get_first_window().cloned();
-                        }
-                    }
-                }
+        placements
+    }
 
-                self.nodes.remove(target_id);
+    // lay the tree out again after a change, new size limits of a subtree can
+    // move borders above it. Only windows away from where `shown` has them are
+    // placed.
+    fn relayout(&mut self, shown: HashMap<W, Rectangle<i32, Logical>>) -> Vec<Placement<W>> {
+        let Some(rec) = self.get_root_rec() else {
+            return vec![];
+        };
 
-                true
-            }
-            NodeData::Leaf { .. } => false,
-        }
+        let mut placements = self.arrange(rec);
+        placements.retain(|placement| shown.get(&placement.window) != Some(&placement.rec));
+        placements
     }
 
-    pub fn modify(
+    // lay the subtree of `node_id` out in `rec`
+    fn layout(
         &mut self,
         node_id: NodeId,
         rec: Rectangle<i32, Logical>,
-        space: &mut Space<Window>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
+        placements: &mut Vec<Placement<W>>,
     ) {
         // modify the child tree with new rec with direction
         match &mut self.nodes[node_id] {
-            NodeData::Leaf { window } => {
-                let from = space.element_geometry(&window).unwrap();
-
-                window.set_rec(rec.size);
-                space.map_element(window.clone(), rec.loc, false);
-
-                let window = window.clone();
-                loop_handle.insert_idle(move |data| {
-                    data.render_manager.add_animation(
-                        window,
-                        from,
-                        rec,
-                        Duration::from_millis(30),
-                        crate::animation::AnimationType::EaseInOutQuad,
-                    );
+            NodeData::Leaf {
+                window,
+                rec: current_rec,
+            } => {
+                *current_rec = rec;
+                placements.push(Placement {
+                    window: window.clone(),
+                    rec,
+                    from: None,
                 });
             }
             NodeData::Split {
@@ -459,163 +843,478 @@ impl TiledTree {
                 right,
                 direction,
                 rec: current_rec,
-                offset,
+                ratio,
             } => {
-                let (l_rec, r_rec) = recover_new_rec(rec, direction, offset.clone(), self.gap);
-
-                *current_rec = rec.clone();
+                *current_rec = rec;
 
                 let left_id = *left;
                 let right_id = *right;
+                let direction = direction.clone();
+                let ratio = *ratio;
+
+                let size = self.left_size(rec, &direction, ratio, left_id, right_id);
+                let (l_rec, r_rec) = recover_new_rec(rec, &direction, size, self.gap);
 
-                self.modify(left_id, l_rec, space, loop_handle);
-                self.modify(right_id, r_rec, space, loop_handle);
+                self.layout(left_id, l_rec, placements);
+                self.layout(right_id, r_rec, placements);
+            }
+            NodeData::Container {
+                kind,
+                windows,
+                active,
+                rec: current_rec,
+            } => {
+                *current_rec = rec;
+                let content = content_rec(rec, *kind, windows.len());
+
+                // the active tab is placed last to stay above the hidden ones
+                let mut windows = windows.clone();
+                let active = windows.remove(*active);
+                windows.push(active);
+
+                placements.extend(windows.into_iter().map(|window| Placement {
+                    window,
+                    rec: content,
+                    from: None,
+                }));
             }
         }
     }
 
-    pub fn invert_window(
-        &mut self,
-        target: &Window,
-        space: &mut Space<Window>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) {
+    /// Gather the split around `window` into a container of `kind`. A
+    /// container changes to `kind`, or is split up again if it already is.
+    pub fn toggle_container(&mut self, window: &W, kind: ContainerKind) -> Vec<Placement<W>> {
+        let Some(id) = self.find_node(window) else {
+            return vec![];
+        };
+
+        let shown = self.shown();
+
+        if let NodeData::Container {
+            kind: current,
+            windows,
+            rec,
+            ..
+        } = &mut self.nodes[id]
+        {
+            let rec = *rec;
+            if *current != kind {
+                *current = kind;
+            } else {
+                // the windows go side by side along the longer side
+                let windows = windows.clone();
+                let direction = if rec.size.w >= rec.size.h {
+                    Direction::Right
+                } else {
+                    Direction::Down
+                };
+
+                let Some(chain_id) = chain(&mut self.nodes, &windows, &direction, rec, self.gap)
+                else {
+                    return vec![];
+                };
+                let node = self.nodes.remove(chain_id).unwrap();
+                self.nodes[id] = node;
+                self.spiral_node = windows.last().and_then(|last| self.find_node(last));
+            }
+
+            return self.relayout(shown);
+        }
+
+        // the parent split, or the window alone
+        let node_id = self
+            .find_parent_and_sibling(id)
+            .map_or(id, |(parent_id, _)| parent_id);
+        let rec = self.node_rec(node_id);
+
+        let windows = self.subtree_windows(node_id);
+        let active = windows.iter().position(|w| w == window).unwrap_or(0);
+        self.remove_children(node_id);
+        if !self
+            .spiral_node
+            .is_some_and(|spiral_node| self.nodes.contains_key(spiral_node))
+        {
+            self.spiral_node = Some(node_id);
+        }
+
+        self.nodes[node_id] = NodeData::Container {
+            kind,
+            windows,
+            active,
+            rec,
+        };
+
+        self.relayout(shown)
+    }
+
+    /// Show the tab of `window` in its container, false if it is no hidden tab.
+    /// The caller raises the window above the others.
+    pub fn activate(&mut self, window: &W) -> bool {
+        let Some(id) = self.find_node(window) else {
+            return false;
+        };
+        let NodeData::Container {
+            windows, active, ..
+        } = &mut self.nodes[id]
+        else {
+            return false;
+        };
+
+        match windows.iter().position(|w| w == window) {
+            Some(index) if index != *active => *active = index,
+            _ => return false,
+        }
+
+        self.rebuild_neighbor_graph();
+        true
+    }
+
+    /// Activate the tab `delta` steps after `window` in its container.
+    pub fn cycle_tab(&mut self, window: &W, delta: isize) -> Option<W> {
+        let id = self.find_node(window)?;
+        let NodeData::Container { windows, .. } = &self.nodes[id] else {
+            return None;
+        };
+
+        let index = windows.iter().position(|w| w == window)? as isize;
+        let next = windows[(index + delta).rem_euclid(windows.len() as isize) as usize].clone();
+
+        self.activate(&next);
+        Some(next)
+    }
+
+    pub fn is_hidden(&self, window: &W) -> bool {
+        self.nodes.values().any(|node| match node {
+            NodeData::Container {
+                windows, active, ..
+            } => windows.contains(window) && &windows[*active] != window,
+            _ => false,
+        })
+    }
+
+    /// The tabs or title bars of every container, with whether they belong to
+    /// the shown window.
+    pub fn title_bars(&self) -> Vec<(Rectangle<i32, Logical>, bool)> {
+        let mut bars = vec![];
+
+        for node in self.nodes.values() {
+            let NodeData::Container {
+                kind,
+                windows,
+                active,
+                rec,
+            } = node
+            else {
+                continue;
+            };
+
+            let count = windows.len() as i32;
+            for i in 0..count {
+                let bar = match kind {
+                    ContainerKind::Tabbed => {
+                        // the last tab takes the rounding rest
+                        let width = rec.size.w / count;
+                        let w = if i == count - 1 {
+                            rec.size.w - width * i
+                        } else {
+                            width
+                        };
+                        Rectangle::new(
+                            rec.loc + Point::from((width * i, 0)),
+                            (w, TITLE_HEIGHT).into(),
+                        )
+                    }
+                    ContainerKind::Stacked => Rectangle::new(
+                        rec.loc + Point::from((0, TITLE_HEIGHT * i)),
+                        (rec.size.w, TITLE_HEIGHT).into(),
+                    ),
+                };
+                bars.push((bar, i as usize == *active));
+            }
+        }
+
+        bars
+    }
+
+    pub fn invert_window(&mut self, target: &W) -> Vec<Placement<W>> {
         let target_id = match self.find_node(target) {
             Some(r) => r,
             None => {
                 warn!("Failed to get target_id");
-                return;
+                return vec![];
             }
         };
 
         // Only single window
         if self.get_root() == Some(target_id) {
-            return;
+            return vec![];
         }
 
         let (parent_id, _) = match self.find_parent_and_sibling(target_id) {
             Some(r) => r,
             None => {
                 warn!("Failed to get node: {:?} parent and sibling", target_id);
-                return;
+                return vec![];
             }
         };
 
+        let shown = self.shown();
         match &mut self.nodes[parent_id] {
-            NodeData::Split { direction, rec, .. } => {
+            NodeData::Split { direction, .. } => {
                 *direction = direction.rotate_cw();
-                let rec = *rec;
-                self.modify(parent_id, rec, space, loop_handle);
+                self.relayout(shown)
             }
-            NodeData::Leaf { .. } => {}
+            _ => vec![],
         }
     }
 
-    pub fn _resize(
+    /// Drag the split borders on the grabbed edges of `target` by `delta`,
+    /// clamped so that every window keeps within its min and max size.
+    /// Returns nothing if no border moved.
+    pub fn resize(
         &mut self,
-        target: &Window,
-        offset: Point<i32, Logical>,
-        space: &mut Space<Window>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) {
+        target: &W,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+    ) -> Vec<Placement<W>> {
         let target_id = match self.find_node(target) {
             Some(r) => r,
             None => {
                 warn!("Failed to get target_id");
-                return;
+                return vec![];
             }
         };
 
-        // Only single window
-        if self.get_root() == Some(target_id) {
-            return;
+        let (left, right, top, bottom) = match edges {
+            ResizeEdge::Left => (true, false, false, false),
+            ResizeEdge::Right => (false, true, false, false),
+            ResizeEdge::Top => (false, false, true, false),
+            ResizeEdge::Bottom => (false, false, false, true),
+            ResizeEdge::TopLeft => (true, false, true, false),
+            ResizeEdge::TopRight => (false, true, true, false),
+            ResizeEdge::BottomLeft => (true, false, false, true),
+            ResizeEdge::BottomRight => (false, true, false, true),
+            _ => return vec![],
+        };
+
+        let shown = self.shown();
+        let mut changed = false;
+
+        if (left || right)
+            && delta.x != 0
+            && let Some(split_id) = self.find_border(target_id, true, right)
+        {
+            let amount = delta.x as f64 / self.room(split_id).max(1) as f64;
+            changed |= self.move_border(split_id, amount);
         }
 
-        let (parent_id, _) = match self.find_parent_and_sibling(target_id) {
-            Some(r) => r,
-            None => {
-                warn!("Failed to get node: {:?} parent and sibling", target_id);
-                return;
-            }
+        if (top || bottom)
+            && delta.y != 0
+            && let Some(split_id) = self.find_border(target_id, false, bottom)
+        {
+            let amount = delta.y as f64 / self.room(split_id).max(1) as f64;
+            changed |= self.move_border(split_id, amount);
+        }
+
+        if !changed {
+            return vec![];
+        }
+
+        self.relayout(shown)
+    }
+
+    /// Move the border on the `direction` side of `target` outwards by
+    /// `amount` of the split's room, a negative amount moves it inwards.
+    /// Returns nothing if no border moved.
+    pub fn resize_ratio(
+        &mut self,
+        target: &W,
+        direction: &Direction,
+        amount: f64,
+    ) -> Vec<Placement<W>> {
+        let Some(target_id) = self.find_node(target) else {
+            warn!("Failed to get target_id");
+            return vec![];
         };
 
-        match &mut self.nodes[parent_id] {
-            NodeData::Split {
-                offset: current_offset,
-                rec,
-                ..
-            } => {
-                *current_offset += offset;
-                let rec = *rec;
-                self.modify(parent_id, rec, space, loop_handle);
+        let horizontal = matches!(direction, Direction::Left | Direction::Right);
+        let after = matches!(direction, Direction::Right | Direction::Down);
+        let Some(split_id) = self.find_border(target_id, horizontal, after) else {
+            return vec![];
+        };
+
+        // growing to the left or up moves the border backwards
+        let amount = if after { amount } else { -amount };
+        let shown = self.shown();
+        if !self.move_border(split_id, amount) {
+            return vec![];
+        }
+
+        self.relayout(shown)
+    }
+
+    /// Reset every split to halves.
+    pub fn equalize(&mut self) -> Vec<Placement<W>> {
+        for node in self.nodes.values_mut() {
+            if let NodeData::Split { ratio, .. } = node {
+                *ratio = 0.5;
             }
-            NodeData::Leaf { .. } => {}
         }
+
+        self.recover()
     }
 
-    #[allow(dead_code)]
-    pub fn from_json(&mut self, path: &str) {
-        if let Some(json_tree) = JsonTree::from_json(path) {
-            json_tree.print_tree();
+    // the nearest split along the axis that has its border after (right or
+    // below) or before the subtree of `node`
+    fn find_border(&self, node: NodeId, horizontal: bool, after: bool) -> Option<NodeId> {
+        let mut child = node;
+        while let Some((parent_id, _)) = self.find_parent_and_sibling(child) {
+            if let NodeData::Split {
+                direction, left, ..
+            } = &self.nodes[parent_id]
+            {
+                let is_horizontal = matches!(direction, Direction::Left | Direction::Right);
+                if is_horizontal == horizontal && (*left == child) == after {
+                    return Some(parent_id);
+                }
+            }
+            child = parent_id;
         }
+        None
     }
 
-    pub fn exchange(
-        &mut self,
-        focus: &Window,
-        direction: &Direction,
-        space: &mut Space<Window>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) {
-        if let Some(neighbor) = self.neighbor_graph.get(focus, direction) {
-            let neighbor = neighbor.first().unwrap();
+    // returns false if the border stays where it is
+    fn move_border(&mut self, split_id: NodeId, amount: f64) -> bool {
+        let (direction, rec, ratio, left, right) = match &self.nodes[split_id] {
+            NodeData::Split {
+                direction,
+                rec,
+                ratio,
+                left,
+                right,
+            } => (direction.clone(), *rec, *ratio, *left, *right),
+            _ => return false,
+        };
 
-            let neighbor_id = self.find_node(neighbor).unwrap();
-            let focus_id = self.find_node(focus).unwrap();
+        let room = split_room(rec, &direction, self.gap);
+        if room <= 0 {
+            return false;
+        }
 
-            let neighbor_rec = space.element_geometry(neighbor).unwrap();
-            let focus_rec = space.element_geometry(focus).unwrap();
+        // start from where the border is shown, the ratio may be bent by the limits
+        let current = self.left_size(rec, &direction, ratio, left, right);
+        let new_ratio = clamp_ratio(current as f64 / room as f64 + amount);
+        if self.left_size(rec, &direction, new_ratio, left, right) == current {
+            return false;
+        }
 
-            // swap window
-            if let Some([a, b]) = self.nodes.get_disjoint_mut([neighbor_id, focus_id]) {
-                match (a, b) {
-                    (NodeData::Leaf { window: win_a }, NodeData::Leaf { window: win_b }) => {
-                        swap(win_a, win_b);
+        if let NodeData::Split { ratio, .. } = &mut self.nodes[split_id] {
+            *ratio = new_ratio;
+        }
 
-                        win_a.set_rec(neighbor_rec.size);
-                        space.map_element(win_a.clone(), neighbor_rec.loc, false);
+        true
+    }
 
-                        win_b.set_rec(focus_rec.size);
-                        space.map_element(win_b.clone(), focus_rec.loc, false);
-                    }
-                    _ => {}
+    // room of a split for both children, zero for other nodes
+    fn room(&self, split_id: NodeId) -> i32 {
+        match &self.nodes[split_id] {
+            NodeData::Split { direction, rec, .. } => split_room(*rec, direction, self.gap),
+            _ => 0,
+        }
+    }
+
+    // size of the left child of a split in `rec`, the ratio gives way to the
+    // min and max sizes of the windows on both sides
+    fn left_size(
+        &self,
+        rec: Rectangle<i32, Logical>,
+        direction: &Direction,
+        ratio: f64,
+        left: NodeId,
+        right: NodeId,
+    ) -> i32 {
+        let room = split_room(rec, direction, self.gap).max(0);
+        let horizontal = matches!(direction, Direction::Left | Direction::Right);
+
+        // the right one gets the rest of the room
+        let (l_min, l_max) = self.size_limits(left, horizontal);
+        let (r_min, r_max) = self.size_limits(right, horizontal);
+        let low = l_min.max(room - r_max);
+        let high = l_max.min(room - r_min);
+
+        let size = (room as f64 * ratio).round() as i32;
+        let size = if low <= high {
+            size.clamp(low, high)
+        } else {
+            size
+        };
+        size.clamp(0, room)
+    }
+
+    // min and max extent of a subtree along one axis
+    fn size_limits(&self, node: NodeId, horizontal: bool) -> (i32, i32) {
+        match &self.nodes[node] {
+            NodeData::Leaf { window, .. } => window_limits(window, horizontal),
+            // every tab has to fit, the titles take room above them
+            NodeData::Container { kind, windows, .. } => {
+                let (min, max) = windows
+                    .iter()
+                    .map(|window| window_limits(window, horizontal))
+                    .fold((1, i32::MAX), |(min, max), (w_min, w_max)| {
+                        (min.max(w_min), max.min(w_max))
+                    });
+                let titles = if horizontal {
+                    0
+                } else {
+                    titles_height(*kind, windows.len())
+                };
+                (min + titles, max.saturating_add(titles))
+            }
+            NodeData::Split {
+                direction,
+                left,
+                right,
+                ..
+            } => {
+                let (l_min, l_max) = self.size_limits(*left, horizontal);
+                let (r_min, r_max) = self.size_limits(*right, horizontal);
+
+                let is_horizontal = matches!(direction, Direction::Left | Direction::Right);
+                if is_horizontal == horizontal {
+                    (
+                        l_min + r_min + self.gap,
+                        l_max.saturating_add(r_max).saturating_add(self.gap),
+                    )
+                } else {
+                    (l_min.max(r_min), l_max.min(r_max))
                 }
             }
+        }
+    }
 
-            let neighbor = neighbor.clone();
-            let focus = focus.clone();
+    /// Swap `focus` with its neighbor in `direction`, a container moves as a
+    /// whole with its tabs.
+    pub fn exchange(&mut self, focus: &W, direction: &Direction) -> Vec<Placement<W>> {
+        let Some(neighbor) = self
+            .neighbor_graph
+            .get(focus, direction)
+            .and_then(|neighbors| neighbors.first())
+        else {
+            return vec![];
+        };
 
-            // modify neighbor_graph
-            self.neighbor_graph.exchange(&neighbor, &focus);
+        let neighbor_id = self.find_node(neighbor).unwrap();
+        let focus_id = self.find_node(focus).unwrap();
 
-            // add animation
-            loop_handle.insert_idle(move |data| {
-                data.render_manager.add_animation(
-                    neighbor.clone(),
-                    neighbor_rec,
-                    focus_rec,
-                    Duration::from_millis(30),
-                    crate::animation::AnimationType::EaseInOutQuad,
-                );
+        let shown = self.shown();
 
-                data.render_manager.add_animation(
-                    focus.clone(),
-                    focus_rec,
-                    neighbor_rec,
-                    Duration::from_millis(30),
-                    crate::animation::AnimationType::EaseInOutQuad,
-                );
-            });
+        // the nodes trade places, the recs are laid out again below
+        match self.nodes.get_disjoint_mut([neighbor_id, focus_id]) {
+            Some([a, b]) => {
+                swap(a, b);
+                self.relayout(shown)
+            }
+            None => vec![],
         }
     }
 
@@ -629,15 +1328,18 @@ impl TiledTree {
             }
         };
 
-        fn print(nodes: &SlotMap<NodeId, NodeData>, id: NodeId, depth: usize) {
+        fn print<W: Debug>(nodes: &SlotMap<NodeId, NodeData<W>>, id: NodeId, depth: usize) {
             let indent = "  ".repeat(depth);
             match &nodes[id] {
-                NodeData::Leaf { window } => tracing::info!("{indent}- Leaf: {:?}", window),
+                NodeData::Leaf { window, .. } => tracing::info!("{indent}- Leaf: {:?}", window),
                 NodeData::Split { left, right, .. } => {
                     tracing::info!("{indent}- Split:");
                     print(nodes, *left, depth + 1);
                     print(nodes, *right, depth + 1);
                 }
+                NodeData::Container { kind, windows, .. } => {
+                    tracing::info!("{indent}- {:?}: {:?}", kind, windows)
+                }
             }
         }
 
@@ -646,69 +1348,457 @@ impl TiledTree {
     }
 }
 
+// min and max extent of a window along one axis, unlimited as i32::MAX
+fn window_limits<W: TiledWindow>(window: &W, horizontal: bool) -> (i32, i32) {
+    let (min_size, max_size) = window.size_limits();
+    let (min, max) = if horizontal {
+        (min_size.w, max_size.w)
+    } else {
+        (min_size.h, max_size.h)
+    };
+    (min.max(1), if max > 0 { max } else { i32::MAX })
+}
+
+// windows side by side in `direction`, each with an equal share of `rec`
+fn chain<W: TiledWindow>(
+    nodes: &mut SlotMap<NodeId, NodeData<W>>,
+    windows: &[W],
+    direction: &Direction,
+    rec: Rectangle<i32, Logical>,
+    gap: i32,
+) -> Option<NodeId> {
+    let (first, rest) = windows.split_first()?;
+    if rest.is_empty() {
+        return Some(nodes.insert(NodeData::Leaf {
+            window: first.clone(),
+            rec,
+        }));
+    }
+
+    let ratio = 1.0 / windows.len() as f64;
+    let size = share_size(rec, direction, ratio, gap);
+    let (l_rec, r_rec) = recover_new_rec(rec, direction, size, gap);
+    let leaf = nodes.insert(NodeData::Leaf {
+        window: first.clone(),
+        rec: l_rec,
+    });
+    let right = chain(nodes, rest, direction, r_rec, gap)?;
+
+    Some(nodes.insert(NodeData::Split {
+        direction: direction.clone(),
+        rec,
+        ratio,
+        left: leaf,
+        right,
+    }))
+}
+
+fn titles_height(kind: ContainerKind, count: usize) -> i32 {
+    match kind {
+        ContainerKind::Tabbed => TITLE_HEIGHT,
+        ContainerKind::Stacked => TITLE_HEIGHT * count as i32,
+    }
+}
+
+// the room a container leaves to its windows below the titles
+fn content_rec(
+    rec: Rectangle<i32, Logical>,
+    kind: ContainerKind,
+    count: usize,
+) -> Rectangle<i32, Logical> {
+    let titles = titles_height(kind, count);
+    let mut content = rec;
+    content.loc.y += titles;
+    content.size.h = (content.size.h - titles).max(1);
+    content
+}
+
+fn clamp_ratio(ratio: f64) -> f64 {
+    if ratio.is_finite() {
+        ratio.clamp(MIN_RATIO, 1.0 - MIN_RATIO)
+    } else {
+        0.5
+    }
+}
+
+// room of a split for its two children, without the gap between them
+fn split_room(rec: Rectangle<i32, Logical>, direction: &Direction, gap: i32) -> i32 {
+    match direction {
+        Direction::Left | Direction::Right => rec.size.w - gap,
+        Direction::Up | Direction::Down => rec.size.h - gap,
+    }
+}
+
+// size of the left child that gets `share` of the room, without size limits
+fn share_size(rec: Rectangle<i32, Logical>, direction: &Direction, share: f64, gap: i32) -> i32 {
+    (split_room(rec, direction, gap) as f64 * share).round() as i32
+}
+
+// the two child recs of a split that leaves `size` to the left child
 fn recover_new_rec(
     rec: Rectangle<i32, Logical>,
     direction: &Direction,
-    offset: Point<i32, Logical>,
+    size: i32,
     gap: i32,
 ) -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+    let room = split_room(rec, direction, gap).max(0);
+    let size = size.clamp(0, room);
+
     let mut l_rec = rec;
     let mut r_rec = rec;
 
     match direction {
         Direction::Left | Direction::Right => {
-            let half = rec.size.w / 2 - gap;
-            l_rec.size.w = half + offset.x;
-            r_rec.size.w = half - offset.x;
+            l_rec.size.w = size;
+            r_rec.size.w = room - size;
 
-            r_rec.loc.x += half + gap + offset.x;
+            r_rec.loc.x += size + gap;
         }
         Direction::Up | Direction::Down => {
-            let half = rec.size.h / 2 - gap;
-            l_rec.size.h = half + offset.y;
-            r_rec.size.h = half - offset.y;
+            l_rec.size.h = size;
+            r_rec.size.h = room - size;
 
-            r_rec.loc.y += half + gap + offset.y;
+            r_rec.loc.y += size + gap;
         }
     }
 
     (l_rec, r_rec)
 }
 
-fn get_new_rec(
-    direction: &Direction,
-    rec: &mut Rectangle<i32, Logical>,
-    gap: i32,
-) -> Rectangle<i32, Logical> {
-    let mut new_rec = *rec;
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-    match direction {
-        Direction::Left | Direction::Right => {
-            let half = (rec.size.w - gap) / 2;
-            new_rec.size.w = half;
-            rec.size.w = half;
+    use super::*;
 
-            if direction == &Direction::Left {
-                rec.loc.x += half + gap;
+    const GAP: i32 = 8;
+
+    // windows are plain numbers here, multiples of three ask for a min size
+    impl TiledWindow for u32 {
+        fn size_limits(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+            if self.is_multiple_of(3) {
+                ((200, 150).into(), (0, 0).into())
             } else {
-                new_rec.loc.x += half + gap;
+                ((0, 0).into(), (0, 0).into())
             }
+        }
+    }
+
+    // xorshift, enough to shuffle the operations of the property tests
+    struct Rng(u64);
 
-            new_rec
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
         }
-        Direction::Up | Direction::Down => {
-            let half = (rec.size.h - gap) / 2;
-            new_rec.size.h = half;
-            rec.size.h = half;
+    }
 
-            if direction == &Direction::Up {
-                rec.loc.y += half + gap;
-            } else {
-                new_rec.loc.y += half + gap;
+    fn root() -> Rectangle<i32, Logical> {
+        Rectangle::new((10, 20).into(), (1920, 1080).into())
+    }
+
+    fn rec(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::new((x, y).into(), (w, h).into())
+    }
+
+    // stands in for the space, every window sits where it was placed last
+    fn place(mapped: &mut HashMap<u32, Rectangle<i32, Logical>>, placements: Vec<Placement<u32>>) {
+        for placement in placements {
+            mapped.insert(placement.window, placement.rec);
+        }
+    }
+
+    fn overlap(a: Rectangle<i32, Logical>, b: Rectangle<i32, Logical>) -> bool {
+        a.loc.x < b.loc.x + b.size.w
+            && b.loc.x < a.loc.x + a.size.w
+            && a.loc.y < b.loc.y + b.size.h
+            && b.loc.y < a.loc.y + a.size.h
+    }
+
+    // `b` lies on the `direction` side of `a`, across exactly one gap
+    fn facing(
+        a: Rectangle<i32, Logical>,
+        b: Rectangle<i32, Logical>,
+        direction: &Direction,
+    ) -> bool {
+        let overlap_x = a.loc.x < b.loc.x + b.size.w && b.loc.x < a.loc.x + a.size.w;
+        let overlap_y = a.loc.y < b.loc.y + b.size.h && b.loc.y < a.loc.y + a.size.h;
+        match direction {
+            Direction::Right => overlap_y && b.loc.x == a.loc.x + a.size.w + GAP,
+            Direction::Left => overlap_y && a.loc.x == b.loc.x + b.size.w + GAP,
+            Direction::Down => overlap_x && b.loc.y == a.loc.y + a.size.h + GAP,
+            Direction::Up => overlap_x && a.loc.y == b.loc.y + b.size.h + GAP,
+        }
+    }
+
+    // every split hands its whole rec to its children and the gap between them
+    fn check_cover(tree: &TiledTree<u32>, id: NodeId) {
+        let NodeData::Split {
+            direction,
+            rec,
+            left,
+            right,
+            ..
+        } = &tree.nodes[id]
+        else {
+            return;
+        };
+
+        let (l_rec, r_rec) = (tree.node_rec(*left), tree.node_rec(*right));
+        assert_eq!(l_rec.loc, rec.loc);
+        match direction {
+            Direction::Left | Direction::Right => {
+                assert_eq!((l_rec.size.h, r_rec.size.h), (rec.size.h, rec.size.h));
+                assert_eq!(r_rec.loc, rec.loc + Point::from((l_rec.size.w + GAP, 0)));
+                assert_eq!(l_rec.size.w + GAP + r_rec.size.w, rec.size.w);
+            }
+            Direction::Up | Direction::Down => {
+                assert_eq!((l_rec.size.w, r_rec.size.w), (rec.size.w, rec.size.w));
+                assert_eq!(r_rec.loc, rec.loc + Point::from((0, l_rec.size.h + GAP)));
+                assert_eq!(l_rec.size.h + GAP + r_rec.size.h, rec.size.h);
             }
+        }
+
+        check_cover(tree, *left);
+        check_cover(tree, *right);
+    }
+
+    fn check(tree: &TiledTree<u32>, mapped: &HashMap<u32, Rectangle<i32, Logical>>) {
+        let Some(root_id) = tree.root else {
+            assert!(mapped.is_empty());
+            return;
+        };
+
+        // the leaves cover the root, apart from the gaps between them
+        assert_eq!(tree.node_rec(root_id), root());
+        check_cover(tree, root_id);
+
+        let recs = tree.leaf_recs();
+        for (i, (a, a_rec)) in recs.iter().enumerate() {
+            assert!(a_rec.size.w > 0 && a_rec.size.h > 0, "{a} is empty");
+            assert_eq!(tree.node_rec(tree.find_node(a).unwrap()), *a_rec);
 
-            new_rec
+            for (b, b_rec) in &recs[i + 1..] {
+                assert!(!overlap(*a_rec, *b_rec), "{a} overlaps {b}");
+            }
+        }
+
+        // the placements left every window where the tree has it
+        assert_eq!(mapped.len(), tree.get_count());
+        for node in tree.nodes.values() {
+            match node {
+                NodeData::Leaf { window, rec } => assert_eq!(mapped.get(window), Some(rec)),
+                NodeData::Container {
+                    kind, windows, rec, ..
+                } => {
+                    let content = content_rec(*rec, *kind, windows.len());
+                    for window in windows {
+                        assert_eq!(mapped.get(window), Some(&content));
+                    }
+                }
+                NodeData::Split { .. } => {}
+            }
+        }
+
+        // the graph knows exactly the windows that face each other
+        for (a, a_rec) in &recs {
+            for direction in &Direction::ALL {
+                let mut expected: Vec<u32> = recs
+                    .iter()
+                    .filter(|(b, b_rec)| b != a && facing(*a_rec, *b_rec, direction))
+                    .map(|(b, _)| *b)
+                    .collect();
+                let mut neighbors = tree.neighbors(a, direction).cloned().unwrap_or_default();
+                expected.sort();
+                neighbors.sort();
+                assert_eq!(neighbors, expected, "neighbors of {a} to the {direction:?}");
+            }
         }
     }
-}
 
+    // run random operations on a tree of up to `max` windows, checking the
+    // geometry after each of them
+    fn random_ops(seed: u64, max: usize) {
+        let mut rng = Rng(seed);
+        let mut next = 1;
+        let mut tree = TiledTree::new(0, root(), GAP);
+        let mut mapped = HashMap::from([(0, root())]);
+
+        for _ in 0..300 {
+            if tree.is_empty() {
+                tree = TiledTree::new(next, root(), GAP);
+                mapped.insert(next, root());
+                next += 1;
+                continue;
+            }
+
+            let windows = tree.windows();
+            let window = windows[rng.below(windows.len())];
+            let direction = Direction::ALL[rng.below(4)].clone();
+            let full = windows.len() >= max;
+
+            let placements = match rng.below(12) {
+                0 | 1 if !full => {
+                    next += 1;
+                    tree.insert_window(Some(&window), next - 1, direction)
+                }
+                2 if !full => {
+                    next += 1;
+                    tree.insert_window_spiral(next - 1)
+                }
+                3 => {
+                    let mut focus = Some(window);
+                    mapped.remove(&window);
+                    let placements = tree.remove(&window, &mut focus);
+                    assert_ne!(focus, Some(window));
+                    placements
+                }
+                4 => {
+                    let amount = (rng.below(7) as f64 - 3.0) / 20.0;
+                    tree.resize_ratio(&window, &direction, amount)
+                }
+                5 => {
+                    let edges = [
+                        ResizeEdge::Left,
+                        ResizeEdge::TopRight,
+                        ResizeEdge::BottomLeft,
+                        ResizeEdge::Bottom,
+                    ][rng.below(4)];
+                    let delta = (rng.below(301) as i32 - 150, rng.below(301) as i32 - 150);
+                    tree.resize(&window, edges, delta.into())
+                }
+                6 => tree.exchange(&window, &direction),
+                7 => {
+                    let kind = [ContainerKind::Tabbed, ContainerKind::Stacked][rng.below(2)];
+                    tree.toggle_container(&window, kind)
+                }
+                8 => tree.invert_window(&window),
+                9 => {
+                    tree.cycle_tab(&window, 1);
+                    vec![]
+                }
+                10 => tree.equalize(),
+                // a fullscreen window leaves its slot and comes back
+                11 => match tree.slot(&window) {
+                    Some(slot) => {
+                        let mut focus = None;
+                        mapped.remove(&window);
+                        let placements = tree.remove(&window, &mut focus);
+                        place(&mut mapped, placements);
+                        check(&tree, &mapped);
+
+                        let placements = tree.restore(slot, window);
+                        assert!(!placements.is_empty());
+                        placements
+                    }
+                    None => vec![],
+                },
+                _ => vec![],
+            };
+
+            place(&mut mapped, placements);
+            check(&tree, &mapped);
+        }
+    }
+
+    #[test]
+    fn halves_leave_one_gap() {
+        let mut tree = TiledTree::new(1, root(), GAP);
+        let mut placements = tree.insert_window(Some(&1), 2, Direction::Right);
+
+        assert_eq!(placements.len(), 2);
+        let new = placements.pop().unwrap();
+        assert_eq!(new.window, 2);
+        assert_eq!(new.rec, rec(10 + 956 + GAP, 20, 956, 1080));
+        assert_eq!(new.from, Some(rec(10 + 956 * 2 + GAP, 20, 956, 1080)));
+
+        let old = placements.pop().unwrap();
+        assert_eq!(
+            (old.window, old.rec, old.from),
+            (1, rec(10, 20, 956, 1080), None)
+        );
+        assert_eq!(tree.neighbors(&1, &Direction::Right), Some(&vec![2]));
+        assert_eq!(tree.neighbors(&2, &Direction::Left), Some(&vec![1]));
+    }
+
+    #[test]
+    fn ratio_survives_a_new_root() {
+        let mut tree = TiledTree::new(1, root(), GAP);
+        tree.insert_window(Some(&1), 2, Direction::Right);
+        tree.resize_ratio(&1, &Direction::Right, -0.25);
+
+        let placements = tree.arrange(rec(0, 0, 968, 500));
+        let recs: Vec<_> = placements.iter().map(|p| (p.window, p.rec)).collect();
+        assert_eq!(
+            recs,
+            vec![(1, rec(0, 0, 240, 500)), (2, rec(248, 0, 720, 500))]
+        );
+    }
+
+    #[test]
+    fn min_size_bends_the_ratio() {
+        let mut tree = TiledTree::new(1, root(), GAP);
+        tree.insert_window(Some(&1), 3, Direction::Right);
+
+        let placements = tree.resize_ratio(&1, &Direction::Right, 0.45);
+        let recs: Vec<_> = placements.iter().map(|p| (p.window, p.rec)).collect();
+        assert_eq!(
+            recs,
+            vec![(1, rec(10, 20, 1712, 1080)), (3, rec(1730, 20, 200, 1080))]
+        );
+
+        // the border stays put at the limit
+        assert!(tree.resize_ratio(&1, &Direction::Right, 0.05).is_empty());
+    }
+
+    #[test]
+    fn sibling_takes_over_the_parent() {
+        let mut tree = TiledTree::new(1, root(), GAP);
+        tree.insert_window(Some(&1), 2, Direction::Right);
+        tree.insert_window(Some(&2), 4, Direction::Down);
+
+        let mut focus = Some(2);
+        let placements = tree.remove(&2, &mut focus);
+        assert_eq!(focus, Some(4));
+        assert_eq!(placements.len(), 1);
+        assert_eq!(placements[0].window, 4);
+        assert_eq!(placements[0].rec, rec(10 + 956 + GAP, 20, 956, 1080));
+    }
+
+    #[test]
+    fn hidden_tabs_share_the_content() {
+        let mut tree = TiledTree::new(1, root(), GAP);
+        tree.insert_window(Some(&1), 2, Direction::Right);
+        let placements = tree.toggle_container(&2, ContainerKind::Tabbed);
+
+        // the active tab is placed last to stay on top
+        let order: Vec<u32> = placements.iter().map(|p| p.window).collect();
+        assert_eq!(order, vec![1, 2]);
+        assert!(
+            placements
+                .iter()
+                .all(|p| p.rec == content_rec(root(), ContainerKind::Tabbed, 2))
+        );
+        assert!(tree.is_hidden(&1));
+
+        assert_eq!(tree.cycle_tab(&2, 1), Some(1));
+        assert!(tree.is_hidden(&2));
+    }
+
+    #[test]
+    fn random_small_trees() {
+        for seed in 1..=64u64 {
+            random_ops(seed * 0x9e37_79b9, 3);
+        }
+    }
+
+    #[test]
+    fn random_trees() {
+        for seed in 1..=64u64 {
+            random_ops(seed.wrapping_mul(0x2545_f491_4f6c_dd1d), 7);
+        }
+    }
+}
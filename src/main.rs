@@ -12,6 +12,7 @@ mod animation;
 mod backend;
 mod config;
 mod input;
+mod ipc;
 mod layout;
 mod manager;
 mod protocol;
@@ -19,7 +20,7 @@ mod render;
 mod state;
 mod utils;
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use smithay::{
     reexports::{
@@ -32,6 +33,9 @@ use smithay::{
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{self, FmtSubscriber, layer::SubscriberExt};
 
+use backend::BackendType;
+use config::Configs;
+use ipc::{IpcServer, protocol::SOCKET_ENV};
 use state::{ClientState, GlobalData};
 use utils::errors::AnyHowErr;
 
@@ -52,6 +56,49 @@ fn main() -> anyhow::Result<()> {
     tracing::subscriber::set_global_default(subscriber)
         .anyhow_err("Failed to init tracing subscriber")?;
 
+    // parse the command line
+    let mut backend_type = None;
+    let mut command = None;
+    let mut config_path = None;
+    let mut check_config = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        // the only flag without a value
+        if flag == "--check-config" {
+            check_config = true;
+            continue;
+        }
+
+        match (flag.as_str(), args.next()) {
+            ("-c" | "--command", Some(arg)) => command = Some(arg),
+            ("--config", Some(arg)) => config_path = Some(PathBuf::from(arg)),
+            ("-b" | "--backend", Some(arg)) => match BackendType::from_name(&arg) {
+                Some(r) => backend_type = Some(r),
+                None => anyhow::bail!("Unknown backend: {}, expect tty, winit or headless", arg),
+            },
+            (flag, _) => anyhow::bail!("Unknown or incomplete argument: {}", flag),
+        }
+    }
+
+    // validate the config files and exit, no compositor is started
+    if check_config {
+        match Configs::new(config_path) {
+            Ok(configs) => {
+                for file in &configs.files {
+                    println!("{}: ok", file.name);
+                }
+                return Ok(());
+            }
+            Err(errors) => {
+                eprintln!("{}", errors);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let backend_type = backend_type.unwrap_or_else(BackendType::from_env);
+
     // initial main event loop
     let mut event_loop: EventLoop<'_, GlobalData> =
         EventLoop::try_new().anyhow_err("Failed to init main event loop")?;
@@ -90,23 +137,26 @@ fn main() -> anyhow::Result<()> {
     info!(name = socket_name, "Listening on wayland socket.");
 
     // initial the main data
-    let mut global_data =
-        GlobalData::new(loop_handle, display_handle).anyhow_err("Failed to init global data")?;
+    let mut global_data = GlobalData::new(loop_handle, display_handle, backend_type, config_path)
+        .anyhow_err("Failed to init global data")?;
 
     unsafe { std::env::set_var("WAYLAND_DISPLAY", &socket_name) };
 
+    // initial ipc socket, the compositor still works without it
+    match IpcServer::new(&global_data.loop_handle, &socket_name) {
+        Ok(ipc_server) => {
+            unsafe { std::env::set_var(SOCKET_ENV, &ipc_server.socket_path) };
+            global_data.ipc_server = Some(ipc_server);
+        }
+        Err(err) => warn!("Failed to init ipc server: {:?}", err),
+    }
+
     global_data.configs.init();
+    global_data.watch_configs();
 
     // start the project
-    let mut args = std::env::args().skip(1);
-    let flag = args.next();
-    let arg = args.next();
-
-    match (flag.as_deref(), arg) {
-        (Some("-c") | Some("--command"), Some(command)) => {
-            std::process::Command::new(command).spawn().ok();
-        }
-        _ => {}
+    if let Some(command) = command {
+        std::process::Command::new(command).spawn().ok();
     }
 
     info!("Initialization completed, starting the main loop.");
@@ -121,4 +171,3 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
-
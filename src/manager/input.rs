@@ -1,7 +1,6 @@
-use std::{collections::HashMap, fs};
+use std::collections::HashMap;
 
 use itertools::Itertools;
-use regex::Regex;
 
 use smithay::{
     input::{
@@ -10,25 +9,135 @@ use smithay::{
     reexports::wayland_server::DisplayHandle,
 };
 
-use crate::{layout::Direction, state::GlobalData, utils::errors::AnyHowErr};
+use crate::{
+    config::Configs,
+    layout::{Direction, tiled_tree::ContainerKind},
+    state::GlobalData,
+    utils::errors::AnyHowErr,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum FunctionEnum {
-    SwitchWorkspace1,
-    SwitchWorkspace2,
+    // switch to a workspace by name, created when missing
+    Workspace(String),
+    WorkspaceNext,
+    WorkspacePrev,
+    WorkspaceBackAndForth,
+    // move the focused window to a workspace by name
+    MoveToWorkspace(String),
+    // same, then switch to that workspace
+    MoveToWorkspaceFollow(String),
     InvertWindow,
     Expansion,
     Recover,
+    ToggleFloating,
+    // toggle the focused window covering its output
+    Fullscreen,
+    // toggle the focused tiled window filling the tiling area
+    Maximize,
+    FocusNext,
+    Focus(Direction),
+    // move the border of the focused window on that side outwards
+    Grow(Direction),
+    // move the border of the focused window on that side inwards
+    Shrink(Direction),
+    Equalize,
+    // master-stack: make the focus the master, change the master count by n
+    Promote,
+    MasterCount(isize),
+    // scrolling: step the focused column width, move the focus between columns
+    ColumnWidth,
+    ConsumeOrExpel(Direction),
+    // turn the split around the focus into a container and back, show the next n-th tab
+    Container(ContainerKind),
+    CycleTab(isize),
+    ResizeMode,
     Quit,
     Kill,
     Json,
+    JsonLoad,
+    Reload,
     Up(Direction),
     Down(Direction),
     Left(Direction),
     Right(Direction),
 }
 
-#[derive(Debug)]
+impl FunctionEnum {
+    /// Parse the internal function name used by `exec` bindings and ipc actions.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+
+        // functions taking an argument, e.g. `workspace 3`
+        if let Some((function, argument)) = name.split_once(' ') {
+            let argument = argument.trim().to_string();
+            return match function {
+                "workspace" => Some(FunctionEnum::Workspace(argument)),
+                "move-to-workspace" => Some(FunctionEnum::MoveToWorkspace(argument)),
+                "move-to-workspace-follow" => Some(FunctionEnum::MoveToWorkspaceFollow(argument)),
+                _ => None,
+            };
+        }
+
+        let function = match name {
+            "workspace-next" => FunctionEnum::WorkspaceNext,
+            "workspace-prev" => FunctionEnum::WorkspacePrev,
+            "workspace-back-and-forth" => FunctionEnum::WorkspaceBackAndForth,
+            "invert" => FunctionEnum::InvertWindow,
+            "recover" => FunctionEnum::Recover,
+            "expansion" => FunctionEnum::Expansion,
+            "toggle-floating" => FunctionEnum::ToggleFloating,
+            "fullscreen" => FunctionEnum::Fullscreen,
+            "maximize" => FunctionEnum::Maximize,
+            "focus-next" => FunctionEnum::FocusNext,
+            "focus-left" => FunctionEnum::Focus(Direction::Left),
+            "focus-right" => FunctionEnum::Focus(Direction::Right),
+            "focus-up" => FunctionEnum::Focus(Direction::Up),
+            "focus-down" => FunctionEnum::Focus(Direction::Down),
+            "grow-left" => FunctionEnum::Grow(Direction::Left),
+            "grow-right" => FunctionEnum::Grow(Direction::Right),
+            "grow-up" => FunctionEnum::Grow(Direction::Up),
+            "grow-down" => FunctionEnum::Grow(Direction::Down),
+            "shrink-left" => FunctionEnum::Shrink(Direction::Left),
+            "shrink-right" => FunctionEnum::Shrink(Direction::Right),
+            "shrink-up" => FunctionEnum::Shrink(Direction::Up),
+            "shrink-down" => FunctionEnum::Shrink(Direction::Down),
+            "equalize" => FunctionEnum::Equalize,
+            "promote" => FunctionEnum::Promote,
+            "master-count-inc" => FunctionEnum::MasterCount(1),
+            "master-count-dec" => FunctionEnum::MasterCount(-1),
+            "column-width" => FunctionEnum::ColumnWidth,
+            "consume-or-expel-left" => FunctionEnum::ConsumeOrExpel(Direction::Left),
+            "consume-or-expel-right" => FunctionEnum::ConsumeOrExpel(Direction::Right),
+            "tabbed" => FunctionEnum::Container(ContainerKind::Tabbed),
+            "stacked" => FunctionEnum::Container(ContainerKind::Stacked),
+            "tab-next" => FunctionEnum::CycleTab(1),
+            "tab-prev" => FunctionEnum::CycleTab(-1),
+            "resize-mode" => FunctionEnum::ResizeMode,
+            "quit" => FunctionEnum::Quit,
+            "kill" => FunctionEnum::Kill,
+            "json" => FunctionEnum::Json,
+            "json-load" => FunctionEnum::JsonLoad,
+            "reload" => FunctionEnum::Reload,
+            "up" => FunctionEnum::Up(Direction::Up),
+            "down" => FunctionEnum::Down(Direction::Down),
+            "left" => FunctionEnum::Left(Direction::Left),
+            "right" => FunctionEnum::Right(Direction::Right),
+            _ => return None,
+        };
+
+        Some(function)
+    }
+}
+
+/// A modal set of keys that replaces the keybindings until it is left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Submap {
+    // arrows resize the focused window, Escape or Return leaves
+    Resize,
+}
+
+#[derive(Debug, Clone)]
 pub enum KeyAction {
     Command(String, Vec<String>),
     Internal(FunctionEnum),
@@ -41,6 +150,7 @@ pub struct InputManager {
     // keyboard
     keybindings: HashMap<String, KeyAction>,
     priority_map: HashMap<String, i32>,
+    submap: Option<Submap>,
 
     // global data
     is_mainmod_pressed: bool,
@@ -50,7 +160,7 @@ impl InputManager {
     pub fn new(
         seat_name: String,
         display_handle: &DisplayHandle,
-        keybindgings_path: &str,
+        configs: &Configs,
     ) -> anyhow::Result<Self> {
         let mut seat_state = SeatState::new();
         let seat_name = seat_name;
@@ -61,7 +171,7 @@ impl InputManager {
             .anyhow_err("Failed to add keyboard")?;
         seat.add_pointer();
 
-        let keybindings = Self::load_keybindings(keybindgings_path)?;
+        let keybindings = Self::load_keybindings(&configs.keybindings);
 
         // priority: Ctrl > Shift > Alt
         let priority_map: HashMap<String, i32> = [
@@ -82,6 +192,7 @@ impl InputManager {
             seat,
             keybindings,
             priority_map,
+            submap: None,
             is_mainmod_pressed: false,
         })
     }
@@ -94,24 +205,30 @@ impl InputManager {
         self.is_mainmod_pressed
     }
 
+    pub fn submap(&self) -> Option<Submap> {
+        self.submap
+    }
+
+    pub fn set_submap(&mut self, submap: Option<Submap>) {
+        info!("submap: {:?}", submap);
+        self.submap = submap;
+    }
+
     pub fn get_keybindings(&self) -> &HashMap<String, KeyAction> {
         &self.keybindings
     }
 
+    pub fn reload_keybindings(&mut self, configs: &Configs) {
+        self.keybindings = Self::load_keybindings(&configs.keybindings);
+    }
+
     pub fn get_priority_map(&self) -> &HashMap<String, i32> {
         &self.priority_map
     }
 
-    fn load_keybindings(path: &str) -> anyhow::Result<HashMap<String, KeyAction>> {
-        let content = fs::read_to_string(path).anyhow_err("Failed to load keybindings config")?;
+    fn load_keybindings(keybindings: &[(String, KeyAction)]) -> HashMap<String, KeyAction> {
         let mut bindings = HashMap::<String, KeyAction>::new();
 
-        let re =
-            // bind = Ctrl + t, command, "kitty"
-            // bind = Ctrl + 1, exec, "func1"
-            Regex::new(r#"(?m)^\s*bind\s*=\s*([^,]+?),\s*(command|exec),\s*"([^"]+)"(?:\s*#.*)?$"#)
-                .unwrap();
-
         let modifier_map: HashMap<&str, Vec<&str>> = [
             ("Ctrl", vec!["Control_L", "Control_R"]),
             ("Shift", vec!["Shift_L", "Shift_R"]),
@@ -129,11 +246,8 @@ impl InputManager {
         .into_iter()
         .collect();
 
-        for cap in re.captures_iter(&content) {
-            let keybind = &cap[1]; // Ctrl+t / Alt+Enter
-            let action = &cap[2]; // exec / command
-            let command = &cap[3]; // kitty / rofi -show drun
-
+        for (keybind, action) in keybindings {
+            // Ctrl+t / Alt+Enter
             let keys: Vec<String> = keybind
                 .split('+')
                 .map(|key| {
@@ -148,42 +262,7 @@ impl InputManager {
                 .collect();
 
             for key in keys {
-                let action_enum = match action {
-                    "command" => {
-                        let mut parts = command.split_whitespace();
-                        let cmd = parts.next().unwrap_or("").to_string();
-                        let args: Vec<String> = parts.map(|s| s.to_string()).collect();
-
-                        KeyAction::Command(cmd, args)
-                    }
-                    "exec" => {
-                        let internal_action = match command.trim() {
-                            "workspace-1" => FunctionEnum::SwitchWorkspace1,
-                            "workspace-2" => FunctionEnum::SwitchWorkspace2,
-                            "invert" => FunctionEnum::InvertWindow,
-                            "recover" => FunctionEnum::Recover,
-                            "expansion" => FunctionEnum::Expansion,
-                            "quit" => FunctionEnum::Quit,
-                            "kill" => FunctionEnum::Kill,
-                            "json" => FunctionEnum::Json,
-                            "up" => FunctionEnum::Up(Direction::Up),
-                            "down" => FunctionEnum::Down(Direction::Down),
-                            "left" => FunctionEnum::Left(Direction::Left),
-                            "right" => FunctionEnum::Right(Direction::Right),
-                            _ => {
-                                tracing::info!(
-                                    "Warning: No registered function for exec '{}'",
-                                    command
-                                );
-                                continue;
-                            }
-                        };
-                        KeyAction::Internal(internal_action)
-                    }
-                    _ => continue,
-                };
-
-                bindings.insert(key.to_string(), action_enum);
+                bindings.insert(key, action.clone());
             }
         }
 
@@ -192,7 +271,7 @@ impl InputManager {
             tracing::info!(%key, action = ?action, "Keybinding registered");
         }
 
-        Ok(bindings)
+        bindings
     }
 
     pub fn get_keyboard(&self) -> Option<KeyboardHandle<GlobalData>> {
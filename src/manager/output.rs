@@ -3,66 +3,72 @@ use std::sync::Arc;
 use smithay::{
     desktop::{Space, Window},
     output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
-    reexports::wayland_server::DisplayHandle,
+    reexports::wayland_server::{DisplayHandle, backend::GlobalId},
     utils::{Logical, Point, Raw, Rectangle, Size, Transform},
     wayland::output::OutputManagerState,
 };
 
-use crate::{config::Configs, state::GlobalData};
+use crate::{
+    config::{Configs, OutputConfig},
+    ipc::{EventSender, protocol::Event},
+    layout::Direction,
+    state::GlobalData,
+};
 
 #[derive(Debug)]
 pub struct OutputElement {
     pub output: Output,
     pub activate: bool,
+    global: GlobalId,
 }
 
 impl OutputElement {
-    pub fn new(output: Output, activate: bool) -> Self {
-        Self { output, activate }
+    pub fn new(output: Output, activate: bool, global: GlobalId) -> Self {
+        Self {
+            output,
+            activate,
+            global,
+        }
     }
 
     pub fn set_preferred(&mut self, mode: Mode) {
         self.output.set_preferred(mode);
     }
 
-    pub fn change_current_state(
-        &mut self,
-        mode: Option<Mode>,
-        transform: Option<Transform>,
-        scale: Option<Scale>,
-        location: Option<Point<i32, Logical>>,
-    ) {
-        self.output
-            .change_current_state(mode, transform, scale, location);
-    }
-
     pub fn output(&self) -> &Output {
         &self.output
     }
 }
 pub struct OutputManager {
     pub outputs: Vec<OutputElement>,
+    // connected but turned off, only known to output management clients
+    disabled: Vec<Output>,
     #[allow(dead_code)]
     pub output_manager_state: OutputManagerState,
     // This space does not actually contain any windows, but all outputs are
     // mapped into it
     pub output_space: Space<Window>,
 
-    #[allow(dead_code)]
     pub configs: Arc<Configs>,
+
+    display_handle: DisplayHandle,
+    events: EventSender,
 }
 
 impl OutputManager {
-    pub fn new(display_handle: &DisplayHandle, configs: Arc<Configs>) -> Self {
+    pub fn new(display_handle: &DisplayHandle, configs: Arc<Configs>, events: EventSender) -> Self {
         let output_manager_state =
             OutputManagerState::new_with_xdg_output::<GlobalData>(display_handle);
         let output_space: Space<Window> = Default::default();
 
         Self {
             outputs: Vec::new(),
+            disabled: Vec::new(),
             output_manager_state,
             output_space,
             configs,
+            display_handle: display_handle.clone(),
+            events,
         }
     }
 
@@ -75,8 +81,50 @@ impl OutputManager {
         model: String,
         location: Point<i32, Logical>,
         activate: bool,
-        display_handle: &DisplayHandle,
-    ) {
+    ) -> Output {
+        // a disabled output comes back as the same head
+        let output = match self.disabled.iter().position(|o| o.name() == name) {
+            Some(index) => self.disabled.remove(index),
+            None => Output::new(
+                name,
+                PhysicalProperties {
+                    size,
+                    subpixel,
+                    make,
+                    model,
+                },
+            ),
+        };
+        let global = output.create_global::<GlobalData>(&self.display_handle);
+
+        self.output_space.map_output(&output, location);
+
+        self.events.send(Event::OutputAdded {
+            name: output.name(),
+        });
+
+        // only one output has the focus
+        let activate = activate && !self.outputs.iter().any(|o| o.activate);
+        self.outputs
+            .push(OutputElement::new(output.clone(), activate, global));
+
+        output
+    }
+
+    /// Keep a connected output that is turned off, so output management
+    /// clients can turn it on again.
+    pub fn add_disabled_output(
+        &mut self,
+        name: String,
+        size: Size<i32, Raw>,
+        subpixel: Subpixel,
+        make: String,
+        model: String,
+    ) -> Output {
+        if let Some(output) = self.disabled.iter().find(|o| o.name() == name) {
+            return output.clone();
+        }
+
         let output = Output::new(
             name,
             PhysicalProperties {
@@ -86,17 +134,40 @@ impl OutputManager {
                 model,
             },
         );
-        let _ = output.create_global::<GlobalData>(display_handle);
+        self.disabled.push(output.clone());
+        output
+    }
 
-        self.output_space.map_output(&output, location);
+    pub fn disable_output(&mut self, output: &Output) {
+        self.remove_output(output);
+        self.disabled.push(output.clone());
+    }
+
+    // unplugged while turned off
+    pub fn remove_disabled_output(&mut self, name: &str) {
+        self.disabled.retain(|o| o.name() != name);
+    }
 
-        self.outputs.push(OutputElement::new(output, activate));
+    pub fn disabled_outputs(&self) -> impl Iterator<Item = &Output> {
+        self.disabled.iter()
     }
 
     pub fn remove_output(&mut self, output: &Output) {
         if let Some(pos) = self.outputs.iter().position(|o| o.output == *output) {
             self.output_space.unmap_output(output);
-            self.outputs.remove(pos);
+            let removed = self.outputs.remove(pos);
+            self.display_handle
+                .remove_global::<GlobalData>(removed.global);
+
+            if removed.activate {
+                if let Some(first) = self.outputs.first_mut() {
+                    first.activate = true;
+                }
+            }
+
+            self.events.send(Event::OutputRemoved {
+                name: output.name(),
+            });
         } else {
             warn!("Failed to remove output: Output not found in the list");
             return;
@@ -115,21 +186,125 @@ impl OutputManager {
         self.outputs.iter().find(|o| o.activate).unwrap().output()
     }
 
+    pub fn outputs(&self) -> impl Iterator<Item = &Output> {
+        self.outputs.iter().map(|o| o.output())
+    }
+
+    pub fn output_by_name(&self, name: &str) -> Option<&Output> {
+        self.outputs
+            .iter()
+            .map(|o| o.output())
+            .find(|o| o.name() == name)
+    }
+
+    pub fn set_current(&mut self, output: &Output) {
+        for element in &mut self.outputs {
+            element.activate = element.output == *output;
+        }
+    }
+
+    /// The closest output lying on the `direction` side of `output` and
+    /// overlapping it on the other axis.
+    pub fn neighbor_output(&self, output: &Output, direction: &Direction) -> Option<&Output> {
+        let geo = self.output_geometry(output)?;
+        let center = geo.loc + geo.size.downscale(2).to_point();
+
+        self.outputs
+            .iter()
+            .map(|o| o.output())
+            .filter(|o| *o != output)
+            .filter_map(|o| Some((o, self.output_geometry(o)?)))
+            .filter(|(_, other)| {
+                let overlap_x =
+                    other.loc.x < geo.loc.x + geo.size.w && geo.loc.x < other.loc.x + other.size.w;
+                let overlap_y =
+                    other.loc.y < geo.loc.y + geo.size.h && geo.loc.y < other.loc.y + other.size.h;
+
+                match direction {
+                    Direction::Left => overlap_y && other.loc.x + other.size.w <= geo.loc.x,
+                    Direction::Right => overlap_y && other.loc.x >= geo.loc.x + geo.size.w,
+                    Direction::Up => overlap_x && other.loc.y + other.size.h <= geo.loc.y,
+                    Direction::Down => overlap_x && other.loc.y >= geo.loc.y + geo.size.h,
+                }
+            })
+            .min_by_key(|(_, other)| {
+                let other_center = other.loc + other.size.downscale(2).to_point();
+                (other_center.x - center.x).abs() + (other_center.y - center.y).abs()
+            })
+            .map(|(o, _)| o)
+    }
+
+    pub fn output_geometry(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        self.output_space.output_geometry(output)
+    }
+
+    /// Every output with its geometry, the focused one first.
+    pub fn outputs_geometry(&self) -> Vec<(Output, Rectangle<i32, Logical>)> {
+        let mut outputs: Vec<&OutputElement> = self.outputs.iter().collect();
+        outputs.sort_by_key(|o| !o.activate);
+
+        outputs
+            .into_iter()
+            .filter_map(|o| Some((o.output.clone(), self.output_geometry(&o.output)?)))
+            .collect()
+    }
+
+    /// The output containing `position`.
+    pub fn output_at(&self, position: Point<f64, Logical>) -> Option<&Output> {
+        self.output_space.output_under(position).next()
+    }
+
+    /// Where a new output goes, right of the existing ones.
+    pub fn next_location(&self) -> Point<i32, Logical> {
+        let right = self
+            .outputs
+            .iter()
+            .filter_map(|o| self.output_geometry(&o.output))
+            .map(|geo| geo.loc.x + geo.size.w)
+            .max()
+            .unwrap_or(0);
+
+        (right, 0).into()
+    }
+
     pub fn change_current_state(
         &mut self,
+        output: &Output,
         mode: Option<Mode>,
         transform: Option<Transform>,
         scale: Option<Scale>,
         location: Option<Point<i32, Logical>>,
     ) {
-        self.outputs
-            .iter_mut()
-            .find(|o| o.activate)
-            .unwrap()
-            .change_current_state(mode, transform, scale, location);
+        output.change_current_state(mode, transform, scale, location);
+        // the space caches the output size, so map it again on any change
+        let location = location.or_else(|| self.output_geometry(output).map(|geo| geo.loc));
+        if let Some(location) = location {
+            self.output_space.map_output(output, location);
+        }
     }
 
-    pub fn output_geometry(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
-        self.output_space.output_geometry(output)
+    pub fn output_config(&self, output: &Output) -> Option<&OutputConfig> {
+        let properties = output.physical_properties();
+        self.configs
+            .output_config(&output.name(), &properties.make, &properties.model)
+    }
+
+    /// Scale, transform and position from the output's config block, unset
+    /// ones fall back to the defaults. `mode` is picked by the backend, which
+    /// knows the modes available.
+    pub fn apply_config(&mut self, output: &Output, mode: Option<Mode>, transform: Transform) {
+        let config = self.output_config(output).cloned();
+
+        let transform = config
+            .as_ref()
+            .and_then(|c| c.transform)
+            .unwrap_or(transform);
+        let scale = config
+            .as_ref()
+            .and_then(|c| c.scale)
+            .map_or(Scale::Integer(1), Scale::Fractional);
+        let location = config.and_then(|c| c.position).map(Point::from);
+
+        self.change_current_state(output, mode, Some(transform), Some(scale), location);
     }
 }
@@ -1,7 +1,4 @@
-use std::{
-    collections::HashMap,
-    time::{Duration, Instant},
-};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 use smithay::{
     backend::renderer::{
@@ -14,18 +11,22 @@ use smithay::{
         gles::{GlesRenderer, Uniform},
     },
     desktop::{Window, layer_map_for_output},
-    utils::{Logical, Rectangle, Scale},
+    output::Output,
+    utils::{Logical, Point, Rectangle, Scale},
     wayland::shell::wlr_layer::Layer,
 };
 
 use crate::{
     animation::{Animation, AnimationState, AnimationType},
+    config::Configs,
     manager::window::WindowExt,
     render::{
         MondrianRenderer,
         background::{Background, BackgroundRenderElement},
         border::{BorderRenderElement, BorderShader},
         elements::{CustomRenderElements, OutputRenderElements, ShaderRenderElement},
+        shader::PixelShader,
+        title::{TitleRenderElement, TitleShader},
     },
 };
 
@@ -40,24 +41,29 @@ pub struct RenderManager {
     // no need now
     start_time: Instant,
     animations: HashMap<Window, Animation>,
+
+    pub configs: Arc<Configs>,
 }
 
 impl RenderManager {
-    pub fn new() -> Self {
+    pub fn new(configs: Arc<Configs>) -> Self {
         Self {
             start_time: Instant::now(),
             animations: HashMap::new(),
+            configs,
         }
     }
 
     pub fn compile_shaders(&self, renderer: &mut GlesRenderer) {
-        BorderRenderElement::complie_shaders(renderer);
+        BorderShader::compile(renderer);
         BackgroundRenderElement::complie_shaders(renderer);
+        TitleShader::compile(renderer);
     }
 
     pub fn get_render_elements<R: MondrianRenderer>(
         &mut self,
         renderer: &mut R,
+        output: &Output,
         output_manager: &OutputManager,
         workspace_manager: &WorkspaceManager,
         cursor_manager: &mut CursorManager,
@@ -69,6 +75,7 @@ impl RenderManager {
         output_elements.extend(
             self.get_cursor_render_elements(
                 renderer,
+                output,
                 output_manager,
                 cursor_manager,
                 input_manager,
@@ -79,18 +86,43 @@ impl RenderManager {
 
         // Then Some Control elements
 
-        // Then fullscreen
-
         // Then Border
         output_elements.extend(
-            self.get_border_render_elements(renderer, workspace_manager)
+            self.get_border_render_elements(renderer, output, output_manager, workspace_manager)
+                .into_iter()
+                .map(OutputRenderElements::Custom),
+        );
+
+        // Then overlay layer, it stays above fullscreen windows
+        output_elements.extend(
+            self.get_layer_render_elements(renderer, output, &[Layer::Overlay])
                 .into_iter()
+                .map(CustomRenderElements::Surface)
                 .map(OutputRenderElements::Custom),
         );
 
+        // Then fullscreen
+        output_elements.extend(
+            self.get_fullscreen_render_elements(
+                renderer,
+                output,
+                output_manager,
+                workspace_manager,
+            )
+            .into_iter()
+            .map(OutputRenderElements::Custom),
+        );
+
         // Then common Windows
         output_elements.extend(
-            self.get_windows_render_elements(renderer, output_manager, workspace_manager)
+            self.get_windows_render_elements(renderer, output, output_manager, workspace_manager)
+                .into_iter()
+                .map(OutputRenderElements::Custom),
+        );
+
+        // Then the titles of tabbed and stacked containers
+        output_elements.extend(
+            self.get_title_render_elements(renderer, output, output_manager, workspace_manager)
                 .into_iter()
                 .map(OutputRenderElements::Custom),
         );
@@ -110,6 +142,7 @@ impl RenderManager {
     pub fn get_windows_render_elements<R: MondrianRenderer>(
         &mut self,
         renderer: &mut R,
+        output: &Output,
         output_manager: &OutputManager,
         workspace_manager: &WorkspaceManager,
     ) -> Vec<CustomRenderElements<R>> {
@@ -118,69 +151,113 @@ impl RenderManager {
 
         let mut elements: Vec<WaylandSurfaceRenderElement<R>> = vec![];
 
-        let output = output_manager.current_output();
-        let output_geo = output_manager.output_geometry(output).unwrap();
+        let Some(output_geo) = output_manager.output_geometry(output) else {
+            warn!("Failed to get output {:?} geometry", output);
+            return vec![];
+        };
         let output_scale = output.current_scale().fractional_scale();
 
-        // layer shell top and overlap
-        let layer_map = layer_map_for_output(output);
+        // layer shell top
+        elements.extend(self.get_layer_render_elements(renderer, output, &[Layer::Top]));
 
-        for layer in [Layer::Overlay, Layer::Top] {
-            for layer_surface in layer_map.layers_on(layer) {
-                let layout_rec = layer_map.layer_geometry(layer_surface).unwrap();
-                elements.extend(
-                    layer_surface.render_elements::<WaylandSurfaceRenderElement<R>>(
-                        renderer,
-                        (layout_rec.loc + output_geo.loc).to_physical_precise_round(output_scale),
-                        Scale::from(output_scale),
-                        0.85,
-                    ),
-                );
+        // windows from top to bottom, floating ones above the tiled, unless
+        // a fullscreen window hides them
+        let workspace = workspace_manager
+            .visible_workspace(output)
+            .filter(|w| w.fullscreen().is_none());
+        for window in workspace
+            .into_iter()
+            .flat_map(|w| w.floating_elements().rev().chain(w.tiled_elements().rev()))
+            .filter(|window| !workspace.is_some_and(|w| w.is_hidden(window)))
+        {
+            let Some(location) = self.window_location(window, workspace_manager) else {
+                continue;
+            };
+
+            // scrolled out of the viewport
+            if !output_geo.overlaps(Rectangle::new(location, window.geometry().size)) {
+                continue;
             }
+
+            elements.extend(
+                window.render_elements::<WaylandSurfaceRenderElement<R>>(
+                    renderer,
+                    (location - output_geo.loc - window.geometry().loc)
+                        .to_physical_precise_round(output_scale),
+                    Scale::from(output_scale),
+                    0.8,
+                ),
+            );
         }
 
-        // windows
-        for window in workspace_manager.elements() {
-            let location = match self.animations.get_mut(window) {
-                Some(animation) => {
-                    match animation.state {
-                        AnimationState::NotStarted => {
-                            let rec = animation.start();
-                            window.set_rec(rec.size);
-                            rec.loc
-                        }
-                        AnimationState::Running => {
-                            animation.tick();
-                            let rec = animation.current_value();
-                            window.set_rec(rec.size);
-                            // info!("{:?}", rec);
-                            rec.loc
-                        }
-                        _ => break,
-                    }
-                }
-                None => {
-                    let window_rec = workspace_manager.window_geometry(window).unwrap();
-                    window_rec.loc
-                }
-            };
+        // layer shell bottom and background
+        elements.extend(self.get_layer_render_elements(
+            renderer,
+            output,
+            &[Layer::Bottom, Layer::Background],
+        ));
+
+        elements
+            .into_iter()
+            .map(CustomRenderElements::Surface)
+            .collect()
+    }
+
+    pub fn get_fullscreen_render_elements<R: MondrianRenderer>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        output_manager: &OutputManager,
+        workspace_manager: &WorkspaceManager,
+    ) -> Vec<CustomRenderElements<R>> {
+        let Some(window) = workspace_manager
+            .visible_workspace(output)
+            .and_then(|w| w.fullscreen())
+        else {
+            return vec![];
+        };
+
+        let Some(output_geo) = output_manager.output_geometry(output) else {
+            warn!("Failed to get output {:?} geometry", output);
+            return vec![];
+        };
+        let output_scale = output.current_scale().fractional_scale();
 
-            elements.extend(window.render_elements::<WaylandSurfaceRenderElement<R>>(
+        let Some(location) = self.window_location(window, workspace_manager) else {
+            return vec![];
+        };
+
+        window
+            .render_elements::<WaylandSurfaceRenderElement<R>>(
                 renderer,
-                (location - window.geometry().loc).to_physical_precise_round(output_scale),
+                (location - output_geo.loc - window.geometry().loc)
+                    .to_physical_precise_round(output_scale),
                 Scale::from(output_scale),
-                0.8,
-            ));
-        }
+                1.0,
+            )
+            .into_iter()
+            .map(CustomRenderElements::Surface)
+            .collect()
+    }
 
-        // layer shell bottom and background
-        for layer in [Layer::Bottom, Layer::Background] {
-            for layer_surface in layer_map.layers_on(layer) {
+    fn get_layer_render_elements<R: MondrianRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        layers: &[Layer],
+    ) -> Vec<WaylandSurfaceRenderElement<R>> {
+        let mut elements = vec![];
+
+        let output_scale = output.current_scale().fractional_scale();
+        let layer_map = layer_map_for_output(output);
+
+        for layer in layers {
+            for layer_surface in layer_map.layers_on(*layer) {
                 let layout_rec = layer_map.layer_geometry(layer_surface).unwrap();
                 elements.extend(
                     layer_surface.render_elements::<WaylandSurfaceRenderElement<R>>(
                         renderer,
-                        (layout_rec.loc + output_geo.loc).to_physical_precise_round(output_scale),
+                        layout_rec.loc.to_physical_precise_round(output_scale),
                         Scale::from(output_scale),
                         0.85,
                     ),
@@ -189,21 +266,42 @@ impl RenderManager {
         }
 
         elements
-            .into_iter()
-            .map(CustomRenderElements::Surface)
-            .collect()
+    }
+
+    // where to draw the window this frame, stepping its animation
+    fn window_location(
+        &mut self,
+        window: &Window,
+        workspace_manager: &WorkspaceManager,
+    ) -> Option<Point<i32, Logical>> {
+        match self.animations.get_mut(window) {
+            Some(animation) if matches!(animation.state, AnimationState::NotStarted) => {
+                let rec = animation.start();
+                window.set_rec(rec.size);
+                Some(rec.loc)
+            }
+            Some(animation) if matches!(animation.state, AnimationState::Running) => {
+                animation.tick();
+                let rec = animation.current_value();
+                window.set_rec(rec.size);
+                Some(rec.loc)
+            }
+            _ => workspace_manager
+                .window_geometry(window)
+                .map(|window_rec| window_rec.loc),
+        }
     }
 
     pub fn get_cursor_render_elements<R: MondrianRenderer>(
         &self,
         renderer: &mut R,
+        output: &Output,
         output_manager: &OutputManager,
         cursor_manager: &mut CursorManager,
         input_manager: &InputManager,
     ) -> Vec<CustomRenderElements<R>> {
         cursor_manager.check_cursor_image_surface_alive();
 
-        let output = output_manager.current_output();
         let output_scale = output.current_scale();
 
         let output_geo = match output_manager.output_geometry(&output) {
@@ -224,6 +322,13 @@ impl RenderManager {
         };
 
         let pointer_pos = pointer.current_location();
+        // only the output under the pointer draws it
+        if output_manager
+            .output_at(pointer_pos)
+            .is_some_and(|o| o != output)
+        {
+            return vec![];
+        }
         let pointer_pos = pointer_pos - output_pos.to_f64();
 
         let cursor_scale = output_scale.integer_scale();
@@ -287,25 +392,37 @@ impl RenderManager {
     pub fn get_border_render_elements<R: MondrianRenderer>(
         &self,
         renderer: &mut R,
+        output: &Output,
+        output_manager: &OutputManager,
         workspace_manager: &WorkspaceManager,
     ) -> Vec<CustomRenderElements<R>> {
         let mut elements: Vec<CustomRenderElements<R>> = vec![];
 
-        let focus = workspace_manager.current_workspace().focus();
-        if let Some(window) = focus {
-            let window_rec = match self.animations.get(window) {
-                Some(animation) => animation.current_value(),
-                None => workspace_manager.window_geometry(window).unwrap(),
-            };
+        // only the focused output shows the focus
+        let workspace = workspace_manager.current_workspace();
+        let Some(output_geo) = output_manager
+            .output_geometry(output)
+            .filter(|_| workspace.output() == output)
+        else {
+            return elements;
+        };
 
-            let program = renderer
-                .as_gles_renderer()
-                .egl_context()
-                .user_data()
-                .get::<BorderShader>()
-                .unwrap()
-                .0
-                .clone();
+        // a fullscreen window has no border
+        let focus = workspace
+            .focus()
+            .filter(|focus| workspace.fullscreen() != Some(*focus));
+        let focus_rec = focus.map(|window| match self.animations.get(window) {
+            Some(animation) => animation.current_value(),
+            None => workspace_manager.window_geometry(window).unwrap(),
+        });
+
+        // the half a dragged window would drop into gets the same border
+        let drop_preview = workspace.drop_preview();
+
+        for mut window_rec in focus_rec.into_iter().chain(drop_preview) {
+            window_rec.loc -= output_geo.loc;
+
+            let program = BorderShader::compiled(renderer.as_gles_renderer());
 
             let point = window_rec.size.to_point();
 
@@ -315,8 +432,10 @@ impl RenderManager {
             // zero out everything but the last 8 bits. This is where the color
             // has been shifted to.
 
-            let border_color: Color32F = Color32F::from([0.0, 0.0, 1.0, 1.0]);
-            let border_thickness = 5.0;
+            let decoration = &self.configs.conf_decoration;
+            let [r, g, b] = decoration.border_color;
+            let border_color: Color32F = Color32F::from([r, g, b, 1.0]);
+            let border_thickness = decoration.border_width;
 
             elements.push(CustomRenderElements::Shader(ShaderRenderElement::Border(
                 BorderRenderElement::new(
@@ -335,7 +454,52 @@ impl RenderManager {
                             "u_time",
                             self.start_time.elapsed().as_secs_f32() % (2.0 * 3.1415926),
                         ), // TODO: just a test
-                        Uniform::new("corner_radius", 10.0),
+                        Uniform::new("corner_radius", decoration.corner_radius),
+                    ],
+                    Kind::Unspecified,
+                ),
+            )));
+        }
+
+        elements
+    }
+
+    pub fn get_title_render_elements<R: MondrianRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        output_manager: &OutputManager,
+        workspace_manager: &WorkspaceManager,
+    ) -> Vec<CustomRenderElements<R>> {
+        let mut elements: Vec<CustomRenderElements<R>> = vec![];
+
+        let Some(output_geo) = output_manager.output_geometry(output) else {
+            return elements;
+        };
+        let Some(workspace) = workspace_manager
+            .visible_workspace(output)
+            .filter(|w| w.fullscreen().is_none())
+        else {
+            return elements;
+        };
+
+        let program = TitleShader::compiled(renderer.as_gles_renderer());
+        let [r, g, b] = self.configs.conf_decoration.border_color;
+
+        for (mut rec, active) in workspace.title_bars() {
+            rec.loc -= output_geo.loc;
+            let point = rec.size.to_point();
+
+            elements.push(CustomRenderElements::Shader(ShaderRenderElement::Title(
+                TitleRenderElement::new(
+                    program.clone(),
+                    rec,
+                    None,
+                    1.0,
+                    vec![
+                        Uniform::new("u_resolution", (point.x as f32, point.y as f32)),
+                        Uniform::new("u_color", (r, g, b)),
+                        Uniform::new("u_active", if active { 1.0 } else { 0.0 }),
                     ],
                     Kind::Unspecified,
                 ),
@@ -391,10 +555,16 @@ impl RenderManager {
         window: Window,
         from: Rectangle<i32, Logical>,
         to: Rectangle<i32, Logical>,
-        duration: Duration,
         animation_type: AnimationType,
     ) {
-        let animation = Animation::new(from, to, duration, animation_type);
+        let configs = &self.configs.conf_animation;
+        if !configs.enabled {
+            self.animations.remove(&window);
+            return;
+        }
+
+        let animation_type = configs.curve.unwrap_or(animation_type);
+        let animation = Animation::new(from, to, configs.duration, animation_type);
         self.animations.insert(window, animation);
     }
 
@@ -1,26 +1,59 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use smithay::{
     desktop::Window,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
     utils::{Logical, Size},
     wayland::{
-        compositor, foreign_toplevel_list::ForeignToplevelHandle,
-        shell::xdg::XdgToplevelSurfaceData,
+        compositor,
+        foreign_toplevel_list::ForeignToplevelHandle,
+        shell::xdg::{SurfaceCachedState, XdgToplevelSurfaceData},
     },
 };
 
-use crate::state::{GlobalData, State};
+use crate::{
+    ipc::{EventSender, protocol::Event},
+    state::{GlobalData, State},
+};
 
 use super::workspace::WorkspaceId;
 
+static NEXT_WINDOW_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowId(u64);
+
+impl WindowId {
+    #[inline]
+    pub fn next() -> Self {
+        Self(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+}
+
 pub trait WindowExt {
+    fn id(&self) -> WindowId;
     fn set_rec(&self, size: Size<i32, Logical>);
+    fn size_limits(&self) -> (Size<i32, Logical>, Size<i32, Logical>);
+    fn should_float(&self) -> bool;
     #[allow(dead_code)]
     fn get_title_and_id(&self) -> Option<(Option<String>, Option<String>)>;
 }
 
 impl WindowExt for Window {
+    // stable id handed out to ipc clients, assigned on first use
+    fn id(&self) -> WindowId {
+        self.user_data()
+            .insert_if_missing_threadsafe(WindowId::next);
+        *self.user_data().get::<WindowId>().unwrap()
+    }
+
     fn set_rec(&self, size: Size<i32, Logical>) {
         self.toplevel()
             .unwrap()
@@ -29,6 +62,26 @@ impl WindowExt for Window {
         self.toplevel().unwrap().send_pending_configure();
     }
 
+    // min and max size from the client, 0 means unlimited
+    fn size_limits(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+        compositor::with_states(self.toplevel().unwrap().wl_surface(), |states| {
+            let mut guard = states.cached_state.get::<SurfaceCachedState>();
+            let data = guard.current();
+            (data.min_size, data.max_size)
+        })
+    }
+
+    // dialogs and fixed size windows don't fit into tiles
+    fn should_float(&self) -> bool {
+        let toplevel = self.toplevel().unwrap();
+        if toplevel.parent().is_some() {
+            return true;
+        }
+
+        let (min_size, max_size) = self.size_limits();
+        min_size.w > 0 && min_size.h > 0 && min_size == max_size
+    }
+
     fn get_title_and_id(&self) -> Option<(Option<String>, Option<String>)> {
         self.toplevel().and_then(|toplevel| {
             compositor::with_states(toplevel.wl_surface(), |states| {
@@ -48,14 +101,17 @@ pub struct WindowManager {
     pub windows: Vec<Window>,
     pub window_workspace: HashMap<Window, WorkspaceId>,
     pub foreign_handle: HashMap<WlSurface, ForeignToplevelHandle>,
+
+    events: EventSender,
 }
 
 impl WindowManager {
-    pub fn new() -> Self {
+    pub fn new(events: EventSender) -> Self {
         Self {
             windows: Vec::new(),
             window_workspace: HashMap::new(),
             foreign_handle: HashMap::new(),
+            events,
         }
     }
 
@@ -72,6 +128,11 @@ impl WindowManager {
         self.foreign_handle
             .insert(window.toplevel().unwrap().wl_surface().clone(), handle);
 
+        self.events.send(Event::WindowAdded {
+            id: window.id().get(),
+            workspace: workspace_id.get(),
+        });
+
         self.window_workspace.insert(window.clone(), workspace_id);
         self.windows.push(window);
     }
@@ -85,7 +146,12 @@ impl WindowManager {
             }
         };
 
-        self.window_workspace.remove(&window);
+        let workspace_id = self.window_workspace.remove(&window);
+        self.events.send(Event::WindowRemoved {
+            id: window.id().get(),
+            workspace: workspace_id.map(|id| id.get()),
+        });
+
         self.foreign_handle
             .remove(window.toplevel().unwrap().wl_surface());
 
@@ -96,6 +162,35 @@ impl WindowManager {
         None
     }
 
+    pub fn move_window(&mut self, window: &Window, workspace_id: WorkspaceId) {
+        let previous = self.window_workspace.insert(window.clone(), workspace_id);
+
+        // ext-foreign-toplevel-list has no notion of workspaces, listeners
+        // learn about the move from the ipc event
+        if previous != Some(workspace_id) {
+            self.events.send(Event::WindowMoved {
+                id: window.id().get(),
+                workspace: workspace_id.get(),
+                previous: previous.map(|id| id.get()),
+            });
+        }
+    }
+
+    pub fn title_changed(
+        &self,
+        surface: &WlSurface,
+        title: Option<String>,
+        app_id: Option<String>,
+    ) {
+        if let Some(window) = self.get_window(surface) {
+            self.events.send(Event::TitleChanged {
+                id: window.id().get(),
+                title,
+                app_id,
+            });
+        }
+    }
+
     pub fn get_foreign_handle(&self, surface: &WlSurface) -> Option<&ForeignToplevelHandle> {
         self.foreign_handle.get(surface)
     }
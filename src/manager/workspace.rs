@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     hash::Hash,
     sync::{
         Arc,
@@ -8,21 +9,26 @@ use std::{
 };
 
 use smithay::{
-    desktop::{Space, Window, WindowSurfaceType},
+    desktop::{Space, Window, WindowSurfaceType, layer_map_for_output},
     output::Output,
     reexports::{
-        calloop::LoopHandle, wayland_protocols::xdg::shell::server::xdg_toplevel::ResizeEdge,
+        calloop::LoopHandle,
+        wayland_protocols::xdg::shell::server::xdg_toplevel::{self, ResizeEdge},
         wayland_server::protocol::wl_surface::WlSurface,
     },
-    utils::{Logical, Point, Rectangle},
+    utils::{IsAlive, Logical, Point, Rectangle, Size},
 };
 
 use crate::{
     config::WorkspaceConfigs,
+    ipc::{EventSender, protocol::Event},
     layout::{
         Direction,
-        tiled_tree::{TiledScheme, TiledTree},
+        json_tiled_tree::{JsonTree, LayoutTemplate},
+        scrolling::Scrolling,
+        tiled_tree::{ContainerKind, MasterStack, Placement, Slot, TiledScheme, TiledTree},
     },
+    protocol::xdg_shell::detect_pointer_quadrant,
     state::GlobalData,
 };
 
@@ -34,38 +40,79 @@ static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
 pub struct WorkspaceId(usize);
 
 impl WorkspaceId {
-    // only for test
-    pub fn new(id: usize) -> Self {
-        Self(id)
-    }
     #[inline]
     pub fn next() -> Self {
         Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
     }
+
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowLayout {
+    Tiled,
+    Floating,
+    Fullscreen,
+}
+
+// where a fullscreen window goes back to
+#[derive(Debug)]
+enum Restore {
+    Tiled(Option<Slot>),
+    Floating(Rectangle<i32, Logical>),
 }
 
 #[derive(Debug)]
 pub struct Workspace {
     id: WorkspaceId,
+    name: String,
+    output: Output,
 
     tiled: Space<Window>,
-    // pub floating: Space<Window>,
-    // pub layout: HashMap<Window, WindowLayout>,
+    floating: Space<Window>,
+    layout: HashMap<Window, WindowLayout>,
     scheme: TiledScheme,
-    tiled_tree: Option<TiledTree>,
+    // master count can change at runtime, so each workspace keeps its own
+    master: MasterStack,
+    tiled_tree: Option<TiledTree<Window>>,
+    // takes the place of the tree in the scrolling scheme
+    scrolling: Option<Scrolling>,
+    template: Option<LayoutTemplate>,
     focus: Option<Window>,
+    // most recently focused first
+    focus_history: Vec<Window>,
     output_geometry: Rectangle<i32, Logical>,
 
+    // tiled window lifted out by a move grab, and where it would land
+    dragged: Option<Window>,
+    drop_preview: Option<Rectangle<i32, Logical>>,
+
+    // window covering the whole output, kept in the floating space
+    fullscreen: Option<(Window, Restore)>,
+    // tiled window filling the tiling area, it keeps its slot in the tree
+    maximized: Option<Window>,
+
     configs: Arc<WorkspaceConfigs>,
+    events: EventSender,
 }
 
 impl Workspace {
     pub fn new(
+        name: String,
         output: &Output,
         output_geometry: Rectangle<i32, Logical>,
-        scheme: TiledScheme,
         configs: Arc<WorkspaceConfigs>,
+        events: EventSender,
     ) -> Self {
+        let scheme = configs
+            .rule(&name)
+            .and_then(|rule| rule.scheme.clone())
+            .unwrap_or_else(|| configs.scheme.clone());
+        let master = configs.master.clone();
+        let scrolling = matches!(scheme, TiledScheme::Scrolling).then(Scrolling::default);
+
         let mut tiled: Space<Window> = Default::default();
         let mut floating: Space<Window> = Default::default();
         tiled.map_output(output, output_geometry.loc);
@@ -73,15 +120,28 @@ impl Workspace {
 
         Self {
             id: WorkspaceId::next(),
+            name,
+            output: output.clone(),
             tiled,
-            // floating,
-            // layout: HashMap::new(),
+            floating,
+            layout: HashMap::new(),
             scheme,
+            master,
             tiled_tree: None,
+            scrolling,
+            template: None,
             focus: None,
+            focus_history: Vec::new(),
             output_geometry,
 
+            dragged: None,
+            drop_preview: None,
+
+            fullscreen: None,
+            maximized: None,
+
             configs,
+            events,
         }
     }
 
@@ -89,20 +149,85 @@ impl Workspace {
         self.id
     }
 
-    pub fn current_space(&self) -> &Space<Window> {
-        &self.tiled
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty()
+    }
+
+    pub fn contains(&self, window: &Window) -> bool {
+        self.layout.contains_key(window)
+    }
+
+    // a workspace rule may override the global gap
+    fn gap(&self) -> i32 {
+        self.configs
+            .rule(&self.name)
+            .and_then(|rule| rule.gap)
+            .unwrap_or(self.configs.gap)
+    }
+
+    pub fn current_spaces(&self) -> [&Space<Window>; 2] {
+        [&self.tiled, &self.floating]
+    }
+
+    pub fn scheme(&self) -> &TiledScheme {
+        &self.scheme
     }
 
     pub fn focus(&self) -> Option<&Window> {
         self.focus.as_ref()
     }
 
+    pub fn fullscreen(&self) -> Option<&Window> {
+        self.fullscreen.as_ref().map(|(window, _)| window)
+    }
+
     pub fn elements(&self) -> impl DoubleEndedIterator<Item = &Window> {
+        self.tiled.elements().chain(self.floating.elements())
+    }
+
+    pub fn tiled_elements(&self) -> impl DoubleEndedIterator<Item = &Window> {
         self.tiled.elements()
     }
 
+    /// Floating windows from bottom to top.
+    pub fn floating_elements(&self) -> impl DoubleEndedIterator<Item = &Window> {
+        self.floating.elements()
+    }
+
     pub fn window_geometry(&self, window: &Window) -> Option<Rectangle<i32, Logical>> {
-        self.tiled.element_geometry(window)
+        self.tiled
+            .element_geometry(window)
+            .or_else(|| self.floating.element_geometry(window))
+    }
+
+    pub fn is_floating(&self, window: &Window) -> bool {
+        self.layout.get(window) == Some(&WindowLayout::Floating)
+    }
+
+    /// A tab behind the active one of its container, still mapped but not shown.
+    pub fn is_hidden(&self, window: &Window) -> bool {
+        self.tiled_tree
+            .as_ref()
+            .is_some_and(|tiled_tree| tiled_tree.is_hidden(window))
+    }
+
+    pub fn title_bars(&self) -> Vec<(Rectangle<i32, Logical>, bool)> {
+        self.tiled_tree
+            .as_ref()
+            .map(|tiled_tree| tiled_tree.title_bars())
+            .unwrap_or_default()
+    }
+
+    pub fn drop_preview(&self) -> Option<Rectangle<i32, Logical>> {
+        self.drop_preview
     }
 
     pub fn map_element(
@@ -114,27 +239,75 @@ impl Workspace {
     ) {
         self.refresh();
 
-        window
-            .toplevel()
-            .unwrap()
-            .with_pending_state(|state| state.bounds = Some(self.output_geometry.size));
-        window.toplevel().unwrap().send_pending_configure();
+        // the new window would open behind them
+        self.unset_fullscreen(loop_handle);
+        self.unset_maximized(loop_handle);
+
+        let toplevel = window.toplevel().unwrap();
+        toplevel.with_pending_state(|state| state.bounds = Some(self.usable_area().size));
+        toplevel.send_pending_configure();
+
+        // asked for before the initial commit, or kept from another workspace
+        let (fullscreen, maximized) = toplevel.with_pending_state(|state| {
+            (
+                state.states.contains(xdg_toplevel::State::Fullscreen),
+                state.states.contains(xdg_toplevel::State::Maximized),
+            )
+        });
+
+        if window.should_float() {
+            self.map_floating(window.clone(), activate);
+        } else {
+            self.map_tiled(window.clone(), edges, activate, loop_handle);
+        }
+
+        if fullscreen {
+            self.set_fullscreen(&window, true, loop_handle);
+        } else if maximized {
+            self.set_maximized(&window, true, loop_handle);
+        }
+    }
+
+    fn map_tiled(
+        &mut self,
+        window: Window,
+        edges: ResizeEdge,
+        activate: bool,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.layout.insert(window.clone(), WindowLayout::Tiled);
+
+        if let Some(scrolling) = &mut self.scrolling {
+            // a new column opens right of the focus
+            let target = self
+                .focus
+                .as_ref()
+                .filter(|focus| scrolling.contains(focus));
+            scrolling.insert(window.clone(), target, &Direction::Right);
+
+            if activate {
+                self.replace_focus(Some(window));
+            }
+            self.arrange_scrolling(self.focus.clone(), loop_handle);
+            return;
+        }
+
+        // the app_id is known since the initial commit
+        let claimed = self
+            .template
+            .as_mut()
+            .is_some_and(|template| template.claim(&window));
 
         if self.tiled_tree.is_none() {
-            let rec = Rectangle {
-                loc: (self.configs.gap, self.configs.gap).into(),
-                size: (self.output_geometry.size
-                    - (self.configs.gap * 2, self.configs.gap * 2).into())
-                .into(),
-            };
+            let rec = self.tiling_rec();
 
             window.set_rec(rec.size);
             self.tiled.map_element(window.clone(), rec.loc, activate);
-            self.tiled_tree = Some(TiledTree::new(window.clone(), self.configs.gap));
+            self.tiled_tree = Some(TiledTree::new(window.clone(), rec, self.gap()));
 
             // set focus
             if activate {
-                self.focus = Some(window.clone());
+                self.replace_focus(Some(window.clone()));
             }
 
             loop_handle.insert_idle(move |data| {
@@ -144,7 +317,6 @@ impl Workspace {
                     window,
                     from,
                     rec,
-                    Duration::from_millis(30),
                     crate::animation::AnimationType::EaseInOutQuad,
                 );
             });
@@ -152,58 +324,153 @@ impl Workspace {
             return;
         }
 
+        if claimed && let Some(placements) = self.insert_templated(&window) {
+            place_tiled(&mut self.tiled, placements, loop_handle);
+
+            if activate {
+                self.replace_focus(Some(window));
+            }
+            return;
+        }
+
         match self.scheme {
             TiledScheme::Default => {
                 if let Some(layout_tree) = &mut self.tiled_tree {
-                    // TODO
-                    let focus_rec = self
-                        .tiled
-                        .element_geometry(self.focus.as_ref().unwrap())
+                    // a floating focus can't be split, fall back to the first tiled window
+                    let target = self
+                        .focus
+                        .clone()
+                        .filter(|focus| self.tiled.element_geometry(focus).is_some())
+                        .or_else(|| layout_tree.get_first_window().cloned())
                         .unwrap();
-                    let direction = if focus_rec.size.w > focus_rec.size.h {
-                        match edges {
-                            ResizeEdge::TopLeft | ResizeEdge::BottomLeft => Direction::Left,
-                            ResizeEdge::TopRight | ResizeEdge::BottomRight => Direction::Right,
-                            _ => Direction::default(),
-                        }
-                    } else {
-                        match edges {
-                            ResizeEdge::TopLeft | ResizeEdge::TopRight => Direction::Up,
-                            ResizeEdge::BottomLeft | ResizeEdge::BottomRight => Direction::Down,
-                            _ => Direction::default(),
-                        }
-                    };
-                    layout_tree.insert_window(
-                        self.focus.as_ref(),
-                        window.clone(),
-                        direction,
-                        &mut self.tiled,
-                        loop_handle,
-                    );
+                    let focus_rec = self.tiled.element_geometry(&target).unwrap();
+                    let direction = split_direction(focus_rec, edges);
+                    let placements =
+                        layout_tree.insert_window(Some(&target), window.clone(), direction);
 
                     #[cfg(feature = "trace_layout")]
                     layout_tree.print_tree();
+
+                    place_tiled(&mut self.tiled, placements, loop_handle);
                 }
             }
             TiledScheme::Spiral => {
                 if let Some(layout_tree) = &mut self.tiled_tree {
-                    layout_tree.insert_window_spiral(window.clone(), &mut self.tiled, loop_handle);
+                    let placements = layout_tree.insert_window_spiral(window.clone());
 
                     #[cfg(feature = "trace_layout")]
                     layout_tree.print_tree();
+
+                    place_tiled(&mut self.tiled, placements, loop_handle);
                 }
             }
+            TiledScheme::MasterStack => {
+                // new windows become the master
+                let mut windows = self.master_order();
+                windows.insert(0, window.clone());
+                self.arrange_master_stack(&windows, loop_handle);
+            }
+            // laid out on the strip above
+            TiledScheme::Scrolling => {}
         }
 
         // set focus
         if activate {
-            self.focus = Some(window);
+            self.replace_focus(Some(window));
         }
     }
 
+    fn map_floating(&mut self, window: Window, activate: bool) -> Rectangle<i32, Logical> {
+        self.layout.insert(window.clone(), WindowLayout::Floating);
+
+        let rec = self.floating_rec(&window);
+        window.set_rec(rec.size);
+        self.floating.map_element(window.clone(), rec.loc, activate);
+
+        if activate {
+            self.replace_focus(Some(window));
+        }
+
+        rec
+    }
+
     pub fn unmap_element(&mut self, window: &Window, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if let Some(template) = &mut self.template {
+            template.release(window);
+        }
+        self.focus_history.retain(|w| w != window);
+
+        if self.fullscreen() == Some(window) {
+            self.fullscreen = None;
+        }
+
+        // closed mid-drag, there is nothing left to drop
+        if self.dragged.as_ref() == Some(window) {
+            self.dragged = None;
+            self.drop_preview = None;
+        }
+
+        let next = if self.layout.get(window) != Some(&WindowLayout::Tiled) {
+            self.floating.unmap_elem(window);
+            self.layout.remove(window);
+            None
+        } else {
+            self.unmap_tiled(window, loop_handle)
+        };
+
+        if self.focus.as_ref() == Some(window) {
+            let next = next
+                .or_else(|| self.floating.elements().next_back().cloned())
+                .or_else(|| {
+                    self.tiled_tree
+                        .as_ref()
+                        .and_then(|tree| tree.get_first_window().cloned())
+                });
+            self.replace_focus(next);
+        }
+    }
+
+    // returns the window the tree would hand the focus to
+    fn unmap_tiled(
+        &mut self,
+        window: &Window,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> Option<Window> {
+        let mut next = Some(window.clone());
+
+        // a maximized window gives up the state with its slot
+        if self.maximized.as_ref() == Some(window) {
+            self.maximized = None;
+            window.toplevel().unwrap().with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Maximized);
+            });
+        }
+
+        if let Some(scrolling) = &mut self.scrolling {
+            let next = scrolling.remove(window);
+            self.tiled.unmap_elem(window);
+            self.layout.remove(window);
+
+            // the viewport follows the focus to where it goes next
+            let focus = if self.focus.as_ref() == Some(window) {
+                next.clone()
+            } else {
+                self.focus.clone()
+            };
+            self.arrange_scrolling(focus, loop_handle);
+            return next;
+        }
+
+        // the order is lost once the tree is out of shape
+        let master_order = matches!(self.scheme, TiledScheme::MasterStack).then(|| {
+            let mut windows = self.master_order();
+            windows.retain(|w| w != window);
+            windows
+        });
+
         if let Some(tiled_tree) = &mut self.tiled_tree {
-            tiled_tree.remove(window, &mut self.focus, &mut self.tiled, loop_handle);
+            let placements = tiled_tree.remove(window, &mut next);
+            place_tiled(&mut self.tiled, placements, loop_handle);
 
             if tiled_tree.is_empty() {
                 self.tiled_tree = None;
@@ -213,91 +480,968 @@ impl Workspace {
             }
         } else {
             error!("empty layout tree!");
-            return;
+        }
+
+        if let Some(windows) = master_order.filter(|_| self.tiled_tree.is_some()) {
+            self.arrange_master_stack(&windows, loop_handle);
         }
 
         self.tiled.unmap_elem(window);
+        self.layout.remove(window);
+        self.fit_maximized(None, loop_handle);
+
+        next.filter(|next| next != window)
+    }
+
+    pub fn toggle_floating(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        let Some(window) = self.focus.clone() else {
+            return;
+        };
+
+        if self.fullscreen() == Some(&window) {
+            return;
+        }
+
+        if self.is_floating(&window) {
+            self.floating.unmap_elem(&window);
+            self.layout.remove(&window);
+            self.map_tiled(window, ResizeEdge::None, true, loop_handle);
+        } else {
+            let from = self.tiled.element_geometry(&window);
+            self.unmap_tiled(&window, loop_handle);
+            let to = self.map_floating(window.clone(), true);
+
+            if let Some(from) = from {
+                loop_handle.insert_idle(move |data| {
+                    data.render_manager.add_animation(
+                        window,
+                        from,
+                        to,
+                        crate::animation::AnimationType::EaseInOutQuad,
+                    );
+                });
+            }
+        }
+    }
+
+    /// Let `window` cover the whole output, out of the layout, or put it back
+    /// where it was.
+    pub fn set_fullscreen(
+        &mut self,
+        window: &Window,
+        fullscreen: bool,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        if !fullscreen {
+            if self.fullscreen() == Some(window) {
+                self.unset_fullscreen(loop_handle);
+            }
+            return;
+        }
+
+        if !self.contains(window) || self.fullscreen() == Some(window) {
+            return;
+        }
+        self.unset_fullscreen(loop_handle);
+
+        let from = self.window_geometry(window);
+        let restore = if self.is_floating(window) {
+            self.floating.unmap_elem(window);
+            Restore::Floating(from.unwrap_or_else(|| self.floating_rec(window)))
+        } else {
+            let slot = self
+                .tiled_tree
+                .as_ref()
+                .and_then(|tiled_tree| tiled_tree.slot(window));
+            self.unmap_tiled(window, loop_handle);
+            Restore::Tiled(slot)
+        };
+
+        let rec = self.output_geometry;
+        self.layout.insert(window.clone(), WindowLayout::Fullscreen);
+        window.toplevel().unwrap().with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Fullscreen);
+        });
+        window.set_rec(rec.size);
+        self.floating.map_element(window.clone(), rec.loc, true);
+
+        self.fullscreen = Some((window.clone(), restore));
+        self.replace_focus(Some(window.clone()));
+
+        let window = window.clone();
+        loop_handle.insert_idle(move |data| {
+            data.render_manager.add_animation(
+                window,
+                from.unwrap_or(rec),
+                rec,
+                crate::animation::AnimationType::EaseInOutQuad,
+            );
+        });
+    }
+
+    pub fn toggle_fullscreen(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if let Some(window) = self.focus.clone() {
+            let fullscreen = self.fullscreen() != Some(&window);
+            self.set_fullscreen(&window, fullscreen, loop_handle);
+        }
+    }
+
+    // back into the tree slot or floating place the window left
+    fn unset_fullscreen(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        let Some((window, restore)) = self.fullscreen.take() else {
+            return;
+        };
+
+        self.floating.unmap_elem(&window);
+        self.layout.remove(&window);
+        window.toplevel().unwrap().with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Fullscreen);
+        });
+
+        match restore {
+            Restore::Floating(rec) => {
+                self.layout.insert(window.clone(), WindowLayout::Floating);
+                window.set_rec(rec.size);
+                self.floating.map_element(window.clone(), rec.loc, true);
+            }
+            Restore::Tiled(slot) => {
+                let placements = match (slot, self.tiled_tree.as_mut()) {
+                    (Some(slot), Some(tiled_tree)) => tiled_tree.restore(slot, window.clone()),
+                    _ => vec![],
+                };
+
+                if !placements.is_empty() {
+                    self.layout.insert(window.clone(), WindowLayout::Tiled);
+                    place_tiled(&mut self.tiled, placements, loop_handle);
+                } else {
+                    self.map_tiled(window.clone(), ResizeEdge::None, true, loop_handle);
+                }
+                self.fit_maximized(None, loop_handle);
+            }
+        }
+
+        self.replace_focus(Some(window.clone()));
+
+        let from = self.output_geometry;
+        if let Some(to) = self.window_geometry(&window) {
+            loop_handle.insert_idle(move |data| {
+                data.render_manager.add_animation(
+                    window,
+                    from,
+                    to,
+                    crate::animation::AnimationType::EaseInOutQuad,
+                );
+            });
+        }
+    }
+
+    /// Let a tiled window fill the tiling area, it keeps its slot to return to.
+    pub fn set_maximized(
+        &mut self,
+        window: &Window,
+        maximized: bool,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        if !maximized {
+            if self.maximized.as_ref() == Some(window) {
+                self.unset_maximized(loop_handle);
+            }
+            return;
+        }
+
+        // floating and fullscreen windows have no slot
+        if self.layout.get(window) != Some(&WindowLayout::Tiled) {
+            window.toplevel().unwrap().with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Maximized);
+            });
+            return;
+        }
+
+        if self.maximized.as_ref() == Some(window) {
+            return;
+        }
+        self.unset_maximized(loop_handle);
+
+        window.toplevel().unwrap().with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Maximized);
+        });
+        self.maximized = Some(window.clone());
+
+        let from = self.tiled.element_geometry(window);
+        self.fit_maximized(from, loop_handle);
+        self.replace_focus(Some(window.clone()));
+    }
+
+    pub fn toggle_maximized(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if let Some(window) = self.focus.clone() {
+            let maximized = self.maximized.as_ref() != Some(&window);
+            self.set_maximized(&window, maximized, loop_handle);
+        }
+    }
+
+    fn unset_maximized(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        let Some(window) = self.maximized.take() else {
+            return;
+        };
+
+        window.toplevel().unwrap().with_pending_state(|state| {
+            state.states.unset(xdg_toplevel::State::Maximized);
+        });
+
+        // lay the whole tree out again to move the window back into its slot
+        let tiling_rec = self.tiling_rec();
+        if let Some(layout_tree) = &mut self.tiled_tree {
+            place_tiled(
+                &mut self.tiled,
+                layout_tree.arrange(tiling_rec),
+                loop_handle,
+            );
+        }
+        self.arrange_scrolling(self.focus.clone(), loop_handle);
+    }
+
+    // the tree lays the maximized window out in its slot, stretch it over
+    // the tiling area again and on top of the other tiled windows
+    fn fit_maximized(
+        &mut self,
+        from: Option<Rectangle<i32, Logical>>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        let Some(window) = self.maximized.clone() else {
+            return;
+        };
+
+        let rec = self.tiling_rec();
+        window.set_rec(rec.size);
+        self.tiled.map_element(window.clone(), rec.loc, false);
+
+        // also replaces the animation the tree queued towards the slot
+        loop_handle.insert_idle(move |data| {
+            data.render_manager.add_animation(
+                window,
+                from.unwrap_or(rec),
+                rec,
+                crate::animation::AnimationType::EaseInOutQuad,
+            );
+        });
+    }
+
+    /// Take a tiled window out of the tree, it follows the pointer above the
+    /// other windows until `drop_window`.
+    pub fn lift_window(
+        &mut self,
+        window: &Window,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> bool {
+        let Some(rec) = self.tiled.element_geometry(window) else {
+            return false;
+        };
+
+        self.unmap_tiled(window, loop_handle);
+
+        self.layout.insert(window.clone(), WindowLayout::Floating);
+        self.floating.map_element(window.clone(), rec.loc, true);
+        self.replace_focus(Some(window.clone()));
+        self.dragged = Some(window.clone());
+
+        true
+    }
+
+    pub fn update_drop(&mut self, position: Point<f64, Logical>) {
+        if self.dragged.is_some() {
+            self.drop_preview = self.drop_zone(position).map(|(_, _, rec)| rec);
+        }
+    }
+
+    /// Put the lifted window back into the tree, next to the window under
+    /// `position` on the side of the pointer.
+    pub fn drop_window(
+        &mut self,
+        position: Point<f64, Logical>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.drop_preview = None;
+        let Some(window) = self.dragged.take().filter(|window| window.alive()) else {
+            return;
+        };
+
+        let zone = self.drop_zone(position);
+        self.floating.unmap_elem(&window);
+        self.layout.remove(&window);
+
+        // master-stack keeps its shape, the window lands next to the target in order
+        if let (TiledScheme::MasterStack, Some((target, direction, _))) = (&self.scheme, &zone) {
+            let mut windows = self.master_order();
+            let index = windows.iter().position(|w| w == target).unwrap_or(0);
+            let index = match direction {
+                Direction::Left | Direction::Up => index,
+                Direction::Right | Direction::Down => index + 1,
+            };
+            windows.insert(index, window.clone());
+
+            self.layout.insert(window.clone(), WindowLayout::Tiled);
+            self.arrange_master_stack(&windows, loop_handle);
+            self.replace_focus(Some(window));
+            return;
+        }
+
+        if let (Some(scrolling), Some((target, direction, _))) = (&mut self.scrolling, &zone) {
+            scrolling.insert(window.clone(), Some(target), direction);
+
+            self.layout.insert(window.clone(), WindowLayout::Tiled);
+            self.replace_focus(Some(window.clone()));
+            self.arrange_scrolling(Some(window), loop_handle);
+            return;
+        }
+
+        let placements = match (zone, self.tiled_tree.as_mut()) {
+            (Some((target, direction, _)), Some(tiled_tree)) => {
+                let placements = tiled_tree.insert_window(Some(&target), window.clone(), direction);
+
+                #[cfg(feature = "trace_layout")]
+                tiled_tree.print_tree();
+
+                placements
+            }
+            _ => vec![],
+        };
+
+        if placements.is_empty() {
+            self.map_tiled(window.clone(), ResizeEdge::None, true, loop_handle);
+        } else {
+            self.layout.insert(window.clone(), WindowLayout::Tiled);
+            place_tiled(&mut self.tiled, placements, loop_handle);
+        }
+
+        self.fit_maximized(None, loop_handle);
+        self.replace_focus(Some(window));
+    }
+
+    // the tiled window under the pointer, the side to insert at and the preview of that half
+    fn drop_zone(
+        &self,
+        position: Point<f64, Logical>,
+    ) -> Option<(Window, Direction, Rectangle<i32, Logical>)> {
+        let (target, _) = self.tiled.element_under(position)?;
+        let rec = self.tiled.element_geometry(target)?;
+
+        let direction = split_direction(rec, detect_pointer_quadrant(position, rec.to_f64()));
+
+        let mut half = rec;
+        match direction {
+            Direction::Left | Direction::Right => half.size.w /= 2,
+            Direction::Up | Direction::Down => half.size.h /= 2,
+        }
+        match direction {
+            Direction::Right => half.loc.x += rec.size.w - half.size.w,
+            Direction::Down => half.loc.y += rec.size.h - half.size.h,
+            _ => {}
+        }
+
+        Some((target.clone(), direction, half))
+    }
+
+    pub fn move_floating(&mut self, window: &Window, location: Point<i32, Logical>) {
+        if self.is_floating(window) {
+            self.floating.map_element(window.clone(), location, true);
+        }
+    }
+
+    pub fn resize_floating(&mut self, window: &Window, rec: Rectangle<i32, Logical>) {
+        if self.is_floating(window) {
+            window.set_rec(rec.size);
+            self.floating.map_element(window.clone(), rec.loc, true);
+        }
+    }
+
+    /// Focus the window after the current one, tiled windows first then floating.
+    pub fn focus_next(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) -> Option<Window> {
+        // the others are hidden behind a fullscreen window
+        if self.fullscreen.is_some() {
+            return None;
+        }
+
+        let windows: Vec<Window> = self.elements().cloned().collect();
+        let next = match &self.focus {
+            Some(focus) => windows
+                .iter()
+                .position(|w| w == focus)
+                .map(|i| windows[(i + 1) % windows.len()].clone()),
+            None => None,
+        }
+        .or_else(|| windows.first().cloned())?;
+
+        self.set_focus(Some(next.clone()));
+        self.arrange_scrolling(Some(next.clone()), loop_handle);
+        Some(next)
+    }
+
+    /// Focus the tiled neighbor in `direction`, the most recently focused one
+    /// when several share that side.
+    pub fn focus_direction(
+        &mut self,
+        direction: &Direction,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> Option<Window> {
+        let focus = self.focus.as_ref()?;
+        let neighbors = match &self.scrolling {
+            Some(scrolling) => scrolling.neighbors(focus, direction)?,
+            None => self
+                .tiled_tree
+                .as_ref()?
+                .neighbors(focus, direction)?
+                .clone(),
+        };
+
+        let next = self
+            .focus_history
+            .iter()
+            .find(|w| neighbors.contains(w))
+            .or_else(|| neighbors.first())?
+            .clone();
+
+        self.set_focus(Some(next.clone()));
+        self.arrange_scrolling(Some(next.clone()), loop_handle);
+        Some(next)
     }
 
     pub fn invert_window(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
         if let Some(layout_tree) = &mut self.tiled_tree {
             if let Some(focus) = &self.focus {
-                layout_tree.invert_window(focus, &mut self.tiled, loop_handle);
+                let placements = layout_tree.invert_window(focus);
 
                 #[cfg(feature = "trace_layout")]
                 layout_tree.print_tree();
+
+                place_tiled(&mut self.tiled, placements, loop_handle);
+            }
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    pub fn exchange_window(
+        &mut self,
+        direction: &Direction,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        // columns move along the strip, windows within their column
+        if let (Some(scrolling), Some(focus)) = (&mut self.scrolling, &self.focus) {
+            if scrolling.move_window(focus, direction) {
+                self.arrange_scrolling(self.focus.clone(), loop_handle);
             }
+            return;
+        }
+
+        if let Some(layout_tree) = &mut self.tiled_tree {
+            if let Some(focus) = &self.focus {
+                place_tiled(
+                    &mut self.tiled,
+                    layout_tree.exchange(focus, direction),
+                    loop_handle,
+                );
+            }
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    /// Turn the split around the focus into a container of `kind`, or back.
+    pub fn toggle_container(
+        &mut self,
+        kind: ContainerKind,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        if let (Some(layout_tree), Some(focus)) = (&mut self.tiled_tree, &self.focus) {
+            let placements = layout_tree.toggle_container(focus, kind);
+
+            #[cfg(feature = "trace_layout")]
+            layout_tree.print_tree();
+
+            place_tiled(&mut self.tiled, placements, loop_handle);
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    /// Show the tab `delta` steps after the focus in its container.
+    pub fn cycle_tab(
+        &mut self,
+        delta: isize,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> Option<Window> {
+        let focus = self.focus.clone()?;
+        let next = self.tiled_tree.as_mut()?.cycle_tab(&focus, delta)?;
+
+        // a maximized tab would keep covering the one shown now
+        if self.maximized.as_ref() == Some(&focus) {
+            self.unset_maximized(loop_handle);
+        }
+
+        self.set_focus(Some(next.clone()));
+        Some(next)
+    }
+
+    pub fn tiled_expansion(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if let Some(layout_tree) = &self.tiled_tree {
+            place_tiled(&mut self.tiled, layout_tree.expansion(), loop_handle);
+        }
+    }
+
+    pub fn tiled_recover(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if let Some(layout_tree) = &mut self.tiled_tree {
+            place_tiled(&mut self.tiled, layout_tree.recover(), loop_handle);
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    pub fn resize(
+        &mut self,
+        window: &Window,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        if let Some(layout_tree) = &mut self.tiled_tree {
+            let placements = layout_tree.resize(window, edges, delta);
+            place_tiled(&mut self.tiled, placements, loop_handle);
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    /// Move the border on the `direction` side of the focus outwards by
+    /// `amount` of the split, a negative amount moves it inwards.
+    pub fn resize_focus(
+        &mut self,
+        direction: &Direction,
+        amount: f64,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> bool {
+        let (Some(layout_tree), Some(focus)) = (&mut self.tiled_tree, &self.focus) else {
+            return false;
+        };
+
+        let placements = layout_tree.resize_ratio(focus, direction, amount);
+        let resized = !placements.is_empty();
+        place_tiled(&mut self.tiled, placements, loop_handle);
+        self.fit_maximized(None, loop_handle);
+        resized
+    }
+
+    pub fn equalize(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        // halves would undo the master ratio
+        if matches!(self.scheme, TiledScheme::MasterStack) {
+            self.arrange_master_stack(&self.master_order(), loop_handle);
+            return;
+        }
+
+        if let Some(layout_tree) = &mut self.tiled_tree {
+            place_tiled(&mut self.tiled, layout_tree.equalize(), loop_handle);
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    /// Make the focused window the master, or swap the master with the next
+    /// window if it already is.
+    pub fn promote(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if !matches!(self.scheme, TiledScheme::MasterStack) {
+            return;
+        }
+        let Some(focus) = self.focus.clone() else {
+            return;
+        };
+
+        let mut windows = self.master_order();
+        let Some(index) = windows.iter().position(|w| w == &focus) else {
+            return;
+        };
+
+        match index {
+            0 if windows.len() > 1 => windows.swap(0, 1),
+            0 => return,
+            _ => {
+                let window = windows.remove(index);
+                windows.insert(0, window);
+            }
+        }
+
+        self.arrange_master_stack(&windows, loop_handle);
+    }
+
+    /// Add `delta` windows to the master area.
+    pub fn change_master_count(&mut self, delta: isize, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if !matches!(self.scheme, TiledScheme::MasterStack) {
+            return;
+        }
+
+        // the count is clamped to the windows only when laid out, and stays at
+        // 1 or more unless it was set to 0 in the config
+        let count = self.master.count;
+        self.master.count = count.saturating_add_signed(delta).max(count.min(1));
+
+        let windows = self.master_order();
+        self.arrange_master_stack(&windows, loop_handle);
+    }
+
+    fn master_order(&self) -> Vec<Window> {
+        self.tiled_tree
+            .as_ref()
+            .map(|tiled_tree| tiled_tree.master_order(&self.master))
+            .unwrap_or_default()
+    }
+
+    // lay the tiled windows out as master-stack, masters first in `windows`
+    fn arrange_master_stack(
+        &mut self,
+        windows: &[Window],
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        let tiling_rec = self.tiling_rec();
+
+        // the stored count may exceed the windows there are for now
+        let master = MasterStack {
+            count: self.master.count.min(windows.len()),
+            ..self.master.clone()
+        };
+
+        if let Some(tiled_tree) = &mut self.tiled_tree {
+            let placements = tiled_tree.master_stack(windows, &master, tiling_rec);
+
+            #[cfg(feature = "trace_layout")]
+            tiled_tree.print_tree();
+
+            place_tiled(&mut self.tiled, placements, loop_handle);
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    /// Step the width of the focused column through the presets.
+    pub fn cycle_column_width(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if let (Some(scrolling), Some(focus)) = (&mut self.scrolling, &self.focus) {
+            if scrolling.cycle_width(focus) {
+                self.arrange_scrolling(self.focus.clone(), loop_handle);
+            }
+        }
+    }
+
+    /// Stack the focus into the column on the `direction` side, or give it a
+    /// column of its own there.
+    pub fn consume_or_expel(
+        &mut self,
+        direction: &Direction,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        if let (Some(scrolling), Some(focus)) = (&mut self.scrolling, &self.focus) {
+            if scrolling.consume_or_expel(focus, direction) {
+                self.arrange_scrolling(self.focus.clone(), loop_handle);
+            }
+        }
+    }
+
+    // lay the strip out again, the windows slide to their places so the
+    // viewport scrolls over to show `focus`
+    fn arrange_scrolling(
+        &mut self,
+        focus: Option<Window>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        let tiling_rec = self.tiling_rec();
+        let gap = self.gap();
+        let Some(scrolling) = &mut self.scrolling else {
+            return;
+        };
+
+        for (window, rec) in scrolling.arrange(tiling_rec, gap, focus.as_ref()) {
+            // new windows rise from below like the first window of a tree
+            let from = self.tiled.element_geometry(&window).unwrap_or_else(|| {
+                let mut from = rec;
+                from.loc.y += from.size.h;
+                from
+            });
+
+            // windows out of the viewport still get their size
+            window.set_rec(rec.size);
+            self.tiled.map_element(window.clone(), rec.loc, false);
+
+            loop_handle.insert_idle(move |data| {
+                data.render_manager.add_animation(
+                    window,
+                    from,
+                    rec,
+                    crate::animation::AnimationType::EaseInOutQuad,
+                );
+            });
+        }
+        self.fit_maximized(None, loop_handle);
+    }
+
+    pub fn modify_windows(
+        &mut self,
+        rec: Rectangle<i32, Logical>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        // the output moved, floating windows keep their place on it
+        let offset = rec.loc - self.output_geometry.loc;
+        if offset != Point::from((0, 0)) {
+            self.tiled.map_output(&self.output, rec.loc);
+            self.floating.map_output(&self.output, rec.loc);
+
+            let floating: Vec<(Window, Point<i32, Logical>)> = self
+                .floating
+                .elements()
+                .filter_map(|w| Some((w.clone(), self.floating.element_location(w)?)))
+                .collect();
+            for (window, loc) in floating {
+                self.floating.map_element(window, loc + offset, false);
+            }
+
+            if let Some((_, Restore::Floating(restore))) = &mut self.fullscreen {
+                restore.loc += offset;
+            }
+        }
+
+        self.output_geometry = rec;
+        if let Some((window, _)) = &self.fullscreen {
+            window.set_rec(rec.size);
+            self.floating.map_element(window.clone(), rec.loc, false);
+        }
+
+        let tiling_rec = self.tiling_rec();
+        if let Some(layout_tree) = &mut self.tiled_tree {
+            place_tiled(
+                &mut self.tiled,
+                layout_tree.arrange(tiling_rec),
+                loop_handle,
+            );
+        }
+        self.arrange_scrolling(self.focus.clone(), loop_handle);
+        self.fit_maximized(None, loop_handle);
+    }
+
+    /// Move the workspace and its windows onto another output.
+    pub fn set_output(
+        &mut self,
+        output: &Output,
+        output_geometry: Rectangle<i32, Logical>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.tiled.unmap_output(&self.output);
+        self.floating.unmap_output(&self.output);
+        self.output = output.clone();
+        self.tiled.map_output(output, self.output_geometry.loc);
+        self.floating.map_output(output, self.output_geometry.loc);
+
+        self.modify_windows(output_geometry, loop_handle);
+    }
+
+    pub fn set_configs(
+        &mut self,
+        configs: Arc<WorkspaceConfigs>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.configs = configs;
+
+        if let Some(tiled_tree) = &mut self.tiled_tree {
+            tiled_tree.set_gap(self.gap());
+        }
+        self.modify_windows(self.output_geometry, loop_handle);
+    }
+
+    /// Serialize the live tiled tree.
+    pub fn layout(&self) -> Option<JsonTree> {
+        let tiled_tree = self.tiled_tree.as_ref()?;
+        match JsonTree::try_from(tiled_tree) {
+            Ok(json_tree) => Some(json_tree),
+            Err(err) => {
+                warn!("Failed to serialize tiled tree: {:?}", err);
+                None
+            }
+        }
+    }
+
+    /// Use a json layout as template, windows with matching app_id fill its leaves.
+    pub fn load_template(&mut self, json_tree: JsonTree, loop_handle: &LoopHandle<'_, GlobalData>) {
+        if self.scrolling.is_some() {
+            warn!("Layout templates don't apply to the scrolling scheme");
+            return;
+        }
+
+        let mut template = LayoutTemplate::new(json_tree);
+        if let Some(tiled_tree) = &self.tiled_tree {
+            for window in tiled_tree.windows() {
+                template.claim(&window);
+            }
+        }
+
+        self.template = Some(template);
+        self.apply_template(loop_handle);
+    }
+
+    // a window the template waits for opens next to the nearest window of
+    // the template that is already tiled, on the side its own leaf lies
+    fn insert_templated(&mut self, window: &Window) -> Option<Vec<Placement<Window>>> {
+        // master-stack puts every new window in front anyway
+        if !matches!(self.scheme, TiledScheme::Default | TiledScheme::Spiral) {
+            return None;
+        }
+
+        let tiled_tree = self.tiled_tree.as_ref()?;
+        let (target, direction) = self
+            .template
+            .as_ref()?
+            .anchor(window, |w| tiled_tree.find_node(w).is_some())?;
+
+        let tiled_tree = self.tiled_tree.as_mut()?;
+        Some(tiled_tree.insert_window(Some(&target), window.clone(), direction))
+    }
+
+    fn apply_template(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        let tiling_rec = self.tiling_rec();
+
+        let Some(template) = &self.template else {
+            return;
+        };
+
+        let Some(mut tiled_tree) = template.build_tree(self.gap()) else {
+            return;
+        };
+
+        // windows outside the template keep the common insert way
+        let extras: Vec<Window> = self
+            .tiled_tree
+            .as_ref()
+            .map(|tree| tree.windows())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|window| !template.contains(window))
+            .collect();
+
+        let mut placements = tiled_tree.arrange(tiling_rec);
+        for window in extras {
+            placements.extend(tiled_tree.insert_window(None, window, Direction::default()));
         }
+
+        #[cfg(feature = "trace_layout")]
+        tiled_tree.print_tree();
+
+        place_tiled(&mut self.tiled, placements, loop_handle);
+
+        self.tiled_tree = Some(tiled_tree);
+        self.fit_maximized(None, loop_handle);
     }
 
-    pub fn exchange_window(
-        &mut self,
-        direction: &Direction,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) {
-        if let Some(layout_tree) = &mut self.tiled_tree {
-            if let Some(focus) = &self.focus {
-                layout_tree.exchange(focus, direction, &mut self.tiled, loop_handle);
-            }
-        }
+    // centered on the output, half its size unless the client asks otherwise
+    fn floating_rec(&self, window: &Window) -> Rectangle<i32, Logical> {
+        let (min_size, max_size) = window.size_limits();
+        let output_size = self.output_geometry.size;
+
+        let clamp = |size: i32, min: i32, max: i32| {
+            let size = size.max(min);
+            if max > 0 { size.min(max) } else { size }
+        };
+        let size: Size<i32, Logical> = (
+            clamp(output_size.w / 2, min_size.w, max_size.w),
+            clamp(output_size.h / 2, min_size.h, max_size.h),
+        )
+            .into();
+
+        let loc = self.output_geometry.loc
+            + Point::from(((output_size.w - size.w) / 2, (output_size.h - size.h) / 2));
+
+        Rectangle::new(loc, size)
     }
 
-    pub fn tiled_expansion(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
-        if let Some(layout_tree) = &self.tiled_tree {
-            layout_tree.expansion(&mut self.tiled, loop_handle);
-        }
+    // the output without the exclusive zones of layer surfaces, like a bar
+    fn usable_area(&self) -> Rectangle<i32, Logical> {
+        let mut zone = layer_map_for_output(&self.output).non_exclusive_zone();
+        zone.loc += self.output_geometry.loc;
+        zone
     }
 
-    pub fn tiled_recover(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
-        if let Some(layout_tree) = &mut self.tiled_tree {
-            layout_tree.recover(&mut self.tiled, loop_handle);
-        }
+    fn tiling_rec(&self) -> Rectangle<i32, Logical> {
+        let gap = self.gap();
+        let usable_area = self.usable_area();
+        Rectangle::new(
+            usable_area.loc + Point::from((gap, gap)),
+            (usable_area.size - (gap * 2, gap * 2).into()).into(),
+        )
     }
 
-    // pub fn _resize(&mut self, offset: Point<i32, Logical>, edges: &ResizeEdge, rec: &mut Rectangle<i32, Logical>) {
-    // }
+    /// Lay the tree out again if the layer surfaces changed the usable area.
+    pub fn arrange(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        let tiling_rec = self.tiling_rec();
+        if let Some(scrolling) = &self.scrolling {
+            if scrolling.area() != Some(tiling_rec) {
+                self.arrange_scrolling(self.focus.clone(), loop_handle);
+            }
+            return;
+        }
 
-    pub fn modify_windows(
-        &mut self,
-        rec: Rectangle<i32, Logical>,
-        loop_handle: &LoopHandle<'_, GlobalData>,
-    ) {
-        self.output_geometry = rec;
-        if let Some(layout_tree) = &mut self.tiled_tree {
-            let root_id = layout_tree.get_root().unwrap();
-            layout_tree.modify(
-                root_id,
-                Rectangle::new(
-                    (self.configs.gap, self.configs.gap).into(),
-                    (rec.size - (self.configs.gap * 2, self.configs.gap * 2).into()).into(),
-                ),
-                &mut self.tiled,
-                loop_handle,
-            );
+        let Some(layout_tree) = &mut self.tiled_tree else {
+            return;
+        };
+
+        // layer surfaces commit often, mostly without touching their zone
+        if layout_tree.get_root_rec() == Some(tiling_rec) {
+            return;
         }
+
+        place_tiled(
+            &mut self.tiled,
+            layout_tree.arrange(tiling_rec),
+            loop_handle,
+        );
+        self.fit_maximized(None, loop_handle);
     }
 
     pub fn set_focus(&mut self, window: Option<Window>) {
         match window {
             Some(window) => {
+                // a hidden tab comes to the front of its container
+                if let Some(tiled_tree) = &mut self.tiled_tree {
+                    tiled_tree.activate(&window);
+                }
                 self.raise_element(&window, true);
-                self.focus = Some(window);
+                self.replace_focus(Some(window));
             }
             None => {
                 if let Some(focus) = &self.focus {
                     focus.set_activated(false);
                     focus.toplevel().unwrap().send_pending_configure();
                 }
-                self.focus = None;
+                self.replace_focus(None);
+            }
+        }
+    }
+
+    fn replace_focus(&mut self, window: Option<Window>) {
+        if self.focus != window {
+            if let Some(window) = &window {
+                self.focus_history.retain(|w| w != window);
+                self.focus_history.insert(0, window.clone());
             }
+            self.focus = window;
+            self.focus_changed();
         }
     }
 
+    fn focus_changed(&self) {
+        self.events.send(Event::FocusChanged {
+            workspace: self.id.get(),
+            window: self.focus.as_ref().map(|w| w.id().get()),
+        });
+    }
+
     pub fn window_under(
         &self,
         position: Point<f64, Logical>,
     ) -> Option<(&Window, Point<i32, Logical>)> {
-        self.tiled.element_under(position)
+        // nothing else shows through a fullscreen window
+        if let Some(window) = self.fullscreen() {
+            let location = self.floating.element_location(window)? - window.geometry().loc;
+            return Some((window, location));
+        }
+
+        self.floating
+            .element_under(position)
+            .or_else(|| self.tiled.element_under(position))
     }
 
     pub fn surface_under(
@@ -343,77 +1487,373 @@ impl Workspace {
 
     fn refresh(&mut self) {
         self.tiled.refresh();
-        // self.floating.refresh();
+        self.floating.refresh();
     }
 
     fn deactivate(&mut self) {
-        for window in self.tiled.elements() {
+        for window in self.elements() {
             window.set_activated(false);
             window.toplevel().unwrap().send_pending_configure();
         }
     }
 
     fn raise_element(&mut self, window: &Window, activate: bool) {
-        self.tiled.raise_element(window, activate)
+        if self.is_floating(window) {
+            self.floating.raise_element(window, activate)
+        } else {
+            self.tiled.raise_element(window, activate)
+        }
+    }
+}
+
+// map the windows the tree moved, each slides over from where it was shown
+fn place_tiled(
+    space: &mut Space<Window>,
+    placements: Vec<Placement<Window>>,
+    loop_handle: &LoopHandle<'_, GlobalData>,
+) {
+    for Placement { window, rec, from } in placements {
+        // a window new to the tree bounces in and gets activated
+        let (from, animation, activate) = match from {
+            Some(from) => (from, crate::animation::AnimationType::OvershootBounce, true),
+            None => (
+                space.element_geometry(&window).unwrap_or(rec),
+                crate::animation::AnimationType::EaseInOutQuad,
+                false,
+            ),
+        };
+
+        window.set_rec(rec.size);
+        space.map_element(window.clone(), rec.loc, activate);
+
+        loop_handle.insert_idle(move |data| {
+            data.render_manager
+                .add_animation(window, from, rec, animation);
+        });
+    }
+}
+
+// split the longer side of `rec`, towards the grabbed corner
+fn split_direction(rec: Rectangle<i32, Logical>, edges: ResizeEdge) -> Direction {
+    if rec.size.w > rec.size.h {
+        match edges {
+            ResizeEdge::TopLeft | ResizeEdge::BottomLeft => Direction::Left,
+            ResizeEdge::TopRight | ResizeEdge::BottomRight => Direction::Right,
+            _ => Direction::default(),
+        }
+    } else {
+        match edges {
+            ResizeEdge::TopLeft | ResizeEdge::TopRight => Direction::Up,
+            ResizeEdge::BottomLeft | ResizeEdge::BottomRight => Direction::Down,
+            _ => Direction::default(),
+        }
+    }
+}
+
+// numbered workspaces sort by value and before named ones
+fn workspace_order(name: &str) -> (bool, u64, &str) {
+    match name.parse::<u64>() {
+        Ok(number) => (false, number, ""),
+        Err(_) => (true, 0, name),
     }
 }
 
 #[derive(Debug)]
 pub struct WorkspaceManager {
+    // ordered by name, numbers first
     workspaces: Vec<Workspace>,
+    // one shown workspace per output
+    visible: Vec<WorkspaceId>,
+    // the shown workspace of the focused output
     activated_workspace: Option<WorkspaceId>,
+    // name of the workspace focused before the current one
+    previous_workspace: Option<String>,
     configs: Arc<WorkspaceConfigs>,
+    events: EventSender,
 }
 
 impl WorkspaceManager {
-    pub fn new(configs: Arc<WorkspaceConfigs>) -> Self {
+    pub fn new(configs: Arc<WorkspaceConfigs>, events: EventSender) -> Self {
         Self {
             workspaces: vec![],
+            visible: vec![],
             activated_workspace: None,
+            previous_workspace: None,
             configs,
+            events,
         }
     }
 
-    // TODO: allow more output binds
     pub fn add_workspace(
         &mut self,
+        name: &str,
         output: &Output,
         output_geometry: Rectangle<i32, Logical>,
-        scheme: Option<TiledScheme>,
         activate: bool,
-    ) {
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> WorkspaceId {
         let workspace = Workspace::new(
+            name.to_string(),
             output,
             output_geometry,
-            scheme.unwrap_or_else(|| self.configs.scheme.clone()),
             self.configs.clone(),
+            self.events.clone(),
         );
+        let workspace_id = workspace.id();
+
+        let index = self
+            .workspaces
+            .partition_point(|w| workspace_order(w.name()) <= workspace_order(name));
+        self.workspaces.insert(index, workspace);
 
         if activate {
-            self.set_activated(workspace.id());
+            self.set_activated(workspace_id, loop_handle);
+        }
+
+        workspace_id
+    }
+
+    /// Show the workspace called `name`, creating it on `output` if missing.
+    pub fn switch_to(
+        &mut self,
+        name: &str,
+        output: &Output,
+        output_geometry: Rectangle<i32, Logical>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        match self.workspace_by_name(name).map(|w| w.id()) {
+            Some(workspace_id) => self.set_activated(workspace_id, loop_handle),
+            None => {
+                self.add_workspace(name, output, output_geometry, true, loop_handle);
+            }
+        }
+    }
+
+    pub fn set_activated(
+        &mut self,
+        workspace_id: WorkspaceId,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        let previous = self.activated_workspace;
+        if previous == Some(workspace_id) {
+            return;
         }
 
-        self.workspaces.push(workspace);
+        let replaced = self.show(workspace_id);
+
+        if let Some(current) = previous.and_then(|id| self.workspace_mut(id)) {
+            current.deactivate();
+            let name = current.name().to_string();
+            self.previous_workspace = Some(name);
+        }
+        self.activated_workspace = Some(workspace_id);
+
+        self.events.send(Event::WorkspaceActivated {
+            id: workspace_id.get(),
+            previous: previous.map(|id| id.get()),
+            name: self
+                .workspace(workspace_id)
+                .map(|w| w.name().to_string())
+                .unwrap_or_default(),
+        });
+
+        // empty workspaces only live while they are shown
+        if let Some(replaced) = replaced {
+            if self.workspace(replaced).is_some_and(|w| w.is_empty()) {
+                self.remove_workspace(replaced, loop_handle);
+            }
+        }
+    }
+
+    // make the workspace the shown one of its output, returns the one it hides
+    fn show(&mut self, workspace_id: WorkspaceId) -> Option<WorkspaceId> {
+        let output = self.workspace(workspace_id)?.output().clone();
+
+        let replaced = self.visible_on(&output);
+        self.visible.retain(|id| Some(*id) != replaced);
+        self.visible.push(workspace_id);
+        let replaced = replaced.filter(|id| *id != workspace_id);
+
+        if let Some(workspace) = replaced.and_then(|id| self.workspace_mut(id)) {
+            workspace.deactivate();
+        }
+
+        replaced
     }
 
-    pub fn set_activated(&mut self, workspace_id: WorkspaceId) {
-        if let Some(id) = self.activated_workspace {
-            if id != workspace_id {
-                self.current_workspace_mut().deactivate();
-                self.activated_workspace = Some(workspace_id);
+    /// Give every output a shown workspace and move the workspaces of outputs
+    /// that are gone onto the first output, the focused one.
+    pub fn sync_outputs(
+        &mut self,
+        outputs: &[(Output, Rectangle<i32, Logical>)],
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        let Some((fallback, fallback_geo)) = outputs.first() else {
+            // nowhere to go, keep everything until an output comes back
+            return;
+        };
+
+        let orphans: Vec<WorkspaceId> = self
+            .workspaces
+            .iter()
+            .filter(|w| outputs.iter().all(|(output, _)| output != w.output()))
+            .map(|w| w.id())
+            .collect();
+        for workspace_id in orphans {
+            info!(
+                "Moving workspace {:?} to output {}",
+                workspace_id,
+                fallback.name()
+            );
+            self.visible.retain(|id| *id != workspace_id);
+            if self.activated_workspace == Some(workspace_id) {
+                self.activated_workspace = None;
             }
-        } else {
-            self.activated_workspace = Some(workspace_id);
+            if let Some(workspace) = self.workspace_mut(workspace_id) {
+                workspace.set_output(fallback, *fallback_geo, loop_handle);
+            }
+        }
+
+        for (output, output_geo) in outputs {
+            // follow mode, position or scale changes
+            for workspace in self.workspaces.iter_mut().filter(|w| w.output() == output) {
+                workspace.modify_windows(*output_geo, loop_handle);
+            }
+
+            if self.visible_on(output).is_some() {
+                continue;
+            }
+
+            let workspace_id = match self.workspaces.iter().find(|w| w.output() == output) {
+                Some(workspace) => workspace.id(),
+                None => {
+                    let name = self.free_name(output);
+                    self.add_workspace(&name, output, *output_geo, false, loop_handle)
+                }
+            };
+            self.show(workspace_id);
+        }
+
+        // the hidden leftovers of a removed output may be empty
+        let empty: Vec<WorkspaceId> = self
+            .workspaces
+            .iter()
+            .filter(|w| w.is_empty() && !self.visible.contains(&w.id()))
+            .map(|w| w.id())
+            .collect();
+        for workspace_id in empty {
+            self.remove_workspace(workspace_id, loop_handle);
+        }
+
+        if self.activated_workspace.is_none() {
+            if let Some(workspace_id) = self.visible_on(fallback) {
+                self.set_activated(workspace_id, loop_handle);
+            }
+        }
+    }
+
+    // a workspace ruled onto the output, or the lowest unused number
+    fn free_name(&self, output: &Output) -> String {
+        let ruled = self.configs.rules.iter().find(|rule| {
+            rule.output.as_deref() == Some(output.name().as_str())
+                && self.workspace_by_name(&rule.name).is_none()
+        });
+
+        match ruled {
+            Some(rule) => rule.name.clone(),
+            None => (1..)
+                .map(|n: usize| n.to_string())
+                .find(|name| {
+                    self.workspace_by_name(name).is_none()
+                        && self
+                            .configs
+                            .rule(name)
+                            .is_none_or(|rule| rule.output.is_none())
+                })
+                .unwrap(),
+        }
+    }
+
+    /// Drop a workspace that is not shown, its windows are moved onto the
+    /// current one and returned.
+    pub fn remove_workspace(
+        &mut self,
+        workspace_id: WorkspaceId,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> Vec<Window> {
+        if self.visible.contains(&workspace_id) {
+            warn!("Cannot remove a shown workspace");
+            return vec![];
+        }
+
+        let Some(index) = self.workspaces.iter().position(|w| w.id() == workspace_id) else {
+            warn!("Failed to remove workspace: not found");
+            return vec![];
+        };
+
+        let workspace = self.workspaces.remove(index);
+        let windows: Vec<Window> = workspace.elements().cloned().collect();
+
+        for window in &windows {
+            self.current_workspace_mut().map_element(
+                window.clone(),
+                ResizeEdge::None,
+                false,
+                loop_handle,
+            );
+        }
+
+        windows
+    }
+
+    /// Move `window` from its workspace onto `target`, where it becomes the
+    /// focus. It stays activated only if `target` is the shown workspace.
+    pub fn move_window(
+        &mut self,
+        window: &Window,
+        target: WorkspaceId,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> bool {
+        if self.workspace(target).is_none_or(|w| w.contains(window)) {
+            return false;
+        }
+        let Some(source) = self.workspaces.iter_mut().find(|w| w.contains(window)) else {
+            warn!("Failed to move window: not mapped");
+            return false;
+        };
+        source.unmap_element(window, loop_handle);
+
+        if let Some(workspace) = self.workspaces.iter_mut().find(|w| w.id() == target) {
+            workspace.map_element(window.clone(), ResizeEdge::None, true, loop_handle);
+        }
+
+        if !self.visible.contains(&target) {
+            window.set_activated(false);
+            window.toplevel().unwrap().send_pending_configure();
         }
+
+        true
     }
 
-    pub fn _remove_workspace(&mut self, _workspace_id: usize) {
-        // move windows
-        todo!()
+    /// Name of the workspace `offset` places away from the current one on the
+    /// same output, wrapping around.
+    pub fn relative_workspace(&self, offset: isize) -> Option<String> {
+        let current = self.current_workspace();
+        let names: Vec<&str> = self
+            .workspaces
+            .iter()
+            .filter(|w| w.output() == current.output())
+            .map(|w| w.name())
+            .collect();
+
+        let index = names.iter().position(|name| *name == current.name())?;
+        let next = (index as isize + offset).rem_euclid(names.len() as isize);
+
+        Some(names[next as usize].to_string())
     }
 
-    pub fn current_space(&self) -> &Space<Window> {
-        self.current_workspace().current_space()
+    pub fn previous_workspace(&self) -> Option<&str> {
+        self.previous_workspace.as_deref()
     }
 
     pub fn current_workspace(&self) -> &Workspace {
@@ -428,16 +1868,39 @@ impl WorkspaceManager {
             .expect("no current_workspace")
     }
 
+    pub fn workspaces(&self) -> impl Iterator<Item = &Workspace> {
+        self.workspaces.iter()
+    }
+
+    pub fn workspace(&self, workspace_id: WorkspaceId) -> Option<&Workspace> {
+        self.workspaces.iter().find(|w| w.id() == workspace_id)
+    }
+
+    fn workspace_mut(&mut self, workspace_id: WorkspaceId) -> Option<&mut Workspace> {
+        self.workspaces.iter_mut().find(|w| w.id() == workspace_id)
+    }
+
+    pub fn activated_workspace(&self) -> Option<WorkspaceId> {
+        self.activated_workspace
+    }
+
+    pub fn workspace_by_name(&self, name: &str) -> Option<&Workspace> {
+        self.workspaces.iter().find(|w| w.name() == name)
+    }
+
     pub fn _workspaces_counts(&self) -> usize {
         self.workspaces.iter().count()
     }
 
-    pub fn elements(&self) -> impl DoubleEndedIterator<Item = &Window> {
-        self.current_workspace().elements()
+    pub fn is_floating(&self, window: &Window) -> bool {
+        self.workspaces.iter().any(|w| w.is_floating(window))
     }
 
     pub fn window_geometry(&self, window: &Window) -> Option<Rectangle<i32, Logical>> {
-        self.current_workspace().window_geometry(window)
+        self.workspaces
+            .iter()
+            .find(|w| w.contains(window))
+            .and_then(|w| w.window_geometry(window))
     }
 
     pub fn map_element(
@@ -452,8 +1915,15 @@ impl WorkspaceManager {
     }
 
     pub fn unmap_element(&mut self, window: &Window, loop_handle: &LoopHandle<'_, GlobalData>) {
-        self.current_workspace_mut()
-            .unmap_element(window, loop_handle);
+        let Some(workspace) = self.workspaces.iter_mut().find(|w| w.contains(window)) else {
+            return;
+        };
+        workspace.unmap_element(window, loop_handle);
+
+        let workspace_id = workspace.id();
+        if workspace.is_empty() && !self.visible.contains(&workspace_id) {
+            self.remove_workspace(workspace_id, loop_handle);
+        }
     }
 
     pub fn invert_window(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
@@ -469,6 +1939,23 @@ impl WorkspaceManager {
             .exchange_window(direction, loop_handle);
     }
 
+    pub fn toggle_container(
+        &mut self,
+        kind: ContainerKind,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.current_workspace_mut()
+            .toggle_container(kind, loop_handle);
+    }
+
+    pub fn cycle_tab(
+        &mut self,
+        delta: isize,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> Option<Window> {
+        self.current_workspace_mut().cycle_tab(delta, loop_handle)
+    }
+
     pub fn tiled_expansion(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
         self.current_workspace_mut().tiled_expansion(loop_handle);
     }
@@ -477,17 +1964,195 @@ impl WorkspaceManager {
         self.current_workspace_mut().tiled_recover(loop_handle);
     }
 
-    // pub fn resize(&mut self, offset: Point<i32, Logical>, edges: &ResizeEdge, rec: &mut Rectangle<i32, Logical>) {
-    //     self.current_workspace_mut().resize(offset, edges, rec);
-    // }
+    pub fn toggle_floating(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        self.current_workspace_mut().toggle_floating(loop_handle);
+    }
 
-    pub fn modify_windows(
+    pub fn toggle_fullscreen(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        self.current_workspace_mut().toggle_fullscreen(loop_handle);
+    }
+
+    pub fn toggle_maximized(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        self.current_workspace_mut().toggle_maximized(loop_handle);
+    }
+
+    /// Returns false if `window` is not mapped on any workspace yet.
+    pub fn set_fullscreen(
         &mut self,
-        rec: Rectangle<i32, Logical>,
+        window: &Window,
+        fullscreen: bool,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> bool {
+        let Some(workspace) = self.workspaces.iter_mut().find(|w| w.contains(window)) else {
+            return false;
+        };
+        workspace.set_fullscreen(window, fullscreen, loop_handle);
+        true
+    }
+
+    /// Returns false if `window` is not mapped on any workspace yet.
+    pub fn set_maximized(
+        &mut self,
+        window: &Window,
+        maximized: bool,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> bool {
+        let Some(workspace) = self.workspaces.iter_mut().find(|w| w.contains(window)) else {
+            return false;
+        };
+        workspace.set_maximized(window, maximized, loop_handle);
+        true
+    }
+
+    pub fn move_floating(&mut self, window: &Window, location: Point<i32, Logical>) {
+        self.current_workspace_mut().move_floating(window, location);
+    }
+
+    pub fn lift_window(
+        &mut self,
+        window: &Window,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> bool {
+        self.current_workspace_mut()
+            .lift_window(window, loop_handle)
+    }
+
+    pub fn update_drop(&mut self, position: Point<f64, Logical>) {
+        self.current_workspace_mut().update_drop(position);
+    }
+
+    pub fn drop_window(
+        &mut self,
+        position: Point<f64, Logical>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.current_workspace_mut()
+            .drop_window(position, loop_handle);
+    }
+
+    pub fn resize_floating(&mut self, window: &Window, rec: Rectangle<i32, Logical>) {
+        self.current_workspace_mut().resize_floating(window, rec);
+    }
+
+    pub fn focus_next(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) -> Option<Window> {
+        self.current_workspace_mut().focus_next(loop_handle)
+    }
+
+    pub fn focus_direction(
+        &mut self,
+        direction: &Direction,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> Option<Window> {
+        self.current_workspace_mut()
+            .focus_direction(direction, loop_handle)
+    }
+
+    pub fn set_focus(&mut self, window: Option<Window>) {
+        self.current_workspace_mut().set_focus(window);
+    }
+
+    /// The workspace shown on `output`.
+    pub fn visible_on(&self, output: &Output) -> Option<WorkspaceId> {
+        self.visible
+            .iter()
+            .copied()
+            .find(|id| self.workspace(*id).is_some_and(|w| w.output() == output))
+    }
+
+    pub fn visible_workspace(&self, output: &Output) -> Option<&Workspace> {
+        self.visible_on(output).and_then(|id| self.workspace(id))
+    }
+
+    pub fn visible_workspaces(&self) -> impl Iterator<Item = &Workspace> {
+        self.workspaces
+            .iter()
+            .filter(|w| self.visible.contains(&w.id()))
+    }
+
+    /// Follow a change of the layer surfaces on `output`.
+    pub fn arrange(&mut self, output: &Output, loop_handle: &LoopHandle<'_, GlobalData>) {
+        for workspace in self.workspaces.iter_mut().filter(|w| w.output() == output) {
+            workspace.arrange(loop_handle);
+        }
+    }
+
+    /// Frame callbacks for the windows of every shown workspace.
+    pub fn send_frames(&self, time: Duration) {
+        for workspace in self.visible_workspaces() {
+            let output = workspace.output();
+            for window in workspace.elements() {
+                window.send_frame(output, time, Some(Duration::ZERO), |_, _| {
+                    Some(output.clone())
+                });
+            }
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        window: &Window,
+        edges: ResizeEdge,
+        delta: Point<i32, Logical>,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.current_workspace_mut()
+            .resize(window, edges, delta, loop_handle);
+    }
+
+    pub fn resize_focus(
+        &mut self,
+        direction: &Direction,
+        amount: f64,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) -> bool {
+        self.current_workspace_mut()
+            .resize_focus(direction, amount, loop_handle)
+    }
+
+    pub fn equalize(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        self.current_workspace_mut().equalize(loop_handle);
+    }
+
+    pub fn promote(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        self.current_workspace_mut().promote(loop_handle);
+    }
+
+    pub fn change_master_count(&mut self, delta: isize, loop_handle: &LoopHandle<'_, GlobalData>) {
+        self.current_workspace_mut()
+            .change_master_count(delta, loop_handle);
+    }
+
+    pub fn cycle_column_width(&mut self, loop_handle: &LoopHandle<'_, GlobalData>) {
+        self.current_workspace_mut().cycle_column_width(loop_handle);
+    }
+
+    pub fn consume_or_expel(
+        &mut self,
+        direction: &Direction,
+        loop_handle: &LoopHandle<'_, GlobalData>,
+    ) {
+        self.current_workspace_mut()
+            .consume_or_expel(direction, loop_handle);
+    }
+
+    pub fn set_configs(
+        &mut self,
+        configs: Arc<WorkspaceConfigs>,
         loop_handle: &LoopHandle<'_, GlobalData>,
     ) {
+        for workspace in &mut self.workspaces {
+            workspace.set_configs(configs.clone(), loop_handle);
+        }
+        self.configs = configs;
+    }
+
+    pub fn layout(&self) -> Option<JsonTree> {
+        self.current_workspace().layout()
+    }
+
+    pub fn load_template(&mut self, json_tree: JsonTree, loop_handle: &LoopHandle<'_, GlobalData>) {
         self.current_workspace_mut()
-            .modify_windows(rec, loop_handle);
+            .load_template(json_tree, loop_handle);
     }
 
     pub fn surface_under(
@@ -501,7 +2166,7 @@ impl WorkspaceManager {
 
     pub fn find_window(&self, surface: &WlSurface) -> Option<&Window> {
         // TODO: maybe can use hashmap to store the surface
-        self.current_workspace().find_window(surface)
+        self.workspaces.iter().find_map(|w| w.find_window(surface))
     }
 
     pub fn check_grab(
@@ -512,6 +2177,10 @@ impl WorkspaceManager {
     }
 
     pub fn refresh(&mut self) {
-        self.current_workspace_mut().refresh();
+        for workspace in &mut self.workspaces {
+            if self.visible.contains(&workspace.id()) {
+                workspace.refresh();
+            }
+        }
     }
 }
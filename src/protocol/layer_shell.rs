@@ -42,14 +42,23 @@ impl WlrLayerShellHandler for GlobalData {
     }
 
     fn layer_destroyed(&mut self, surface: smithay::wayland::shell::wlr_layer::LayerSurface) {
-        // TODO: outputs
-        let map = layer_map_for_output(self.output_manager.current_output());
+        let Some(output) = self.layer_output(surface.wl_surface()) else {
+            return;
+        };
+
+        let mut map = layer_map_for_output(&output);
         let layer = map
             .layers()
             .find(|&layer| layer.layer_surface() == &surface)
             .cloned();
-        let (mut map, layer) = layer.map(|layer| (map, layer)).unwrap();
-        map.unmap_layer(&layer);
+        if let Some(layer) = layer {
+            map.unmap_layer(&layer);
+        }
+        map.arrange();
+        drop(map);
+
+        // its exclusive zone is free for the tiled windows again
+        self.workspace_manager.arrange(&output, &self.loop_handle);
     }
 
     fn new_popup(
@@ -64,9 +73,11 @@ delegate_layer_shell!(GlobalData);
 
 impl GlobalData {
     pub fn layer_shell_handle_commit(&mut self, surface: &WlSurface) -> bool {
-        let output = self.output_manager.current_output();
+        let Some(output) = self.layer_output(surface) else {
+            return false;
+        };
 
-        let mut map = layer_map_for_output(output);
+        let mut map = layer_map_for_output(&output);
 
         if map
             .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
@@ -90,10 +101,26 @@ impl GlobalData {
 
                 layer.layer_surface().send_configure();
             }
+            drop(map);
+
+            // the layer may have taken or given back an exclusive zone
+            self.workspace_manager.arrange(&output, &self.loop_handle);
 
             return true;
         }
 
         false
     }
+
+    // the output whose layer map holds this surface
+    fn layer_output(&self, surface: &WlSurface) -> Option<Output> {
+        self.output_manager
+            .outputs()
+            .find(|output| {
+                layer_map_for_output(output)
+                    .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                    .is_some()
+            })
+            .cloned()
+    }
 }
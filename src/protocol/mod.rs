@@ -1,5 +1,5 @@
 pub mod compositor;
 pub mod foreign_toplevel;
 pub mod layer_shell;
+pub mod output_management;
 pub mod xdg_shell;
-
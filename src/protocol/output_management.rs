@@ -0,0 +1,648 @@
+use std::sync::Mutex;
+
+use smithay::{
+    output::{Mode, Output, Scale},
+    reexports::{
+        wayland_protocols_wlr::output_management::v1::server::{
+            zwlr_output_configuration_head_v1::{self, ZwlrOutputConfigurationHeadV1},
+            zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+            zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+            zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+            zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+        },
+        wayland_server::{
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+            backend::ClientId,
+        },
+    },
+    utils::{Logical, Point, Transform},
+};
+
+use crate::state::GlobalData;
+
+// adaptive sync (v4) is not supported
+const VERSION: u32 = 3;
+
+/// What a head looks like, compared between updates to find changes.
+#[derive(Debug, Clone, PartialEq)]
+struct HeadState {
+    output: Output,
+    enabled: bool,
+    modes: Vec<Mode>,
+    preferred: Option<Mode>,
+    current: Option<Mode>,
+    position: Point<i32, Logical>,
+    transform: Transform,
+    scale: f64,
+}
+
+impl HeadState {
+    fn new(output: &Output, enabled: bool) -> Self {
+        Self {
+            output: output.clone(),
+            enabled,
+            modes: output.modes(),
+            preferred: output.preferred_mode(),
+            current: output.current_mode(),
+            position: output.current_location(),
+            transform: output.current_transform(),
+            scale: output.current_scale().fractional_scale(),
+        }
+    }
+}
+
+struct HeadEntry {
+    output: Output,
+    head: ZwlrOutputHeadV1,
+    modes: Vec<(Mode, ZwlrOutputModeV1)>,
+}
+
+struct ManagerEntry {
+    manager: ZwlrOutputManagerV1,
+    heads: Vec<HeadEntry>,
+}
+
+/// Heads and modes sent to every bound `zwlr_output_manager_v1`. Disabled
+/// outputs stay advertised, so clients can turn them on again.
+pub struct OutputManagementState {
+    display_handle: DisplayHandle,
+    managers: Vec<ManagerEntry>,
+    heads: Vec<HeadState>,
+    serial: u32,
+}
+
+impl OutputManagementState {
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<GlobalData, ZwlrOutputManagerV1, _>(VERSION, ());
+
+        Self {
+            display_handle: display_handle.clone(),
+            managers: Vec::new(),
+            heads: Vec::new(),
+            serial: 0,
+        }
+    }
+
+    /// Tell every client about added, removed or changed outputs.
+    pub fn update<'a>(
+        &mut self,
+        outputs: impl Iterator<Item = &'a Output>,
+        disabled: impl Iterator<Item = &'a Output>,
+    ) {
+        let heads: Vec<HeadState> = outputs
+            .map(|output| HeadState::new(output, true))
+            .chain(disabled.map(|output| HeadState::new(output, false)))
+            .collect();
+        if heads == self.heads {
+            return;
+        }
+
+        self.serial = self.serial.wrapping_add(1);
+        for entry in &mut self.managers {
+            entry.update(&self.display_handle, &heads, self.serial);
+        }
+        self.heads = heads;
+    }
+
+    fn add_manager(&mut self, manager: ZwlrOutputManagerV1) {
+        let mut entry = ManagerEntry {
+            manager,
+            heads: Vec::new(),
+        };
+        entry.update(&self.display_handle, &self.heads, self.serial);
+        self.managers.push(entry);
+    }
+
+    fn remove_manager(&mut self, manager: &ZwlrOutputManagerV1) {
+        self.managers.retain(|entry| entry.manager != *manager);
+    }
+}
+
+impl ManagerEntry {
+    fn update(&mut self, display_handle: &DisplayHandle, heads: &[HeadState], serial: u32) {
+        // unplugged
+        self.heads.retain(|entry| {
+            let alive = heads.iter().any(|state| state.output == entry.output);
+            if !alive {
+                for (_, mode) in &entry.modes {
+                    mode.finished();
+                }
+                entry.head.finished();
+            }
+            alive
+        });
+
+        for state in heads {
+            match self.heads.iter_mut().find(|e| e.output == state.output) {
+                Some(entry) => entry.update(display_handle, state),
+                None => {
+                    if let Some(entry) = HeadEntry::new(display_handle, &self.manager, state) {
+                        self.heads.push(entry);
+                    }
+                }
+            }
+        }
+
+        self.manager.done(serial);
+    }
+}
+
+impl HeadEntry {
+    fn new(
+        display_handle: &DisplayHandle,
+        manager: &ZwlrOutputManagerV1,
+        state: &HeadState,
+    ) -> Option<Self> {
+        let client = manager.client()?;
+        let head = client
+            .create_resource::<ZwlrOutputHeadV1, _, GlobalData>(
+                display_handle,
+                manager.version(),
+                state.output.clone(),
+            )
+            .ok()?;
+        manager.head(&head);
+
+        let output = &state.output;
+        let properties = output.physical_properties();
+        head.name(output.name());
+        head.description(output.description());
+        if properties.size.w > 0 && properties.size.h > 0 {
+            head.physical_size(properties.size.w, properties.size.h);
+        }
+        if head.version() >= zwlr_output_head_v1::EVT_MAKE_SINCE {
+            head.make(properties.make);
+            head.model(properties.model);
+        }
+
+        let mut entry = Self {
+            output: output.clone(),
+            head,
+            modes: Vec::new(),
+        };
+        entry.update(display_handle, state);
+
+        Some(entry)
+    }
+
+    fn update(&mut self, display_handle: &DisplayHandle, state: &HeadState) {
+        self.modes.retain(|(mode, object)| {
+            let alive = state.modes.contains(mode);
+            if !alive {
+                object.finished();
+            }
+            alive
+        });
+
+        for mode in &state.modes {
+            if self.modes.iter().any(|(m, _)| m == mode) {
+                continue;
+            }
+            let Some(object) = self.head.client().and_then(|client| {
+                client
+                    .create_resource::<ZwlrOutputModeV1, _, GlobalData>(
+                        display_handle,
+                        self.head.version(),
+                        ModeData {
+                            output: self.output.clone(),
+                            mode: *mode,
+                        },
+                    )
+                    .ok()
+            }) else {
+                continue;
+            };
+
+            self.head.mode(&object);
+            object.size(mode.size.w, mode.size.h);
+            if mode.refresh > 0 {
+                object.refresh(mode.refresh);
+            }
+            if state.preferred == Some(*mode) {
+                object.preferred();
+            }
+            self.modes.push((*mode, object));
+        }
+
+        self.head.enabled(state.enabled.into());
+        // the rest only describes enabled heads
+        if !state.enabled {
+            return;
+        }
+        if let Some((_, object)) = self.modes.iter().find(|(m, _)| Some(*m) == state.current) {
+            self.head.current_mode(object);
+        }
+        self.head.position(state.position.x, state.position.y);
+        self.head.transform(state.transform.into());
+        self.head.scale(state.scale);
+    }
+}
+
+pub struct ModeData {
+    output: Output,
+    mode: Mode,
+}
+
+/// The changes a client asks for on one head.
+#[derive(Debug, Clone)]
+pub struct HeadConfiguration {
+    pub output: Output,
+    pub enabled: bool,
+    pub mode: Option<Mode>,
+    pub position: Option<Point<i32, Logical>>,
+    pub transform: Option<Transform>,
+    pub scale: Option<f64>,
+}
+
+impl HeadConfiguration {
+    fn new(output: Output, enabled: bool) -> Self {
+        Self {
+            output,
+            enabled,
+            mode: None,
+            position: None,
+            transform: None,
+            scale: None,
+        }
+    }
+}
+
+pub struct PendingConfiguration {
+    serial: u32,
+    heads: Vec<HeadConfiguration>,
+    // apply or test was sent
+    used: bool,
+}
+
+impl PendingConfiguration {
+    // heads left out stay as they are
+    fn configure(
+        &mut self,
+        configuration: &ZwlrOutputConfigurationV1,
+        output: Output,
+        enabled: bool,
+    ) {
+        if self.used {
+            configuration.post_error(
+                zwlr_output_configuration_v1::Error::AlreadyUsed,
+                "configuration has already been used",
+            );
+        } else if self.heads.iter().any(|head| head.output == output) {
+            configuration.post_error(
+                zwlr_output_configuration_v1::Error::AlreadyConfiguredHead,
+                "head has already been configured",
+            );
+        } else {
+            self.heads.push(HeadConfiguration::new(output, enabled));
+        }
+    }
+}
+
+pub struct ConfigurationHeadData {
+    configuration: ZwlrOutputConfigurationV1,
+    output: Output,
+}
+
+impl GlobalDispatch<ZwlrOutputManagerV1, ()> for GlobalData {
+    fn bind(
+        state: &mut Self,
+        _display_handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrOutputManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+        state.state.output_management_state.add_manager(manager);
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for GlobalData {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        manager: &ZwlrOutputManagerV1,
+        request: zwlr_output_manager_v1::Request,
+        _data: &(),
+        _display_handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_output_manager_v1::Request::CreateConfiguration { id, serial } => {
+                data_init.init(
+                    id,
+                    Mutex::new(PendingConfiguration {
+                        serial,
+                        heads: Vec::new(),
+                        used: false,
+                    }),
+                );
+            }
+            zwlr_output_manager_v1::Request::Stop => {
+                state.state.output_management_state.remove_manager(manager);
+                manager.finished();
+            }
+            _ => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, manager: &ZwlrOutputManagerV1, _data: &()) {
+        state.state.output_management_state.remove_manager(manager);
+    }
+}
+
+impl Dispatch<ZwlrOutputHeadV1, Output> for GlobalData {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _head: &ZwlrOutputHeadV1,
+        _request: zwlr_output_head_v1::Request,
+        _data: &Output,
+        _display_handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        // only release, a destructor
+    }
+}
+
+impl Dispatch<ZwlrOutputModeV1, ModeData> for GlobalData {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _mode: &ZwlrOutputModeV1,
+        _request: zwlr_output_mode_v1::Request,
+        _data: &ModeData,
+        _display_handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        // only release, a destructor
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, Mutex<PendingConfiguration>> for GlobalData {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        configuration: &ZwlrOutputConfigurationV1,
+        request: zwlr_output_configuration_v1::Request,
+        data: &Mutex<PendingConfiguration>,
+        _display_handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let mut pending = data.lock().unwrap();
+
+        let test_only = match request {
+            zwlr_output_configuration_v1::Request::EnableHead { id, head } => {
+                let Some(output) = head.data::<Output>().cloned() else {
+                    return;
+                };
+                data_init.init(
+                    id,
+                    ConfigurationHeadData {
+                        configuration: configuration.clone(),
+                        output: output.clone(),
+                    },
+                );
+                pending.configure(configuration, output, true);
+                return;
+            }
+            zwlr_output_configuration_v1::Request::DisableHead { head } => {
+                if let Some(output) = head.data::<Output>().cloned() {
+                    pending.configure(configuration, output, false);
+                }
+                return;
+            }
+            zwlr_output_configuration_v1::Request::Apply => false,
+            zwlr_output_configuration_v1::Request::Test => true,
+            _ => return,
+        };
+
+        if pending.used {
+            configuration.post_error(
+                zwlr_output_configuration_v1::Error::AlreadyUsed,
+                "configuration has already been used",
+            );
+            return;
+        }
+        pending.used = true;
+
+        // the outputs changed since the client looked at them
+        if pending.serial != state.state.output_management_state.serial {
+            configuration.cancelled();
+            return;
+        }
+
+        let heads = pending.heads.clone();
+        drop(pending);
+
+        if state.apply_output_configuration(&heads, test_only) {
+            configuration.succeeded();
+        } else {
+            configuration.failed();
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, ConfigurationHeadData> for GlobalData {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        configuration_head: &ZwlrOutputConfigurationHeadV1,
+        request: zwlr_output_configuration_head_v1::Request,
+        data: &ConfigurationHeadData,
+        _display_handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        use zwlr_output_configuration_head_v1::{Error, Request};
+
+        let Some(pending) = data.configuration.data::<Mutex<PendingConfiguration>>() else {
+            return;
+        };
+        let mut pending = pending.lock().unwrap();
+        let Some(head) = pending
+            .heads
+            .iter_mut()
+            .find(|head| head.output == data.output)
+        else {
+            return;
+        };
+
+        let error = |error: Error, message: &str| {
+            configuration_head.post_error(error, message.to_string());
+        };
+
+        match request {
+            Request::SetMode { mode } => {
+                let Some(mode) = mode.data::<ModeData>() else {
+                    return;
+                };
+                if head.mode.is_some() {
+                    error(Error::AlreadySet, "mode has already been set");
+                } else if mode.output != data.output {
+                    error(Error::InvalidMode, "mode belongs to another head");
+                } else {
+                    head.mode = Some(mode.mode);
+                }
+            }
+            Request::SetCustomMode {
+                width,
+                height,
+                refresh,
+            } => {
+                if head.mode.is_some() {
+                    error(Error::AlreadySet, "mode has already been set");
+                } else if width <= 0 || height <= 0 || refresh < 0 {
+                    error(Error::InvalidCustomMode, "invalid custom mode");
+                } else {
+                    head.mode = Some(Mode {
+                        size: (width, height).into(),
+                        refresh,
+                    });
+                }
+            }
+            Request::SetPosition { x, y } => {
+                if head.position.is_some() {
+                    error(Error::AlreadySet, "position has already been set");
+                } else {
+                    head.position = Some((x, y).into());
+                }
+            }
+            Request::SetTransform { transform } => {
+                if head.transform.is_some() {
+                    error(Error::AlreadySet, "transform has already been set");
+                } else {
+                    match transform.into_result() {
+                        Ok(transform) => head.transform = Some(transform.into()),
+                        Err(_) => error(Error::InvalidTransform, "invalid transform"),
+                    }
+                }
+            }
+            Request::SetScale { scale } => {
+                if head.scale.is_some() {
+                    error(Error::AlreadySet, "scale has already been set");
+                } else if !scale.is_finite() || scale <= 0.0 {
+                    error(Error::InvalidScale, "invalid scale");
+                } else {
+                    head.scale = Some(scale);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl GlobalData {
+    /// Everything is checked before anything changes. If the backend still
+    /// fails on a head, the heads changed so far are put back.
+    fn apply_output_configuration(&mut self, heads: &[HeadConfiguration], test_only: bool) -> bool {
+        let enabled = |output: &Output| self.output_manager.outputs().any(|o| o == output);
+        for head in heads {
+            let known = enabled(&head.output)
+                || self
+                    .output_manager
+                    .disabled_outputs()
+                    .any(|o| *o == head.output);
+            if !known {
+                return false;
+            }
+            if !head.enabled && !self.backend.supports_disable() {
+                return false;
+            }
+            if let Some(mode) = head.mode {
+                if head.enabled && !self.backend.supports_mode(&head.output, mode) {
+                    return false;
+                }
+            }
+        }
+
+        // something has to stay on
+        let turned_off = |output: &Output| {
+            heads
+                .iter()
+                .any(|head| head.output == *output && !head.enabled)
+        };
+        let stays_on = heads.iter().any(|head| head.enabled)
+            || self.output_manager.outputs().any(|o| !turned_off(o));
+        if !stays_on {
+            return false;
+        }
+
+        if test_only {
+            return true;
+        }
+
+        let mut applied = Vec::new();
+        for head in heads {
+            applied.push(self.head_configuration(&head.output));
+            if let Err(err) = self.apply_head_configuration(head) {
+                warn!(
+                    "Failed to configure output {}: {:?}",
+                    head.output.name(),
+                    err
+                );
+
+                for previous in applied.iter().rev() {
+                    if let Err(err) = self.apply_head_configuration(previous) {
+                        warn!(
+                            "Failed to restore output {}: {:?}",
+                            previous.output.name(),
+                            err
+                        );
+                    }
+                }
+                self.outputs_changed();
+                return false;
+            }
+        }
+
+        self.outputs_changed();
+        true
+    }
+
+    // what applying would take to get `output` back to how it is now
+    fn head_configuration(&self, output: &Output) -> HeadConfiguration {
+        let mut head = HeadConfiguration::new(
+            output.clone(),
+            self.output_manager.outputs().any(|o| o == output),
+        );
+        if head.enabled {
+            head.mode = output.current_mode();
+            head.position = Some(output.current_location());
+            head.transform = Some(output.current_transform());
+            head.scale = Some(output.current_scale().fractional_scale());
+        }
+        head
+    }
+
+    fn apply_head_configuration(&mut self, head: &HeadConfiguration) -> anyhow::Result<()> {
+        let enabled = self.output_manager.outputs().any(|o| *o == head.output);
+        if !head.enabled {
+            if enabled {
+                self.backend
+                    .disable_output(&head.output, &mut self.output_manager)?;
+            }
+            return Ok(());
+        }
+
+        let mode = if !enabled {
+            self.backend
+                .enable_output(&head.output, head.mode, &mut self.output_manager)?;
+            None
+        } else {
+            match head.mode {
+                Some(mode) if head.output.current_mode() != Some(mode) => {
+                    self.backend.set_output_mode(&head.output, mode)?
+                }
+                _ => None,
+            }
+        };
+        self.output_manager.change_current_state(
+            &head.output,
+            mode,
+            head.transform,
+            head.scale.map(Scale::Fractional),
+            head.position,
+        );
+        Ok(())
+    }
+}
@@ -1,11 +1,11 @@
 use crate::{
-    input::resize_grab::ResizeSurfaceGrab, state::GlobalData
+    input::{move_grab::PointerMoveSurfaceGrab, resize_grab::ResizeSurfaceGrab}, state::GlobalData
 };
 use smithay::{
     delegate_xdg_shell, desktop::{PopupKind, Window}, input::{pointer::{Focus, PointerHandle}, Seat}, reexports::{
         wayland_protocols::xdg::shell::server::xdg_toplevel::{self, ResizeEdge},
         wayland_server::{
-            protocol::{wl_seat, wl_surface::WlSurface}, Resource
+            protocol::{wl_output::WlOutput, wl_seat, wl_surface::WlSurface}, Resource
         },
     }, utils::{Logical, Point, Rectangle, Serial, SERIAL_COUNTER}, wayland::{
         compositor::{self, with_states},
@@ -16,7 +16,7 @@ use smithay::{
 };
 use smithay::{
     desktop::{find_popup_root_surface, get_popup_toplevel_coords},
-    input::pointer::GrabStartData as PointerGrabStartData,
+    input::pointer::{CursorIcon, CursorImageStatus, GrabStartData as PointerGrabStartData},
 };
 
 impl XdgShellHandler for GlobalData {
@@ -38,36 +38,7 @@ impl XdgShellHandler for GlobalData {
         //     window.set_rec(size);
         // }
 
-        // TODO:
-        let pointer = self.input_manager.get_pointer();
-        let pointer = match pointer {
-            Some(k) => k,
-            None => {
-                error!("get pointer error");
-                return;
-            }
-        };
-        let pointer_loc = pointer.current_location();
-
-        let edges = match self.workspace_manager.current_workspace().focus() {
-            Some (focus) => {
-                let window_rec = self.workspace_manager.window_geometry(focus).unwrap();
-                detect_pointer_quadrant(pointer_loc, window_rec.to_f64())
-            }
-            None => {
-                ResizeEdge::None
-            }
-        };
-
-        self.workspace_manager
-            .map_element(
-                window.clone(),
-                edges,
-                true,
-                &self.loop_handle,
-            );
-
-        self.set_keyboard_focus(Some(surface.wl_surface().clone()), SERIAL_COUNTER.next_serial());
+        // mapped on the initial commit, once the parent and size limits are known
     }
 
     fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
@@ -132,7 +103,7 @@ impl XdgShellHandler for GlobalData {
         let wl_surface = surface.wl_surface();
 
         if let Some(start_data) = check_grab(&pointer, wl_surface, serial) {
-            self.resize_move_request(wl_surface, &pointer, start_data, serial);
+            self.grab_move_request(wl_surface, &pointer, start_data, serial);
         }
     }
 
@@ -178,6 +149,27 @@ impl XdgShellHandler for GlobalData {
 
     fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {}
 
+    fn fullscreen_request(&mut self, surface: ToplevelSurface, _output: Option<WlOutput>) {
+        // the window stays on the output of its workspace
+        self.set_fullscreen(&surface, true);
+    }
+
+    fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
+        self.set_fullscreen(&surface, false);
+    }
+
+    fn maximize_request(&mut self, surface: ToplevelSurface) {
+        self.set_maximized(&surface, true);
+    }
+
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        self.set_maximized(&surface, false);
+    }
+
+    fn minimize_request(&mut self, _surface: ToplevelSurface) {
+        // a tiling layout has nowhere to hide a window
+    }
+
     fn title_changed(&mut self, surface: ToplevelSurface) {
         let (title, app_id) = 
             compositor::with_states(surface.wl_surface(), |states| {
@@ -190,6 +182,8 @@ impl XdgShellHandler for GlobalData {
                 (roll.title.clone(), roll.app_id.clone())
             });
 
+        self.window_manager.title_changed(surface.wl_surface(), title.clone(), app_id.clone());
+
         self.window_manager.get_foreign_handle(surface.wl_surface())
             .map(|handle| {
                 handle.send_title(&title.unwrap_or("unkown".to_string()));
@@ -240,9 +234,26 @@ impl GlobalData {
         });
     }
 
-    pub fn grab_move_request(&mut self, wl_surface: &WlSurface, _pointer: &PointerHandle<GlobalData>, _start_data: PointerGrabStartData<GlobalData>, _serial: Serial) {
-        if let Some((..)) = self.workspace_manager.check_grab(wl_surface) {
-            // TODO
+    pub fn grab_move_request(&mut self, wl_surface: &WlSurface, pointer: &PointerHandle<GlobalData>, start_data: PointerGrabStartData<GlobalData>, serial: Serial) {
+        if let Some((window, window_rec)) = self.workspace_manager.check_grab(wl_surface) {
+            let window = window.clone();
+
+            // tiled windows leave the tree while dragged and drop back in on release
+            let retile = !self.workspace_manager.is_floating(&window);
+            if retile && !self.workspace_manager.lift_window(&window, &self.loop_handle) {
+                return;
+            }
+
+            let grab = PointerMoveSurfaceGrab {
+                start_data,
+                window,
+                initial_window_location: window_rec.loc,
+                retile,
+            };
+
+            self.cursor_manager.set_cursor_image(CursorImageStatus::Named(CursorIcon::Grabbing));
+
+            pointer.set_grab(self, grab, serial, Focus::Clear);
         }
     }
 
@@ -253,6 +264,14 @@ impl GlobalData {
 
             let edges = detect_pointer_quadrant(pointer_loc, window_rec.to_f64());
 
+            let icon = match edges {
+                ResizeEdge::TopLeft => CursorIcon::NwResize,
+                ResizeEdge::TopRight => CursorIcon::NeResize,
+                ResizeEdge::BottomLeft => CursorIcon::SwResize,
+                _ => CursorIcon::SeResize,
+            };
+            self.cursor_manager.set_cursor_image(CursorImageStatus::Named(icon));
+
             // set pointer state
             let grab = ResizeSurfaceGrab::start(
                 start_data,
@@ -265,11 +284,96 @@ impl GlobalData {
         }
     }
 
-    pub fn xdg_shell_handle_commit(&mut self, surface: &WlSurface) {
-        let popups = &mut self.popups;
+    fn set_fullscreen(&mut self, surface: &ToplevelSurface, fullscreen: bool) {
+        let mapped = match self.window_manager.get_window(surface.wl_surface()).cloned() {
+            Some(window) => self.workspace_manager.set_fullscreen(&window, fullscreen, &self.loop_handle),
+            None => false,
+        };
+
+        if mapped {
+            // the client waits for a configure even if nothing changed
+            surface.send_configure();
+            self.sync_keyboard_focus();
+        } else {
+            // applied once the window is mapped
+            surface.with_pending_state(|state| {
+                if fullscreen {
+                    state.states.set(xdg_toplevel::State::Fullscreen);
+                } else {
+                    state.states.unset(xdg_toplevel::State::Fullscreen);
+                }
+            });
+        }
+    }
+
+    fn set_maximized(&mut self, surface: &ToplevelSurface, maximized: bool) {
+        let mapped = match self.window_manager.get_window(surface.wl_surface()).cloned() {
+            Some(window) => self.workspace_manager.set_maximized(&window, maximized, &self.loop_handle),
+            None => false,
+        };
+
+        if mapped {
+            surface.send_configure();
+            self.sync_keyboard_focus();
+        } else {
+            surface.with_pending_state(|state| {
+                if maximized {
+                    state.states.set(xdg_toplevel::State::Maximized);
+                } else {
+                    state.states.unset(xdg_toplevel::State::Maximized);
+                }
+            });
+        }
+    }
+
+    // the request may have moved the focus of the current workspace
+    fn sync_keyboard_focus(&mut self) {
+        if let Some(window) = self.workspace_manager.current_workspace().focus() {
+            let wl_surface = window.toplevel().unwrap().wl_surface().clone();
+            self.set_keyboard_focus(Some(wl_surface), SERIAL_COUNTER.next_serial());
+        }
+    }
+
+    fn map_window(&mut self, window: Window) {
+        let pointer = self.input_manager.get_pointer();
+        let pointer = match pointer {
+            Some(k) => k,
+            None => {
+                error!("get pointer error");
+                return;
+            }
+        };
+        let pointer_loc = pointer.current_location();
+
+        let edges = match self.workspace_manager.current_workspace().focus() {
+            Some (focus) => {
+                let window_rec = self.workspace_manager.window_geometry(focus).unwrap();
+                detect_pointer_quadrant(pointer_loc, window_rec.to_f64())
+            }
+            None => {
+                ResizeEdge::None
+            }
+        };
 
+        self.workspace_manager
+            .map_element(
+                window.clone(),
+                edges,
+                true,
+                &self.loop_handle,
+            );
+
+        // the workspace may have changed since the toplevel was created
+        let workspace_id = self.workspace_manager.current_workspace().id();
+        self.window_manager.move_window(&window, workspace_id);
+
+        let wl_surface = window.toplevel().unwrap().wl_surface().clone();
+        self.set_keyboard_focus(Some(wl_surface), SERIAL_COUNTER.next_serial());
+    }
+
+    pub fn xdg_shell_handle_commit(&mut self, surface: &WlSurface) {
         // Handle toplevel commits.
-        if let Some(window) = self.workspace_manager.find_window(surface)
+        if let Some(window) = self.window_manager.get_window(surface).cloned()
         {
             let initial_configure_sent = with_states(surface, |states| {
                 states
@@ -282,11 +386,13 @@ impl GlobalData {
             });
 
             if !initial_configure_sent {
+                self.map_window(window.clone());
                 window.toplevel().unwrap().send_configure();
             }
         }
 
         // Handle popup commits.
+        let popups = &mut self.popups;
         popups.commit(surface);
         if let Some(popup) = popups.find_popup(surface) {
             match popup {
@@ -339,7 +445,7 @@ fn check_grab(
     Some(start_data)
 }
 
-fn detect_pointer_quadrant(
+pub fn detect_pointer_quadrant(
     pointer_loc: Point<f64, Logical>,
     window_rec: Rectangle<f64, Logical>,
 ) -> ResizeEdge {
@@ -1,185 +1,27 @@
-use smithay::{
-    backend::renderer::{
-        element::{Element, Id, Kind, RenderElement, UnderlyingStorage},
-        gles::{
-            GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, Uniform, UniformName, UniformType,
-        },
-        utils::{CommitCounter, OpaqueRegions},
-    },
-    utils::{Buffer, Logical, Physical, Rectangle, Scale, Transform},
-};
+use smithay::backend::renderer::gles::{GlesPixelProgram, UniformType};
 
-use crate::backend::tty::{TtyFrame, TtyRenderer, TtyRendererError};
-
-use super::AsGlesFrame;
-
-pub struct BorderShader(pub GlesPixelProgram);
+use super::shader::{PixelShader, PixelShaderElement};
 
 #[derive(Debug)]
-pub struct BorderRenderElement {
-    id: Id,
-    shader: GlesPixelProgram,
-    commit_counter: CommitCounter,
-    area: Rectangle<i32, Logical>,
-    opaque_regions: Vec<Rectangle<i32, Logical>>,
-    alpha: f32,
-    additional_uniforms: Vec<Uniform<'static>>,
-    kind: Kind,
-}
-
-impl BorderRenderElement {
-    pub fn new(
-        shader: GlesPixelProgram,
-        area: Rectangle<i32, Logical>,
-        opaque_regions: Option<Vec<Rectangle<i32, Logical>>>,
-        alpha: f32,
-        additional_uniforms: Vec<Uniform<'_>>,
-        kind: Kind,
-    ) -> Self {
-        Self {
-            id: Id::new(),
-            shader,
-            commit_counter: CommitCounter::default(),
-            area,
-            opaque_regions: opaque_regions.unwrap_or_default(),
-            alpha,
-            additional_uniforms: additional_uniforms
-                .into_iter()
-                .map(|u| u.into_owned())
-                .collect(),
-            kind,
-        }
-    }
-
-    pub fn complie_shaders(renderer: &mut GlesRenderer) {
-        let border_shader = renderer
-            .compile_custom_pixel_shader(
-                include_str!("../render/shaders/border.frag"),
-                &[
-                    UniformName::new("u_resolution", UniformType::_2f),
-                    UniformName::new("border_color", UniformType::_3f),
-                    UniformName::new("border_thickness", UniformType::_1f),
-                    UniformName::new("u_time", UniformType::_1f),
-                    UniformName::new("corner_radius", UniformType::_1f),
-                ],
-            )
-            .unwrap();
-
-        // Save pixel shader in EGL rendering context.
-        renderer
-            .egl_context()
-            .user_data()
-            .insert_if_missing(|| BorderShader(border_shader));
-    }
-
-    /// Resize the canvas area
-    pub fn _resize(
-        &mut self,
-        area: Rectangle<i32, Logical>,
-        opaque_regions: Option<Vec<Rectangle<i32, Logical>>>,
-    ) {
-        let opaque_regions = opaque_regions.unwrap_or_default();
-        if self.area != area || self.opaque_regions != opaque_regions {
-            self.area = area;
-            self.opaque_regions = opaque_regions;
-            self.commit_counter.increment();
-        }
-    }
-
-    /// Update the additional uniforms
-    /// (see [`GlesRenderer::compile_custom_pixel_shader`] and [`GlesFrame::render_pixel_shader_to`]).
-    ///
-    /// This replaces the stored uniforms, you have to update all of them, partial updates are not possible.
-    pub fn _update_uniforms(&mut self, additional_uniforms: Vec<Uniform<'_>>) {
-        self.additional_uniforms = additional_uniforms
-            .into_iter()
-            .map(|u| u.into_owned())
-            .collect();
-        self.commit_counter.increment();
-    }
-}
-
-impl Element for BorderRenderElement {
-    fn id(&self) -> &Id {
-        &self.id
-    }
-
-    fn current_commit(&self) -> CommitCounter {
-        self.commit_counter
-    }
-
-    fn src(&self) -> Rectangle<f64, Buffer> {
-        Rectangle::from_size(self.area.size.to_f64().to_buffer(1.0, Transform::Normal))
-    }
-
-    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
-        self.area.to_physical_precise_round(scale)
-    }
-
-    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
-        self.opaque_regions
-            .iter()
-            .map(|region| region.to_physical_precise_down(scale))
-            .collect()
-    }
-
-    fn alpha(&self) -> f32 {
-        self.alpha
-    }
-
-    fn kind(&self) -> Kind {
-        self.kind
-    }
-}
+pub struct BorderShader(GlesPixelProgram);
 
-impl RenderElement<GlesRenderer> for BorderRenderElement {
-    fn draw(
-        &self,
-        frame: &mut GlesFrame<'_, '_>,
-        src: Rectangle<f64, Buffer>,
-        dst: Rectangle<i32, Physical>,
-        damage: &[Rectangle<i32, Physical>],
-        _opaque_regions: &[Rectangle<i32, Physical>],
-    ) -> Result<(), GlesError> {
-        frame.render_pixel_shader_to(
-            &self.shader,
-            src,
-            dst,
-            self.area.size.to_buffer(1, Transform::Flipped180),
-            Some(damage),
-            self.alpha,
-            &self.additional_uniforms,
-        )
-    }
-
-    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage> {
-        // If scanout for things other than Wayland buffers is implemented, this will need to take
-        // the target GPU into account.
-        None
-    }
-}
+impl PixelShader for BorderShader {
+    const SOURCE: &'static str = include_str!("../render/shaders/border.frag");
+    const UNIFORMS: &'static [(&'static str, UniformType)] = &[
+        ("u_resolution", UniformType::_2f),
+        ("border_color", UniformType::_3f),
+        ("border_thickness", UniformType::_1f),
+        ("u_time", UniformType::_1f),
+        ("corner_radius", UniformType::_1f),
+    ];
 
-impl<'render> RenderElement<TtyRenderer<'render>> for BorderRenderElement {
-    fn draw(
-        &self,
-        frame: &mut TtyFrame<'_, '_, '_>,
-        src: Rectangle<f64, Buffer>,
-        dst: Rectangle<i32, Physical>,
-        damage: &[Rectangle<i32, Physical>],
-        opaque_regions: &[Rectangle<i32, Physical>],
-    ) -> Result<(), TtyRendererError<'_>> {
-        let frame = frame.as_gles_frame();
-        RenderElement::<GlesRenderer>::draw(&self, frame, src, dst, damage, opaque_regions)?;
-        Ok(())
+    fn new(program: GlesPixelProgram) -> Self {
+        Self(program)
     }
 
-    fn underlying_storage(
-        &self,
-        _renderer: &mut TtyRenderer<'render>,
-    ) -> Option<UnderlyingStorage> {
-        // If scanout for things other than Wayland buffers is implemented, this will need to take
-        // the target GPU into account.
-        None
+    fn program(&self) -> &GlesPixelProgram {
+        &self.0
     }
 }
 
+pub type BorderRenderElement = PixelShaderElement<BorderShader>;
@@ -7,12 +7,15 @@ use smithay::{
 
 use crate::niri_render_elements;
 
-use super::{background::BackgroundRenderElement, border::BorderRenderElement};
+use super::{
+    background::BackgroundRenderElement, border::BorderRenderElement, title::TitleRenderElement,
+};
 
 niri_render_elements! {
     ShaderRenderElement => {
         Border=BorderRenderElement,
         Background=BackgroundRenderElement,
+        Title=TitleRenderElement,
     }
 }
 
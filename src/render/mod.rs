@@ -10,6 +10,7 @@ pub mod border;
 pub mod elements;
 pub mod render_elements;
 pub mod shader;
+pub mod title;
 
 /// Trait with our main renderer requirements to save on the typing.
 pub trait MondrianRenderer:
@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+
+use smithay::{
+    backend::renderer::{
+        element::{Element, Id, Kind, RenderElement, UnderlyingStorage},
+        gles::{
+            GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, Uniform, UniformName, UniformType,
+        },
+        utils::{CommitCounter, OpaqueRegions},
+    },
+    utils::{Buffer, Logical, Physical, Rectangle, Scale, Transform},
+};
+
+use crate::backend::tty::{TtyFrame, TtyRenderer, TtyRendererError};
+
+use super::AsGlesFrame;
+
+/// A pixel shader compiled once per renderer and kept in its EGL context.
+pub trait PixelShader: Sized + 'static {
+    const SOURCE: &'static str;
+    const UNIFORMS: &'static [(&'static str, UniformType)];
+
+    fn new(program: GlesPixelProgram) -> Self;
+
+    fn program(&self) -> &GlesPixelProgram;
+
+    fn compile(renderer: &mut GlesRenderer) {
+        let uniforms: Vec<_> = Self::UNIFORMS
+            .iter()
+            .map(|(name, kind)| UniformName::new(*name, *kind))
+            .collect();
+        let program = renderer
+            .compile_custom_pixel_shader(Self::SOURCE, &uniforms)
+            .unwrap();
+
+        // Save pixel shader in EGL rendering context.
+        renderer
+            .egl_context()
+            .user_data()
+            .insert_if_missing(|| Self::new(program));
+    }
+
+    /// The program saved by [`PixelShader::compile`].
+    fn compiled(renderer: &GlesRenderer) -> GlesPixelProgram {
+        renderer
+            .egl_context()
+            .user_data()
+            .get::<Self>()
+            .unwrap()
+            .program()
+            .clone()
+    }
+}
+
+/// An area drawn by the pixel shader `S`, with the uniforms it declares.
+#[derive(Debug)]
+pub struct PixelShaderElement<S> {
+    id: Id,
+    shader: GlesPixelProgram,
+    commit_counter: CommitCounter,
+    area: Rectangle<i32, Logical>,
+    opaque_regions: Vec<Rectangle<i32, Logical>>,
+    alpha: f32,
+    additional_uniforms: Vec<Uniform<'static>>,
+    kind: Kind,
+    _shader: PhantomData<S>,
+}
+
+impl<S> PixelShaderElement<S> {
+    pub fn new(
+        shader: GlesPixelProgram,
+        area: Rectangle<i32, Logical>,
+        opaque_regions: Option<Vec<Rectangle<i32, Logical>>>,
+        alpha: f32,
+        additional_uniforms: Vec<Uniform<'_>>,
+        kind: Kind,
+    ) -> Self {
+        Self {
+            id: Id::new(),
+            shader,
+            commit_counter: CommitCounter::default(),
+            area,
+            opaque_regions: opaque_regions.unwrap_or_default(),
+            alpha,
+            additional_uniforms: additional_uniforms
+                .into_iter()
+                .map(|u| u.into_owned())
+                .collect(),
+            kind,
+            _shader: PhantomData,
+        }
+    }
+}
+
+impl<S> Element for PixelShaderElement<S> {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.commit_counter
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        Rectangle::from_size(self.area.size.to_f64().to_buffer(1.0, Transform::Normal))
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.area.to_physical_precise_round(scale)
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        self.opaque_regions
+            .iter()
+            .map(|region| region.to_physical_precise_down(scale))
+            .collect()
+    }
+
+    fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+}
+
+impl<S> RenderElement<GlesRenderer> for PixelShaderElement<S> {
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        _opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        frame.render_pixel_shader_to(
+            &self.shader,
+            src,
+            dst,
+            self.area.size.to_buffer(1, Transform::Flipped180),
+            Some(damage),
+            self.alpha,
+            &self.additional_uniforms,
+        )
+    }
+
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage> {
+        // If scanout for things other than Wayland buffers is implemented, this will need to take
+        // the target GPU into account.
+        None
+    }
+}
+
+impl<'render, S> RenderElement<TtyRenderer<'render>> for PixelShaderElement<S> {
+    fn draw(
+        &self,
+        frame: &mut TtyFrame<'_, '_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), TtyRendererError<'_>> {
+        let frame = frame.as_gles_frame();
+        RenderElement::<GlesRenderer>::draw(&self, frame, src, dst, damage, opaque_regions)?;
+        Ok(())
+    }
+
+    fn underlying_storage(
+        &self,
+        _renderer: &mut TtyRenderer<'render>,
+    ) -> Option<UnderlyingStorage> {
+        // If scanout for things other than Wayland buffers is implemented, this will need to take
+        // the target GPU into account.
+        None
+    }
+}
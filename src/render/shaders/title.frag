@@ -0,0 +1,26 @@
+precision mediump float;
+
+uniform vec2 u_resolution;
+uniform vec3 u_color;
+uniform float u_active;
+
+varying vec2 v_coords;
+
+void main() {
+    vec2 coords = v_coords * u_resolution;
+
+    // 未激活的标题使用更暗的颜色
+    vec3 color = mix(u_color * 0.35, u_color, u_active);
+
+    // 相邻标题之间的分隔线
+    if (coords.x > u_resolution.x - 1.0 || coords.y > u_resolution.y - 1.0) {
+        color *= 0.5;
+    }
+
+    // 激活标题底部的高亮线
+    if (u_active > 0.5 && coords.y > u_resolution.y - 3.0) {
+        color = mix(color, vec3(1.0), 0.5);
+    }
+
+    gl_FragColor = vec4(color, 1.0);
+}
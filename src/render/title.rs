@@ -0,0 +1,26 @@
+use smithay::backend::renderer::gles::{GlesPixelProgram, UniformType};
+
+use super::shader::{PixelShader, PixelShaderElement};
+
+// colored bars only, the window titles are not drawn as text
+#[derive(Debug)]
+pub struct TitleShader(GlesPixelProgram);
+
+impl PixelShader for TitleShader {
+    const SOURCE: &'static str = include_str!("../render/shaders/title.frag");
+    const UNIFORMS: &'static [(&'static str, UniformType)] = &[
+        ("u_resolution", UniformType::_2f),
+        ("u_color", UniformType::_3f),
+        ("u_active", UniformType::_1f),
+    ];
+
+    fn new(program: GlesPixelProgram) -> Self {
+        Self(program)
+    }
+
+    fn program(&self) -> &GlesPixelProgram {
+        &self.0
+    }
+}
+
+pub type TitleRenderElement = PixelShaderElement<TitleShader>;
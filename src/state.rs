@@ -1,11 +1,11 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use anyhow::Context;
 use smithay::{
     backend::allocator::dmabuf::Dmabuf,
     delegate_data_device, delegate_dmabuf, delegate_output, delegate_seat, delegate_shm,
     delegate_viewporter,
-    desktop::PopupManager,
+    desktop::{PopupManager, layer_map_for_output},
     input::{Seat, SeatHandler, SeatState},
     reexports::{
         calloop::LoopHandle,
@@ -15,7 +15,7 @@ use smithay::{
             protocol::{wl_buffer, wl_shm, wl_surface::WlSurface},
         },
     },
-    utils::{Clock, Monotonic, Time},
+    utils::{Clock, Monotonic, SERIAL_COUNTER, Time},
     wayland::{
         buffer::BufferHandler,
         compositor::{CompositorClientState, CompositorState},
@@ -37,13 +37,14 @@ use smithay::{
 };
 
 use crate::{
-    backend::Backend,
-    config::Configs,
-    layout::tiled_tree::TiledScheme,
+    backend::{Backend, BackendType},
+    config::{Configs, watcher::ConfigWatcher},
+    ipc::{IpcServer, event_channel},
     manager::{
         cursor::CursorManager, input::InputManager, output::OutputManager, render::RenderManager,
         window::WindowManager, workspace::WorkspaceManager,
     },
+    protocol::output_management::OutputManagementState,
 };
 
 #[derive(Default)]
@@ -72,6 +73,7 @@ impl ClientData for ClientState {
 pub struct GlobalData {
     // config
     pub configs: Arc<Configs>,
+    pub config_watcher: Option<ConfigWatcher>,
 
     pub backend: Backend,
     pub state: State,
@@ -85,6 +87,9 @@ pub struct GlobalData {
     pub popups: PopupManager,
     pub render_manager: RenderManager,
 
+    // ipc
+    pub ipc_server: Option<IpcServer>,
+
     // handles
     pub loop_handle: LoopHandle<'static, GlobalData>,
     pub display_handle: DisplayHandle,
@@ -99,30 +104,35 @@ impl GlobalData {
     pub fn new(
         loop_handle: LoopHandle<'static, GlobalData>,
         display_handle: DisplayHandle,
+        backend_type: BackendType,
+        config_path: Option<PathBuf>,
     ) -> anyhow::Result<Self> {
         // load configs
-        let configs = Arc::new(Configs::new());
+        let configs = Arc::new(Configs::load(config_path));
 
         // init backend
-        let mut backend = Backend::new(&loop_handle).context("Failed to create backend")?;
+        let mut backend =
+            Backend::new(backend_type, &loop_handle).context("Failed to create backend")?;
 
         // initial global state
         let mut nuonuo_state =
             State::new(&display_handle).context("Failed to create global state")?;
 
         // initial managers
-        let mut output_manager = OutputManager::new(&display_handle, configs.clone());
-        let mut workspace_manager = WorkspaceManager::new(configs.conf_workspaces.clone());
-        let window_manager = WindowManager::new();
-        let cursor_manager = CursorManager::new("default", 24);
-        let input_manager = InputManager::new(
-            backend.seat_name(),
-            &display_handle,
-            "src/config/keybindings.conf",
-        )
-        .context("Failed to create input_manager")?;
+        let ipc_events = event_channel(&loop_handle).context("Failed to create ipc events")?;
+        let mut output_manager =
+            OutputManager::new(&display_handle, configs.clone(), ipc_events.clone());
+        let mut workspace_manager =
+            WorkspaceManager::new(configs.conf_workspaces.clone(), ipc_events.clone());
+        let window_manager = WindowManager::new(ipc_events);
+        let cursor_manager = CursorManager::new(
+            &configs.conf_general.cursor_theme,
+            configs.conf_general.cursor_size,
+        );
+        let input_manager = InputManager::new(backend.seat_name(), &display_handle, &configs)
+            .context("Failed to create input_manager")?;
         let popups = PopupManager::default();
-        let render_manager = RenderManager::new();
+        let render_manager = RenderManager::new(configs.clone());
 
         // initial backend
         backend.init(
@@ -133,14 +143,19 @@ impl GlobalData {
             &mut nuonuo_state,
         );
 
-        // TODO: just easy for test workspace exchange
-        let output = output_manager.current_output();
-        let output_geo = output_manager
-            .output_geometry(output)
-            .context("workspace add test error")?;
-
-        workspace_manager.add_workspace(output, output_geo, Some(TiledScheme::Default), true);
-        workspace_manager.add_workspace(output, output_geo, Some(TiledScheme::Spiral), false);
+        // one workspace per output, further ones are created on demand
+        let outputs = output_manager.outputs_geometry();
+        if outputs.is_empty() {
+            anyhow::bail!("No output available");
+        }
+        // the tiling area is what the layer surfaces leave
+        for (output, _) in &outputs {
+            layer_map_for_output(output).arrange();
+        }
+        workspace_manager.sync_outputs(&outputs, &loop_handle);
+        nuonuo_state
+            .output_management_state
+            .update(output_manager.outputs(), output_manager.disabled_outputs());
 
         let start_time = std::time::Instant::now();
         let clock = Clock::new();
@@ -158,16 +173,97 @@ impl GlobalData {
             popups,
             render_manager,
 
+            ipc_server: None,
+
             loop_handle,
             display_handle,
 
             configs,
+            config_watcher: None,
 
             start_time,
             clock,
             next_frame_target,
         })
     }
+
+    /// Follow outputs being added, removed or reconfigured.
+    pub fn outputs_changed(&mut self) {
+        let outputs = self.output_manager.outputs_geometry();
+        for (output, _) in &outputs {
+            layer_map_for_output(output).arrange();
+        }
+        self.workspace_manager
+            .sync_outputs(&outputs, &self.loop_handle);
+        self.state.output_management_state.update(
+            self.output_manager.outputs(),
+            self.output_manager.disabled_outputs(),
+        );
+
+        let serial = SERIAL_COUNTER.next_serial();
+
+        // the pointer may have been left on an unplugged output
+        let lost = self.input_manager.get_pointer().is_some_and(|p| {
+            self.output_manager
+                .output_at(p.current_location())
+                .is_none()
+        });
+        let output_geo = self
+            .output_manager
+            .output_geometry(self.output_manager.current_output());
+        if let (true, Some(rec)) = (lost, output_geo) {
+            let center = rec.loc + rec.size.downscale(2).to_point();
+            self.warp_pointer(center.to_f64(), serial);
+        }
+
+        let surface = self
+            .workspace_manager
+            .current_workspace()
+            .focus()
+            .map(|focus| focus.toplevel().unwrap().wl_surface().clone());
+        self.set_keyboard_focus(surface, serial);
+    }
+
+    // on error the running config stays untouched
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        let configs =
+            Configs::new(self.configs.config_path.clone()).context("Failed to reload configs")?;
+
+        self.input_manager.reload_keybindings(&configs);
+        configs.apply_env();
+        self.workspace_manager
+            .set_configs(configs.conf_workspaces.clone(), &self.loop_handle);
+
+        let configs = Arc::new(configs);
+        self.output_manager.configs = configs.clone();
+        self.render_manager.configs = configs.clone();
+        self.configs = configs;
+
+        self.backend.apply_output_configs(&mut self.output_manager);
+        self.outputs_changed();
+
+        // sources may have changed
+        self.watch_configs();
+
+        info!("Reloaded configs");
+        Ok(())
+    }
+
+    pub fn watch_configs(&mut self) {
+        if let Some(config_watcher) = self.config_watcher.take() {
+            config_watcher.remove(&self.loop_handle);
+        }
+
+        // nothing to watch with the embedded defaults
+        if self.configs.files.iter().all(|file| file.path.is_none()) {
+            return;
+        }
+
+        match ConfigWatcher::new(&self.loop_handle, &self.configs) {
+            Ok(config_watcher) => self.config_watcher = Some(config_watcher),
+            Err(err) => warn!("Failed to watch configs: {:?}", err),
+        }
+    }
 }
 
 pub struct State {
@@ -183,6 +279,7 @@ pub struct State {
     #[allow(dead_code)]
     pub viewporter_state: ViewporterState,
     pub foreign_toplevel_state: ForeignToplevelListState,
+    pub output_management_state: OutputManagementState,
 }
 
 impl State {
@@ -200,6 +297,7 @@ impl State {
         let layer_shell_state = WlrLayerShellState::new::<GlobalData>(display_handle);
         let viewporter_state = ViewporterState::new::<GlobalData>(display_handle);
         let foreign_toplevel_state = ForeignToplevelListState::new::<GlobalData>(display_handle);
+        let output_management_state = OutputManagementState::new(display_handle);
 
         Ok(State {
             compositor_state,
@@ -211,6 +309,7 @@ impl State {
             layer_shell_state,
             viewporter_state,
             foreign_toplevel_state,
+            output_management_state,
         })
     }
 }